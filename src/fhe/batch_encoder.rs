@@ -0,0 +1,228 @@
+//! BFV batch encoder
+//! Packs integer vectors into the N plaintext slots (SIMD batching)
+//!
+//! The slots form a 2 x (N/2) matrix. Slot `i` of row 0 holds the
+//! evaluation at ψ^(5^i), slot `i` of row 1 the evaluation at ψ^(-5^i),
+//! so the automorphism X -> X^(5^k) rotates both rows left by `k` and
+//! X -> X^(2N-1) swaps the rows.
+
+use super::bfv::{BFVContext, Plaintext};
+use crate::modular;
+use crate::ntt::NttTables;
+
+pub struct BatchEncoder {
+    slots: usize,
+    plain_modulus: u64,
+    tables: NttTables,
+    index_map: Vec<usize>, // slot -> position in the bit-reversed NTT vector
+}
+
+impl BatchEncoder {
+    /// Fails if the plain modulus is not a prime with t ≡ 1 mod 2N
+    pub fn new(context: &BFVContext) -> Result<Self, String> {
+        let n = context.params.poly_degree;
+        let t = context.params.plain_modulus;
+        let m = 2 * n as u64;
+
+        if !n.is_power_of_two() || n < 2 {
+            return Err(format!("poly_degree {} must be a power of two >= 2", n));
+        }
+        if !modular::is_prime(t) || !(t - 1).is_multiple_of(m) {
+            return Err(format!(
+                "plain_modulus {} does not support batching: it must be a prime with t ≡ 1 mod 2N = {}",
+                t, m
+            ));
+        }
+        let tables = NttTables::new(n, t)
+            .ok_or_else(|| format!("plain_modulus {} has no primitive {}-th root of unity", t, m))?;
+
+        Ok(BatchEncoder {
+            slots: n,
+            plain_modulus: t,
+            tables,
+            index_map: Self::slot_index_map(n),
+        })
+    }
+
+    /// Total number of slots (N)
+    pub fn slot_count(&self) -> usize {
+        self.slots
+    }
+
+    /// Number of slots per matrix row (N/2)
+    pub fn row_size(&self) -> usize {
+        self.slots / 2
+    }
+
+    /// Encodes up to N values in [0, t); missing slots are zero
+    pub fn encode(&self, values: &[u64]) -> Result<Plaintext, String> {
+        if values.len() > self.slots {
+            return Err(format!("{} values do not fit into {} slots", values.len(), self.slots));
+        }
+
+        let mut buffer = vec![0u64; self.slots];
+        for (i, &v) in values.iter().enumerate() {
+            if v >= self.plain_modulus {
+                return Err(format!(
+                    "value {} at slot {} is not reduced modulo {}",
+                    v, i, self.plain_modulus
+                ));
+            }
+            buffer[self.index_map[i]] = v;
+        }
+
+        self.tables.inverse(&mut buffer);
+        Ok(Plaintext { coeffs: buffer })
+    }
+
+    /// Encodes signed values in [-⌊t/2⌋, ⌊t/2⌋]
+    pub fn encode_signed(&self, values: &[i64]) -> Result<Plaintext, String> {
+        let t = self.plain_modulus as i64;
+        let half = t / 2;
+
+        let mut reduced = Vec::with_capacity(values.len());
+        for (i, &v) in values.iter().enumerate() {
+            if v > half || v < -half {
+                return Err(format!("value {} at slot {} is outside [-{}, {}]", v, i, half, half));
+            }
+            reduced.push(if v < 0 { (v + t) as u64 } else { v as u64 });
+        }
+
+        self.encode(&reduced)
+    }
+
+    /// Decodes all N slots as values in [0, t)
+    pub fn decode(&self, plain: &Plaintext) -> Vec<u64> {
+        let mut buffer = vec![0u64; self.slots];
+        for (dst, &c) in buffer.iter_mut().zip(plain.coeffs.iter()) {
+            *dst = c % self.plain_modulus;
+        }
+
+        self.tables.forward(&mut buffer);
+        self.index_map.iter().map(|&idx| buffer[idx]).collect()
+    }
+
+    /// Decodes all N slots as centered values in (-t/2, t/2]
+    pub fn decode_signed(&self, plain: &Plaintext) -> Vec<i64> {
+        let t = self.plain_modulus;
+        self.decode(plain)
+            .into_iter()
+            .map(|v| if v > t / 2 { v as i64 - t as i64 } else { v as i64 })
+            .collect()
+    }
+
+    fn slot_index_map(n: usize) -> Vec<usize> {
+        let m = 2 * n;
+        let row = n / 2;
+        let log_n = n.trailing_zeros();
+        let bitrev = |x: usize| x.reverse_bits() >> (usize::BITS - log_n);
+
+        let mut map = vec![0usize; n];
+        let mut pos = 1usize;
+        for i in 0..row {
+            // ψ^pos sits at NTT index bitrev((pos - 1) / 2)
+            map[i] = bitrev((pos - 1) / 2);
+            map[row + i] = bitrev((m - pos - 1) / 2);
+            pos = pos * 5 % m;
+        }
+        map
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encoder() -> BatchEncoder {
+        BatchEncoder::new(&BFVContext::new()).unwrap()
+    }
+
+    /// Product in Z_t[X]/(X^N + 1)
+    fn negacyclic_mul(a: &Plaintext, b: &Plaintext, t: u64) -> Plaintext {
+        let n = a.coeffs.len();
+        let mut c = vec![0u64; n];
+        for (i, &x) in a.coeffs.iter().enumerate() {
+            for (j, &y) in b.coeffs.iter().enumerate() {
+                let p = modular::mod_mul(x, y, t);
+                let k = (i + j) % n;
+                c[k] = if i + j < n { modular::mod_add(c[k], p, t) } else { modular::mod_sub(c[k], p, t) };
+            }
+        }
+        Plaintext { coeffs: c }
+    }
+
+    /// p(X) -> p(X^g) in Z_t[X]/(X^N + 1)
+    fn automorphism(a: &Plaintext, g: usize, t: u64) -> Plaintext {
+        let n = a.coeffs.len();
+        let mut c = vec![0u64; n];
+        for (i, &v) in a.coeffs.iter().enumerate() {
+            let e = i * g % (2 * n);
+            if e < n { c[e] = modular::mod_add(c[e], v, t) } else { c[e - n] = modular::mod_sub(c[e - n], v, t) }
+        }
+        Plaintext { coeffs: c }
+    }
+
+    #[test]
+    fn encode_decode_round_trip() {
+        let enc = encoder();
+        let values: Vec<u64> = (0..enc.slot_count() as u64).map(|i| (i * 31 + 5) % 65537).collect();
+        assert_eq!(enc.decode(&enc.encode(&values).unwrap()), values);
+
+        let short = [1u64, 2, 3];
+        let decoded = enc.decode(&enc.encode(&short).unwrap());
+        assert_eq!(&decoded[..3], &short);
+        assert!(decoded[3..].iter().all(|&v| v == 0));
+    }
+
+    #[test]
+    fn signed_range() {
+        let enc = encoder();
+        let values: Vec<i64> = (0..enc.slot_count() as i64).map(|i| i * 61 - 32768).collect();
+        assert_eq!(enc.decode_signed(&enc.encode_signed(&values).unwrap()), values);
+        assert!(enc.encode_signed(&[32768]).is_ok());
+        assert!(enc.encode_signed(&[-32768]).is_ok());
+        assert!(enc.encode_signed(&[32769]).is_err());
+        assert!(enc.encode(&[65537]).is_err());
+        assert!(enc.encode(&vec![0; enc.slot_count() + 1]).is_err());
+    }
+
+    #[test]
+    fn polynomial_product_multiplies_slots() {
+        let enc = encoder();
+        let t = 65537;
+        let a: Vec<u64> = (0..enc.slot_count() as u64).map(|i| (i * 977 + 13) % t).collect();
+        let b: Vec<u64> = (0..enc.slot_count() as u64).map(|i| (i * i + 7) % t).collect();
+        let product = enc.decode(&negacyclic_mul(&enc.encode(&a).unwrap(), &enc.encode(&b).unwrap(), t));
+        for i in 0..a.len() {
+            assert_eq!(product[i], modular::mod_mul(a[i], b[i], t));
+        }
+    }
+
+    #[test]
+    fn automorphisms_rotate_rows_and_swap_them() {
+        let enc = encoder();
+        let t = 65537;
+        let n = enc.slot_count();
+        let row = enc.row_size();
+        let values: Vec<u64> = (0..n as u64).collect();
+        let plain = enc.encode(&values).unwrap();
+
+        let rotated = enc.decode(&automorphism(&plain, 5, t));
+        for i in 0..row {
+            assert_eq!(rotated[i], values[(i + 1) % row]);
+            assert_eq!(rotated[row + i], values[row + (i + 1) % row]);
+        }
+        let swapped = enc.decode(&automorphism(&plain, 2 * n - 1, t));
+        assert_eq!(&swapped[..row], &values[row..]);
+        assert_eq!(&swapped[row..], &values[..row]);
+    }
+
+    #[test]
+    fn rejects_moduli_without_batching() {
+        let mut context = BFVContext::new();
+        context.params.plain_modulus = 65539; // prime, but 65538 is not a multiple of 2048
+        assert!(BatchEncoder::new(&context).is_err());
+        context.params.plain_modulus = 65537 * 3;
+        assert!(BatchEncoder::new(&context).is_err());
+    }
+}
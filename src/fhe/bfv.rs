@@ -1,28 +1,873 @@
 //! BFV scheme operations
 //! Brakerski-Fan-Vercauteren scheme implementation
+//!
+//! Ciphertexts live in an RNS modulus chain q_0, ..., q_L. A ciphertext at
+//! level l uses the prefix Q_l = q_0···q_l; modulus switching drops q_l.
 
 use super::super::modular;
+use super::keyswitch::{KeySwitchBasis, KeySwitchKey};
+use super::poly::{self, RnsPoly};
+use crate::ntt::NttTables;
+use crate::rns::RnsBasis;
+use num_bigint::{BigInt, BigUint, Sign};
+use num_traits::{Signed, ToPrimitive};
+use std::collections::HashMap;
 
 // === NEUE STRUKTUREN ===
+
+/// Scheme run on a context; BFV and BGV share the chain, keys and encoders
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum SchemeType {
+    /// Message scaled by Δ = floor(Q/t) in the high bits (scale invariant)
+    #[default]
+    Bfv,
+    /// Message in the low bits, noise a multiple of t (see `fhe::bgv`)
+    Bgv,
+}
+
+#[derive(Clone, Debug)]
 pub struct BFVParameters {
-    pub cipher_modulus: u64,
+    /// Modulus chain q_0, ..., q_L (NTT-friendly primes, dropped from the end)
+    pub cipher_moduli: Vec<u64>,
+    /// Special prime P for key switching (relinearization, rotations)
+    pub special_modulus: u64,
     pub plain_modulus: u64,
     pub poly_degree: usize,
+    pub scheme: SchemeType,
+}
+
+impl BFVParameters {
+    /// Parameters generated from prime bit sizes: the chain q_0..q_L
+    /// followed by the special prime, which should be the largest
+    pub fn new(poly_degree: usize, plain_modulus: u64, bit_sizes: &[u32]) -> Result<Self, String> {
+        if bit_sizes.len() < 2 {
+            return Err("need at least one chain prime and the special prime".to_string());
+        }
+        let mut cipher_moduli = modular::generate_ntt_primes(poly_degree, bit_sizes)?;
+        let special_modulus = cipher_moduli.pop().unwrap();
+        Ok(Self { cipher_moduli, special_modulus, plain_modulus, poly_degree, scheme: SchemeType::Bfv })
+    }
+
+    /// Same chain and plaintext space for another scheme
+    pub fn with_scheme(mut self, scheme: SchemeType) -> Self {
+        self.scheme = scheme;
+        self
+    }
 }
 
+#[derive(Clone)]
 pub struct BFVContext {
     pub params: BFVParameters,
+    pub ntt: Vec<NttTables>,          // one per chain modulus
+    pub bases: Vec<RnsBasis>,         // bases[l] covers q_0..q_l
+    pub keyswitch: KeySwitchBasis,    // chain plus special prime
+    delta: Vec<Vec<u64>>,             // delta[l][i] = floor(Q_l / t) mod q_i
+    q_mod_t: Vec<u64>,                // Q_l mod t
+    pub(crate) inv_last: Vec<Vec<u64>>, // inv_last[l][j] = q_l^-1 mod q_j
+    ext_ntt: Vec<NttTables>,          // auxiliary basis B for the tensor product
+    ext_basis: RnsBasis,              // B alone
+    ext_bases: Vec<RnsBasis>,         // Q_l ∪ B per level
 }
 
 impl BFVContext {
     pub fn new() -> Self {
-        Self {
-            params: BFVParameters {
-                cipher_modulus: 0x7fffffffe0001,
-                plain_modulus: 65537,
-                poly_degree: 1024,
+        Self::with_params(BFVParameters {
+            cipher_moduli: vec![0x7fffffffe0001, 0x3ffffffffc001, 0x3ffffffffa801],
+            special_modulus: 0xfffffffffffc001,
+            plain_modulus: 65537,
+            poly_degree: 1024,
+            scheme: SchemeType::Bfv,
+        })
+        .expect("default BFV parameters are valid")
+    }
+
+    /// Validates the parameters and precomputes NTT and CRT tables per level
+    pub fn with_params(params: BFVParameters) -> Result<Self, String> {
+        let n = params.poly_degree;
+        let t = params.plain_modulus;
+
+        if params.cipher_moduli.is_empty() {
+            return Err("cipher_moduli must contain at least one prime".to_string());
+        }
+        let mut ntt = Vec::with_capacity(params.cipher_moduli.len());
+        for (i, &q) in params.cipher_moduli.iter().enumerate() {
+            if !modular::is_prime(q) {
+                return Err(format!("cipher modulus {} is not prime", q));
             }
+            if params.cipher_moduli[..i].contains(&q) {
+                return Err(format!("cipher modulus {} appears twice in the chain", q));
+            }
+            if t >= q {
+                return Err(format!("plain_modulus {} must be smaller than every cipher modulus", t));
+            }
+            ntt.push(NttTables::new(n, q).ok_or_else(|| {
+                format!("cipher modulus {} does not support the NTT for poly_degree {}", q, n)
+            })?);
+        }
+        if t < 2 {
+            return Err(format!("plain_modulus {} must be at least 2", t));
+        }
+        let p = params.special_modulus;
+        if !modular::is_prime(p) || params.cipher_moduli.contains(&p) {
+            return Err(format!("special_modulus {} must be a prime outside the chain", p));
         }
+        let special = NttTables::new(n, p).ok_or_else(|| {
+            format!("special_modulus {} does not support the NTT for poly_degree {}", p, n)
+        })?;
+        let bgv_plain = (params.scheme == SchemeType::Bgv).then_some(t);
+        let keyswitch = KeySwitchBasis::new(&ntt, &special, bgv_plain);
+
+        // B > N·Q_L keeps the exact tensor product representable in Q_l ∪ B
+        let levels = params.cipher_moduli.len();
+        let ext_moduli: Vec<u64> = modular::generate_ntt_primes(n, &vec![60; 2 * levels + 3])?
+            .into_iter()
+            .filter(|q| *q != p && !params.cipher_moduli.contains(q))
+            .take(levels + 1)
+            .collect();
+        let ext_ntt: Vec<NttTables> = ext_moduli.iter()
+            .map(|&q| NttTables::new(n, q).unwrap())
+            .collect();
+
+        let moduli = &params.cipher_moduli;
+        let mut bases = Vec::with_capacity(moduli.len());
+        let mut delta = Vec::with_capacity(moduli.len());
+        let mut q_mod_t = Vec::with_capacity(moduli.len());
+        let mut inv_last = Vec::with_capacity(moduli.len());
+        for l in 0..moduli.len() {
+            let basis = RnsBasis::new(&moduli[..=l]);
+            let d: BigUint = &basis.product / t;
+            delta.push(moduli[..=l].iter().map(|&q| (&d % q).to_u64().unwrap()).collect());
+            q_mod_t.push((&basis.product % t).to_u64().unwrap());
+            inv_last.push(moduli[..l].iter().map(|&q| modular::mod_inv(moduli[l] % q, q)).collect());
+            bases.push(basis);
+        }
+
+        let ext_bases = (0..levels)
+            .map(|l| RnsBasis::new(&[&moduli[..=l], &ext_moduli[..]].concat()))
+            .collect();
+        let ext_basis = RnsBasis::new(&ext_moduli);
+
+        Ok(Self {
+            params, ntt, bases, keyswitch, delta, q_mod_t, inv_last,
+            ext_ntt, ext_basis, ext_bases,
+        })
+    }
+
+    /// Highest level L of the modulus chain
+    pub fn max_level(&self) -> usize {
+        self.params.cipher_moduli.len() - 1
+    }
+
+    /// NTT tables of q_0..q_level
+    pub fn tables(&self, level: usize) -> &[NttTables] {
+        &self.ntt[..=level]
+    }
+
+    /// Δ·m rounded: floor((Q·m + t/2) / t) = Δ·m + floor(([Q]_t·m + t/2) / t)
+    fn scale_plain(&self, plain: &Plaintext, level: usize) -> RnsPoly {
+        let t = self.params.plain_modulus;
+        let r = self.q_mod_t[level] as u128;
+        let limbs = self.tables(level).iter().zip(&self.delta[level])
+            .map(|(tab, &d)| {
+                let q = tab.modulus;
+                plain.coeffs.iter()
+                    .map(|&m| {
+                        let m = m % t;
+                        let carry = ((r * m as u128 + t as u128 / 2) / t as u128) as u64;
+                        modular::mod_add(modular::mod_mul(d, m, q), carry % q, q)
+                    })
+                    .collect()
+            })
+            .collect();
+        RnsPoly { limbs }
+    }
+
+    /// Lifts plaintext coefficients to centered representatives mod each q_i
+    pub(crate) fn lift_plain(&self, plain: &Plaintext, level: usize) -> RnsPoly {
+        let limbs = self.tables(level).iter()
+            .map(|tab| plain.coeffs.iter().map(|&m| self.lift_scalar(m, tab.modulus)).collect())
+            .collect();
+        RnsPoly { limbs }
+    }
+
+    pub(crate) fn lift_scalar(&self, m: u64, q: u64) -> u64 {
+        let t = self.params.plain_modulus;
+        let m = m % t;
+        if m > t / 2 { q - (t - m) } else { m }
+    }
+
+    /// Heuristic log2 of ||t·e||_∞ for a fresh encryption
+    ///
+    /// The noise e·u + e1 + e2·s has standard deviation about
+    /// σ·sqrt(4N/3 + 1); six standard deviations bound the coefficients.
+    pub fn fresh_noise_bits(&self) -> f64 {
+        let n = self.params.poly_degree as f64;
+        let t = self.params.plain_modulus as f64;
+        (t * 6.0 * poly::ERROR_STD_DEV * (4.0 * n / 3.0 + 1.0).sqrt()).log2()
+    }
+
+    /// Heuristic log2 of the t·(rounding error) added by a modulus switch
+    pub(crate) fn mod_switch_noise_bits(&self) -> f64 {
+        let n = self.params.poly_degree as f64;
+        let t = self.params.plain_modulus as f64;
+        (t * (0.5 + 6.0 * (n / 18.0).sqrt())).log2()
+    }
+
+    /// Heuristic log2 of the t·(noise) added by one key switch at `level`
+    pub(crate) fn key_switch_noise_bits(&self, level: usize) -> f64 {
+        let n = self.params.poly_degree as f64;
+        let t = self.params.plain_modulus as f64;
+        let q_max = self.params.cipher_moduli[..=level].iter().copied().max().unwrap() as f64;
+        let p = self.params.special_modulus as f64;
+        let key_noise = 6.0 * poly::ERROR_STD_DEV * n.sqrt() * (level + 1) as f64 * q_max / (2.0 * p);
+        (t * (key_noise + 0.5 + 6.0 * (n / 18.0).sqrt())).log2()
+    }
+
+    /// Key-less noise budget in bits, from the estimate carried by `ct`
+    pub fn estimated_noise_budget(&self, ct: &Ciphertext) -> u32 {
+        let q_bits = self.bases[ct.level()].bits();
+        (q_bits - ct.noise_bits - 1.0).max(0.0) as u32
+    }
+}
+
+/// log2(2^a + 2^b) without overflow
+pub(crate) fn log2_add(a: f64, b: f64) -> f64 {
+    let (hi, lo) = if a > b { (a, b) } else { (b, a) };
+    hi + (1.0 + (lo - hi).exp2()).log2()
+}
+
+impl Default for BFVContext {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// BFV plaintext: polynomial with coefficients modulo plain_modulus
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Plaintext {
+    pub coeffs: Vec<u64>,
+}
+
+impl Plaintext {
+    /// All-zero plaintext of the given degree
+    pub fn zero(poly_degree: usize) -> Self {
+        Self { coeffs: vec![0u64; poly_degree] }
+    }
+}
+
+/// Plaintext lifted to every chain modulus and cached in NTT form for
+/// repeated `mul_plain`; usable at any ciphertext level
+#[derive(Clone, Debug)]
+pub struct PlaintextNtt {
+    values: RnsPoly,
+    shoup: Vec<Vec<u64>>,   // Shoup constants for the fixed multiplicand
+    noise_growth_bits: f64, // log2 of the expected noise growth factor
+}
+
+/// BFV ciphertext (c0, c1, ...) at a level of the modulus chain
+#[derive(Clone, Debug)]
+pub struct Ciphertext {
+    pub polys: Vec<RnsPoly>,
+    /// Heuristic log2 of ||t·e||_∞, maintained by the evaluator
+    pub noise_bits: f64,
+    /// BGV only: decryption yields correction_factor·m mod t (1 for BFV)
+    pub correction_factor: u64,
+}
+
+impl Ciphertext {
+    /// Number of polynomials (2 for fresh ciphertexts)
+    pub fn size(&self) -> usize {
+        self.polys.len()
+    }
+
+    /// Level l in the chain: the ciphertext lives modulo q_0···q_l
+    pub fn level(&self) -> usize {
+        self.polys[0].level()
+    }
+
+    /// Bytes needed to store the residues (shrinks with every modulus switch)
+    pub fn serialized_size(&self) -> usize {
+        self.polys.iter()
+            .map(|p| p.limbs.iter().map(|l| l.len() * 8).sum::<usize>())
+            .sum()
+    }
+}
+
+// === SCHLÜSSEL ===
+
+/// Ternary secret key, stored modulo every chain prime and the special prime
+#[derive(Clone)]
+pub struct SecretKey {
+    pub s: RnsPoly,
+}
+
+/// Key switching key from s² to s
+#[derive(Clone)]
+pub struct RelinKeys {
+    pub key: KeySwitchKey,
+}
+
+/// Key switching keys from s(X^g) to s, by Galois element g
+#[derive(Clone)]
+pub struct GaloisKeys {
+    pub keys: HashMap<u64, KeySwitchKey>,
+}
+
+impl GaloisKeys {
+    pub fn get(&self, galois_elt: u64) -> Result<&KeySwitchKey, String> {
+        self.keys.get(&galois_elt)
+            .ok_or_else(|| format!("no Galois key for element {}", galois_elt))
+    }
+}
+
+/// Public key (b, a) = (-(a·s + e), a) at the top level (e scaled by t for BGV)
+#[derive(Clone)]
+pub struct PublicKey {
+    pub b: RnsPoly,
+    pub a: RnsPoly,
+}
+
+pub struct KeyGenerator {
+    context: BFVContext,
+    secret_key: SecretKey,
+}
+
+impl KeyGenerator {
+    /// Samples a fresh ternary secret key
+    pub fn new(context: &BFVContext) -> Self {
+        let n = context.params.poly_degree;
+        let s = RnsPoly::from_signed(&poly::sample_ternary(n), &context.keyswitch.tables);
+        Self { context: context.clone(), secret_key: SecretKey { s } }
+    }
+
+    pub fn secret_key(&self) -> SecretKey {
+        self.secret_key.clone()
+    }
+
+    pub fn public_key(&self) -> PublicKey {
+        let ctx = &self.context;
+        let n = ctx.params.poly_degree;
+
+        let s = self.secret_key.s.truncated(ctx.ntt.len());
+        let a = RnsPoly::uniform(n, &ctx.ntt);
+        let mut e = RnsPoly::from_signed(&poly::sample_gaussian(n, poly::ERROR_STD_DEV), &ctx.ntt);
+        if ctx.params.scheme == SchemeType::Bgv {
+            e = e.mul_scalars(&vec![ctx.params.plain_modulus; ctx.ntt.len()], &ctx.ntt);
+        }
+        let b = a.mul(&s, &ctx.ntt).add(&e, &ctx.ntt).negate(&ctx.ntt);
+
+        PublicKey { b, a }
+    }
+
+    /// Relinearization keys for size-3 ciphertexts
+    pub fn relin_keys(&self) -> RelinKeys {
+        let tables = &self.context.keyswitch.tables;
+        let s = &self.secret_key.s;
+        let s2 = s.mul(s, tables);
+        RelinKeys { key: self.context.keyswitch.generate_key(s, &s2) }
+    }
+
+    /// Galois keys for row rotations by each of `steps`
+    pub fn galois_keys(&self, steps: &[i64]) -> GaloisKeys {
+        let n = self.context.params.poly_degree;
+        self.galois_keys_for_elements(&poly::galois_elements_for_steps(steps, n))
+    }
+
+    /// Galois keys for arbitrary odd elements g (e.g. 2N - 1 for the row swap)
+    pub fn galois_keys_for_elements(&self, elements: &[u64]) -> GaloisKeys {
+        let tables = &self.context.keyswitch.tables;
+        let s = &self.secret_key.s;
+        let keys = elements.iter()
+            .map(|&g| (g, self.context.keyswitch.generate_key(s, &s.automorphism(g, tables))))
+            .collect();
+        GaloisKeys { keys }
+    }
+}
+
+// === VERSCHLÜSSELUNG ===
+
+pub struct Encryptor {
+    pub(crate) context: BFVContext,
+    pub(crate) public_key: PublicKey,
+}
+
+impl Encryptor {
+    pub fn new(context: &BFVContext, public_key: &PublicKey) -> Self {
+        Self { context: context.clone(), public_key: public_key.clone() }
+    }
+
+    /// ct = (b·u + e1 + Δ·m, a·u + e2) at the top level
+    pub fn encrypt(&self, plain: &Plaintext) -> Ciphertext {
+        let ctx = &self.context;
+        let n = ctx.params.poly_degree;
+        let tables = &ctx.ntt;
+
+        let u = RnsPoly::from_signed(&poly::sample_ternary(n), tables);
+        let e1 = RnsPoly::from_signed(&poly::sample_gaussian(n, poly::ERROR_STD_DEV), tables);
+        let e2 = RnsPoly::from_signed(&poly::sample_gaussian(n, poly::ERROR_STD_DEV), tables);
+
+        let c0 = self.public_key.b.mul(&u, tables)
+            .add(&e1, tables)
+            .add(&ctx.scale_plain(plain, ctx.max_level()), tables);
+        let c1 = self.public_key.a.mul(&u, tables).add(&e2, tables);
+
+        Ciphertext { polys: vec![c0, c1], noise_bits: ctx.fresh_noise_bits(), correction_factor: 1 }
+    }
+}
+
+pub struct Decryptor {
+    context: BFVContext,
+    secret_key: SecretKey,
+}
+
+impl Decryptor {
+    pub fn new(context: &BFVContext, secret_key: &SecretKey) -> Self {
+        Self { context: context.clone(), secret_key: secret_key.clone() }
+    }
+
+    /// Remaining invariant noise budget in bits, measured with the secret key
+    ///
+    /// [t·(c0 + c1·s + ...)]_Q cancels the message and leaves t·e; the
+    /// budget is log2(Q) - log2(||t·e||_∞) - 1 and decryption is correct
+    /// while it stays positive.
+    pub fn invariant_noise_budget(&self, ct: &Ciphertext) -> u32 {
+        let level = ct.level();
+        let basis = &self.context.bases[level];
+        let t = self.context.params.plain_modulus;
+
+        let scaled = self.dot_product_with_secret(ct)
+            .mul_scalars(&vec![t; level + 1], self.context.tables(level));
+
+        let n = self.context.params.poly_degree;
+        let mut residues = vec![0u64; level + 1];
+        let mut norm_bits = 0;
+        for k in 0..n {
+            for (r, limb) in residues.iter_mut().zip(&scaled.limbs) {
+                *r = limb[k];
+            }
+            norm_bits = norm_bits.max(basis.compose_centered(&residues).bits());
+        }
+
+        (basis.product.bits()).saturating_sub(norm_bits + 1) as u32
+    }
+
+    /// m = round(t/Q · [c0 + c1·s + c2·s² + ...]_Q) mod t
+    pub fn decrypt(&self, ct: &Ciphertext) -> Plaintext {
+        let level = ct.level();
+        let basis = &self.context.bases[level];
+        let t = self.context.params.plain_modulus;
+        let half_q: BigUint = &basis.product >> 1;
+
+        let x = self.dot_product_with_secret(ct);
+        let mut residues = vec![0u64; level + 1];
+        let coeffs = (0..self.context.params.poly_degree)
+            .map(|k| {
+                for (r, limb) in residues.iter_mut().zip(&x.limbs) {
+                    *r = limb[k];
+                }
+                let m = (basis.compose(&residues) * t + &half_q) / &basis.product;
+                (m % t).to_u64().unwrap()
+            })
+            .collect();
+
+        Plaintext { coeffs }
+    }
+
+    pub(crate) fn dot_product_with_secret(&self, ct: &Ciphertext) -> RnsPoly {
+        let level = ct.level();
+        let tables = self.context.tables(level);
+        let s = self.secret_key.s.truncated(level + 1);
+
+        // Horner: ((c_k·s + c_{k-1})·s + ...)·s + c0
+        let mut acc = ct.polys[ct.size() - 1].clone();
+        for c in ct.polys[..ct.size() - 1].iter().rev() {
+            acc = acc.mul(&s, tables).add(c, tables);
+        }
+        acc
+    }
+}
+
+// === EVALUATOR ===
+
+pub struct Evaluator {
+    context: BFVContext,
+}
+
+impl Evaluator {
+    pub fn new(context: &BFVContext) -> Self {
+        Self { context: context.clone() }
+    }
+
+    // --- Modulus switching ---
+
+    /// Drops q_l: c' = round(c / q_l) mod Q_{l-1}
+    pub fn mod_switch_to_next(&self, ct: &Ciphertext) -> Result<Ciphertext, String> {
+        let mut result = ct.clone();
+        self.mod_switch_to_next_inplace(&mut result)?;
+        Ok(result)
+    }
+
+    pub fn mod_switch_to_next_inplace(&self, ct: &mut Ciphertext) -> Result<(), String> {
+        let level = ct.level();
+        if level == 0 {
+            return Err("ciphertext is already at level 0".to_string());
+        }
+
+        let q_last = self.context.params.cipher_moduli[level];
+        let inv_last = &self.context.inv_last[level];
+        for c in ct.polys.iter_mut() {
+            let last = c.limbs.pop().unwrap();
+            for (j, limb) in c.limbs.iter_mut().enumerate() {
+                let q = self.context.params.cipher_moduli[j];
+                for (x, &r) in limb.iter_mut().zip(&last) {
+                    // Centered remainder makes the division round to nearest
+                    let r_mod_q = if r > q_last / 2 {
+                        modular::mod_sub(0, (q_last - r) % q, q)
+                    } else {
+                        r % q
+                    };
+                    *x = modular::mod_mul(modular::mod_sub(*x, r_mod_q, q), inv_last[j], q);
+                }
+            }
+        }
+
+        ct.noise_bits = log2_add(ct.noise_bits - (q_last as f64).log2(), self.context.mod_switch_noise_bits());
+        Ok(())
+    }
+
+    /// Switches down to `level` (no-op if already there)
+    pub fn mod_switch_to(&self, ct: &Ciphertext, level: usize) -> Result<Ciphertext, String> {
+        let mut result = ct.clone();
+        self.mod_switch_to_inplace(&mut result, level)?;
+        Ok(result)
+    }
+
+    pub fn mod_switch_to_inplace(&self, ct: &mut Ciphertext, level: usize) -> Result<(), String> {
+        if level > ct.level() {
+            return Err(format!(
+                "cannot switch a level-{} ciphertext up to level {}",
+                ct.level(), level
+            ));
+        }
+        while ct.level() > level {
+            self.mod_switch_to_next_inplace(ct)?;
+        }
+        Ok(())
+    }
+
+    /// Brings `a` down to the level of `b` if it is higher and returns
+    /// `b` switched down to the level of `a` otherwise
+    fn align_levels<'a>(&self, a: &mut Ciphertext, b: &'a Ciphertext) -> std::borrow::Cow<'a, Ciphertext> {
+        if a.level() > b.level() {
+            self.mod_switch_to_inplace(a, b.level()).expect("target level is lower");
+        }
+        if b.level() > a.level() {
+            std::borrow::Cow::Owned(self.mod_switch_to(b, a.level()).expect("target level is lower"))
+        } else {
+            std::borrow::Cow::Borrowed(b)
+        }
+    }
+
+    // --- Ciphertext-Ciphertext ---
+
+    pub fn add(&self, a: &Ciphertext, b: &Ciphertext) -> Ciphertext {
+        let mut result = a.clone();
+        self.add_inplace(&mut result, b);
+        result
+    }
+
+    pub fn add_inplace(&self, a: &mut Ciphertext, b: &Ciphertext) {
+        let b = self.align_levels(a, b);
+        let level = a.level();
+        let tables = self.context.tables(level);
+        if a.size() < b.size() {
+            a.polys.resize(b.size(), RnsPoly::zero(self.context.params.poly_degree, level + 1));
+        }
+        for (x, y) in a.polys.iter_mut().zip(b.polys.iter()) {
+            *x = x.add(y, tables);
+        }
+        a.noise_bits = log2_add(a.noise_bits, b.noise_bits);
+    }
+
+    pub fn sub(&self, a: &Ciphertext, b: &Ciphertext) -> Ciphertext {
+        let mut result = a.clone();
+        self.sub_inplace(&mut result, b);
+        result
+    }
+
+    pub fn sub_inplace(&self, a: &mut Ciphertext, b: &Ciphertext) {
+        let b = self.align_levels(a, b);
+        let level = a.level();
+        let tables = self.context.tables(level);
+        if a.size() < b.size() {
+            a.polys.resize(b.size(), RnsPoly::zero(self.context.params.poly_degree, level + 1));
+        }
+        for (x, y) in a.polys.iter_mut().zip(b.polys.iter()) {
+            *x = x.sub(y, tables);
+        }
+        a.noise_bits = log2_add(a.noise_bits, b.noise_bits);
+    }
+
+    pub fn negate(&self, ct: &Ciphertext) -> Ciphertext {
+        let mut result = ct.clone();
+        self.negate_inplace(&mut result);
+        result
+    }
+
+    pub fn negate_inplace(&self, ct: &mut Ciphertext) {
+        let tables = self.context.tables(ct.level());
+        for c in ct.polys.iter_mut() {
+            *c = c.negate(tables);
+        }
+    }
+
+    /// Tensor product round(t/Q_l · ct1 ⊗ ct2) at the common level
+    ///
+    /// The result has size |ct1| + |ct2| - 1 (3 for fresh inputs) and
+    /// should be relinearized before further multiplications.
+    pub fn multiply(&self, a: &Ciphertext, b: &Ciphertext) -> Ciphertext {
+        let mut result = a.clone();
+        self.multiply_inplace(&mut result, b);
+        result
+    }
+
+    pub fn multiply_inplace(&self, a: &mut Ciphertext, b: &Ciphertext) {
+        let b = self.align_levels(a, b);
+        let ctx = &self.context;
+        let level = a.level();
+        let n = ctx.params.poly_degree;
+        let t = ctx.params.plain_modulus;
+        let tables: Vec<&NttTables> = ctx.ntt[..=level].iter().chain(&ctx.ext_ntt).collect();
+
+        // Exact lift to Q_l ∪ B, then NTT
+        let lift = |c: &RnsPoly| -> Vec<Vec<u64>> {
+            let mut limbs = c.limbs.clone();
+            limbs.extend(vec![vec![0u64; n]; ctx.ext_ntt.len()]);
+            let mut residues = vec![0u64; level + 1];
+            for k in 0..n {
+                for (r, limb) in residues.iter_mut().zip(&c.limbs) {
+                    *r = limb[k];
+                }
+                let x = ctx.bases[level].compose_centered(&residues);
+                for (limb, r) in limbs[level + 1..].iter_mut().zip(ctx.ext_basis.decompose(&x)) {
+                    limb[k] = r;
+                }
+            }
+            for (limb, tab) in limbs.iter_mut().zip(&tables) {
+                tab.forward(limb);
+            }
+            limbs
+        };
+        let lhs: Vec<Vec<Vec<u64>>> = a.polys.iter().map(lift).collect();
+        let rhs: Vec<Vec<Vec<u64>>> = b.polys.iter().map(lift).collect();
+
+        let mut product = vec![vec![vec![0u64; n]; tables.len()]; lhs.len() + rhs.len() - 1];
+        for (i, x) in lhs.iter().enumerate() {
+            for (j, y) in rhs.iter().enumerate() {
+                for (m, tab) in tables.iter().enumerate() {
+                    let q = tab.modulus;
+                    let out = &mut product[i + j][m];
+                    for k in 0..n {
+                        out[k] = modular::mod_add(out[k], modular::mod_mul(x[m][k], y[m][k], q), q);
+                    }
+                }
+            }
+        }
+
+        // Back to coefficients and scale by t/Q_l with rounding
+        let q_l = BigInt::from_biguint(Sign::Plus, ctx.bases[level].product.clone());
+        let two_q = &q_l * 2;
+        let mut residues = vec![0u64; tables.len()];
+        a.polys = product.into_iter()
+            .map(|mut limbs| {
+                for (limb, tab) in limbs.iter_mut().zip(&tables) {
+                    tab.inverse(limb);
+                }
+                let mut out = RnsPoly::zero(n, level + 1);
+                for k in 0..n {
+                    for (r, limb) in residues.iter_mut().zip(&limbs) {
+                        *r = limb[k];
+                    }
+                    let x = ctx.ext_bases[level].compose_centered(&residues);
+                    let rounded: BigInt = (x.abs() * (2 * t) + &q_l) / &two_q;
+                    let y = if x.is_negative() { -rounded } else { rounded };
+                    for (limb, r) in out.limbs.iter_mut().zip(ctx.bases[level].decompose(&y)) {
+                        limb[k] = r;
+                    }
+                }
+                out
+            })
+            .collect();
+
+        // v1·m2 + v2·m1 + s-dependent rounding terms grow by about t·N
+        a.noise_bits = log2_add(a.noise_bits, b.noise_bits) + (t as f64 * n as f64).log2();
+    }
+
+    pub fn square(&self, ct: &Ciphertext) -> Ciphertext {
+        self.multiply(ct, ct)
+    }
+
+    pub fn square_inplace(&self, ct: &mut Ciphertext) {
+        let copy = ct.clone();
+        self.multiply_inplace(ct, &copy);
+    }
+
+    /// Reduces a size-3 ciphertext back to size 2
+    pub fn relinearize(&self, ct: &Ciphertext, relin_keys: &RelinKeys) -> Result<Ciphertext, String> {
+        let mut result = ct.clone();
+        self.relinearize_inplace(&mut result, relin_keys)?;
+        Ok(result)
+    }
+
+    pub fn relinearize_inplace(&self, ct: &mut Ciphertext, relin_keys: &RelinKeys) -> Result<(), String> {
+        match ct.size() {
+            2 => return Ok(()),
+            3 => {}
+            size => return Err(format!("cannot relinearize a size-{} ciphertext, expected 3", size)),
+        }
+        let level = ct.level();
+        let tables = self.context.tables(level);
+
+        let c2 = ct.polys.pop().unwrap();
+        let (k0, k1) = self.context.keyswitch.switch(&c2, &relin_keys.key);
+        ct.polys[0] = ct.polys[0].add(&k0, tables);
+        ct.polys[1] = ct.polys[1].add(&k1, tables);
+        ct.noise_bits = log2_add(ct.noise_bits, self.context.key_switch_noise_bits(level));
+        Ok(())
+    }
+
+    /// Multiplication followed by relinearization
+    pub fn multiply_relin(&self, a: &Ciphertext, b: &Ciphertext, relin_keys: &RelinKeys) -> Result<Ciphertext, String> {
+        let mut result = self.multiply(a, b);
+        self.relinearize_inplace(&mut result, relin_keys)?;
+        Ok(result)
+    }
+
+    // --- Rotations ---
+
+    /// Applies X -> X^g and switches back to s with the matching Galois key
+    pub fn apply_galois(&self, ct: &Ciphertext, galois_elt: u64, galois_keys: &GaloisKeys) -> Result<Ciphertext, String> {
+        if ct.size() != 2 {
+            return Err(format!("cannot rotate a size-{} ciphertext, relinearize first", ct.size()));
+        }
+        let key = galois_keys.get(galois_elt)?;
+        let level = ct.level();
+        let tables = self.context.tables(level);
+
+        let c1 = ct.polys[1].automorphism(galois_elt, tables);
+        let (k0, k1) = self.context.keyswitch.switch(&c1, key);
+        Ok(Ciphertext {
+            polys: vec![ct.polys[0].automorphism(galois_elt, tables).add(&k0, tables), k1],
+            noise_bits: log2_add(ct.noise_bits, self.context.key_switch_noise_bits(level)),
+            correction_factor: ct.correction_factor,
+        })
+    }
+
+    /// Rotates both batching rows left by `step` (right if negative)
+    pub fn rotate_rows(&self, ct: &Ciphertext, step: i64, galois_keys: &GaloisKeys) -> Result<Ciphertext, String> {
+        let g = poly::galois_element_for_step(step, self.context.params.poly_degree);
+        if g == 1 {
+            return Ok(ct.clone());
+        }
+        self.apply_galois(ct, g, galois_keys)
+    }
+
+    /// Swaps the two batching rows
+    pub fn rotate_columns(&self, ct: &Ciphertext, galois_keys: &GaloisKeys) -> Result<Ciphertext, String> {
+        self.apply_galois(ct, poly::galois_element_conjugate(self.context.params.poly_degree), galois_keys)
+    }
+
+    // --- Plaintext-Ciphertext ---
+
+    pub fn add_plain(&self, ct: &Ciphertext, plain: &Plaintext) -> Ciphertext {
+        let mut result = ct.clone();
+        self.add_plain_inplace(&mut result, plain);
+        result
+    }
+
+    pub fn add_plain_inplace(&self, ct: &mut Ciphertext, plain: &Plaintext) {
+        let level = ct.level();
+        let scaled = self.context.scale_plain(plain, level);
+        ct.polys[0] = ct.polys[0].add(&scaled, self.context.tables(level));
+    }
+
+    pub fn sub_plain(&self, ct: &Ciphertext, plain: &Plaintext) -> Ciphertext {
+        let mut result = ct.clone();
+        self.sub_plain_inplace(&mut result, plain);
+        result
+    }
+
+    pub fn sub_plain_inplace(&self, ct: &mut Ciphertext, plain: &Plaintext) {
+        let level = ct.level();
+        let scaled = self.context.scale_plain(plain, level);
+        ct.polys[0] = ct.polys[0].sub(&scaled, self.context.tables(level));
+    }
+
+    /// Lifts the plaintext to centered form mod every q_i and caches its NTT
+    pub fn transform_plain_to_ntt(&self, plain: &Plaintext) -> PlaintextNtt {
+        let t = self.context.params.plain_modulus;
+
+        // e·m grows like max|m_i|·sqrt(#non-zero m_i) for random-looking noise
+        let centered = plain.coeffs.iter().map(|&m| if m % t > t / 2 { t - m % t } else { m % t });
+        let max_abs = centered.clone().max().unwrap_or(0).max(1) as f64;
+        let non_zero = centered.filter(|&m| m != 0).count().max(1) as f64;
+        let noise_growth_bits = (max_abs * non_zero.sqrt()).log2();
+
+        let mut values = self.context.lift_plain(plain, self.context.max_level());
+        let mut shoup = Vec::with_capacity(values.limbs.len());
+        for (limb, tab) in values.limbs.iter_mut().zip(&self.context.ntt) {
+            tab.forward(limb);
+            shoup.push(limb.iter().map(|&w| modular::shoup_precompute(w, tab.modulus)).collect());
+        }
+        PlaintextNtt { values, shoup, noise_growth_bits }
+    }
+
+    pub fn mul_plain(&self, ct: &Ciphertext, plain: &Plaintext) -> Ciphertext {
+        self.mul_plain_ntt(ct, &self.transform_plain_to_ntt(plain))
+    }
+
+    pub fn mul_plain_inplace(&self, ct: &mut Ciphertext, plain: &Plaintext) {
+        self.mul_plain_ntt_inplace(ct, &self.transform_plain_to_ntt(plain));
+    }
+
+    pub fn mul_plain_ntt(&self, ct: &Ciphertext, plain: &PlaintextNtt) -> Ciphertext {
+        let mut result = ct.clone();
+        self.mul_plain_ntt_inplace(&mut result, plain);
+        result
+    }
+
+    /// Hot path: one forward/inverse NTT per limb and a Shoup pointwise
+    /// product against the cached plaintext
+    pub fn mul_plain_ntt_inplace(&self, ct: &mut Ciphertext, plain: &PlaintextNtt) {
+        for c in ct.polys.iter_mut() {
+            for (i, limb) in c.limbs.iter_mut().enumerate() {
+                let tab = &self.context.ntt[i];
+                tab.forward(limb);
+                for ((x, &w), &w_shoup) in limb.iter_mut().zip(&plain.values.limbs[i]).zip(&plain.shoup[i]) {
+                    *x = modular::mod_mul_shoup(*x, w, w_shoup, tab.modulus);
+                }
+                tab.inverse(limb);
+            }
+        }
+        ct.noise_bits += plain.noise_growth_bits;
+    }
+
+    /// Multiplies by an integer scalar modulo t
+    pub fn multiply_by_scalar(&self, ct: &Ciphertext, scalar: u64) -> Ciphertext {
+        let mut result = ct.clone();
+        self.multiply_by_scalar_inplace(&mut result, scalar);
+        result
+    }
+
+    pub fn multiply_by_scalar_inplace(&self, ct: &mut Ciphertext, scalar: u64) {
+        let t = self.context.params.plain_modulus;
+        let tables = self.context.tables(ct.level());
+        let lifted: Vec<u64> = tables.iter().map(|tab| self.context.lift_scalar(scalar, tab.modulus)).collect();
+        for c in ct.polys.iter_mut() {
+            *c = c.mul_scalars(&lifted, tables);
+        }
+        let s = scalar % t;
+        ct.noise_bits += (s.min(t - s).max(1) as f64).log2();
     }
 }
 
@@ -32,14 +877,14 @@ impl BFVContext {
 pub fn encrypt_decrypt_cycle(poly_degree: usize, cipher_modulus: u64, plain_modulus: u64) -> bool {
     // Deterministic test vectors
     let mut plaintext = vec![0u64; poly_degree];
-    for i in 0..poly_degree {
-        plaintext[i] = (i as u64) % plain_modulus;
+    for (i, m) in plaintext.iter_mut().enumerate() {
+        *m = (i as u64) % plain_modulus;
     }
     
     // Deterministic "secret key" (ternary distribution)
     let mut secret_key = vec![0u64; poly_degree];
-    for i in 0..poly_degree {
-        secret_key[i] = match i % 5 {
+    for (i, s) in secret_key.iter_mut().enumerate() {
+        *s = match i % 5 {
             0 => 0,
             1 => 1,
             2 => cipher_modulus - 1, // -1 mod q
@@ -50,8 +895,8 @@ pub fn encrypt_decrypt_cycle(poly_degree: usize, cipher_modulus: u64, plain_modu
     
     // Deterministic error (small)
     let mut error = vec![0u64; poly_degree];
-    for i in 0..poly_degree {
-        error[i] = match i % 3 {
+    for (i, e) in error.iter_mut().enumerate() {
+        *e = match i % 3 {
             0 => 0,
             1 => 1,
             _ => cipher_modulus - 1,
@@ -66,7 +911,7 @@ pub fn encrypt_decrypt_cycle(poly_degree: usize, cipher_modulus: u64, plain_modu
     
     for i in 0..poly_degree {
         // c0[i] = deterministic value
-        c0[i] = ((i as u64 * 3) % cipher_modulus) as u64;
+        c0[i] = (i as u64 * 3) % cipher_modulus;
         
         // c1[i] = c0[i] * s[i] + m[i] * Δ + e[i] mod q
         let c0s = modular::mod_mul(c0[i], secret_key[i], cipher_modulus);
@@ -145,3 +990,156 @@ pub fn homomorphic_mul_simple(
     
     (result_c0, result_c1, result_c2)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fhe::BatchEncoder;
+
+    const T: u64 = 65537;
+
+    fn slots(f: impl Fn(u64) -> u64) -> Vec<u64> {
+        (0..1024).map(|i| f(i) % T).collect()
+    }
+
+    fn slotwise(a: &[u64], b: &[u64], op: impl Fn(u64, u64) -> u64) -> Vec<u64> {
+        a.iter().zip(b).map(|(&x, &y)| op(x, y)).collect()
+    }
+
+    #[test]
+    fn encrypt_decrypt() {
+        let context = BFVContext::new();
+        let keygen = KeyGenerator::new(&context);
+        let encoder = BatchEncoder::new(&context).unwrap();
+        let encryptor = Encryptor::new(&context, &keygen.public_key());
+        let decryptor = Decryptor::new(&context, &keygen.secret_key());
+
+        let a = slots(|i| i * 977 + 13);
+        let ct = encryptor.encrypt(&encoder.encode(&a).unwrap());
+        assert_eq!(encoder.decode(&decryptor.decrypt(&ct)), a);
+        assert_eq!(decryptor.decrypt(&encryptor.encrypt(&Plaintext::zero(1024))), Plaintext::zero(1024));
+    }
+
+    #[test]
+    fn ciphertext_and_plaintext_arithmetic() {
+        let context = BFVContext::new();
+        let keygen = KeyGenerator::new(&context);
+        let encoder = BatchEncoder::new(&context).unwrap();
+        let encryptor = Encryptor::new(&context, &keygen.public_key());
+        let decryptor = Decryptor::new(&context, &keygen.secret_key());
+        let ev = Evaluator::new(&context);
+        let decrypt = |ct: &Ciphertext| encoder.decode(&decryptor.decrypt(ct));
+
+        let (a, b) = (slots(|i| i * 977 + 13), slots(|i| i * i * 3 + 7));
+        let pb = encoder.encode(&b).unwrap();
+        let ca = encryptor.encrypt(&encoder.encode(&a).unwrap());
+        let cb = encryptor.encrypt(&pb);
+
+        let sum = slotwise(&a, &b, |x, y| modular::mod_add(x, y, T));
+        let difference = slotwise(&a, &b, |x, y| modular::mod_sub(x, y, T));
+        let product = slotwise(&a, &b, |x, y| modular::mod_mul(x, y, T));
+        assert_eq!(decrypt(&ev.add(&ca, &cb)), sum);
+        assert_eq!(decrypt(&ev.sub(&ca, &cb)), difference);
+        assert_eq!(decrypt(&ev.add_plain(&ca, &pb)), sum);
+        assert_eq!(decrypt(&ev.sub_plain(&ca, &pb)), difference);
+        assert_eq!(decrypt(&ev.mul_plain(&ca, &pb)), product);
+        assert_eq!(decrypt(&ev.mul_plain_ntt(&ca, &ev.transform_plain_to_ntt(&pb))), product);
+        assert_eq!(decrypt(&ev.negate(&ca)), slotwise(&a, &a, |x, _| modular::mod_sub(0, x, T)));
+        assert_eq!(decrypt(&ev.multiply_by_scalar(&ca, 65530)), slotwise(&a, &a, |x, _| modular::mod_mul(x, 65530, T)));
+
+        let mut ct = ca.clone();
+        ev.add_inplace(&mut ct, &cb);
+        ev.sub_plain_inplace(&mut ct, &pb);
+        ev.mul_plain_inplace(&mut ct, &pb);
+        assert_eq!(decrypt(&ct), product);
+    }
+
+    #[test]
+    fn noise_budget_shrinks_and_estimate_is_conservative() {
+        let context = BFVContext::new();
+        let keygen = KeyGenerator::new(&context);
+        let encoder = BatchEncoder::new(&context).unwrap();
+        let encryptor = Encryptor::new(&context, &keygen.public_key());
+        let decryptor = Decryptor::new(&context, &keygen.secret_key());
+
+        let ct = encryptor.encrypt(&encoder.encode(&slots(|i| i * 61)).unwrap());
+        let fresh = decryptor.invariant_noise_budget(&ct);
+        assert!(fresh > 0);
+        assert!(context.estimated_noise_budget(&ct) <= fresh);
+
+        let product = Evaluator::new(&context).mul_plain(&ct, &encoder.encode(&slots(|i| i + 1)).unwrap());
+        let budget = decryptor.invariant_noise_budget(&product);
+        assert!(budget < fresh);
+        assert!(context.estimated_noise_budget(&product) <= budget);
+    }
+
+    #[test]
+    fn modulus_switching_down_the_chain() {
+        let context = BFVContext::new();
+        let keygen = KeyGenerator::new(&context);
+        let encoder = BatchEncoder::new(&context).unwrap();
+        let encryptor = Encryptor::new(&context, &keygen.public_key());
+        let decryptor = Decryptor::new(&context, &keygen.secret_key());
+        let ev = Evaluator::new(&context);
+        let decrypt = |ct: &Ciphertext| encoder.decode(&decryptor.decrypt(ct));
+
+        let (a, b) = (slots(|i| i * 977 + 13), slots(|i| i * i * 3 + 7));
+        let ca = encryptor.encrypt(&encoder.encode(&a).unwrap());
+        let cb = encryptor.encrypt(&encoder.encode(&b).unwrap());
+
+        let mut ct = ca.clone();
+        while ct.level() > 0 {
+            let size = ct.serialized_size();
+            ev.mod_switch_to_next_inplace(&mut ct).unwrap();
+            assert!(ct.serialized_size() < size);
+            assert_eq!(decrypt(&ct), a);
+        }
+        assert!(ev.mod_switch_to_next(&ct).is_err());
+        assert!(ev.mod_switch_to(&ct, 1).is_err());
+
+        // operands at different levels are aligned to the lower one
+        let sum = ev.add(&ct, &cb);
+        assert_eq!(sum.level(), 0);
+        assert_eq!(decrypt(&sum), slotwise(&a, &b, |x, y| modular::mod_add(x, y, T)));
+        assert_eq!(decrypt(&ev.sub(&cb, &ct)), slotwise(&b, &a, |x, y| modular::mod_sub(x, y, T)));
+
+        let pb = ev.transform_plain_to_ntt(&encoder.encode(&b).unwrap());
+        let product = slotwise(&a, &b, |x, y| modular::mod_mul(x, y, T));
+        for level in 0..=context.max_level() {
+            let switched = ev.mod_switch_to(&ca, level).unwrap();
+            assert_eq!(decrypt(&ev.mul_plain_ntt(&switched, &pb)), product, "level {}", level);
+        }
+    }
+
+    #[test]
+    fn multiplication_and_relinearization() {
+        let context = BFVContext::new();
+        let keygen = KeyGenerator::new(&context);
+        let encoder = BatchEncoder::new(&context).unwrap();
+        let encryptor = Encryptor::new(&context, &keygen.public_key());
+        let decryptor = Decryptor::new(&context, &keygen.secret_key());
+        let ev = Evaluator::new(&context);
+        let decrypt = |ct: &Ciphertext| encoder.decode(&decryptor.decrypt(ct));
+
+        let (a, b) = (slots(|i| i * 977 + 13), slots(|i| i * i * 3 + 7));
+        let ca = encryptor.encrypt(&encoder.encode(&a).unwrap());
+        let cb = encryptor.encrypt(&encoder.encode(&b).unwrap());
+        let relin_keys = keygen.relin_keys();
+        let ab = slotwise(&a, &b, |x, y| modular::mod_mul(x, y, T));
+
+        let product = ev.multiply(&ca, &cb);
+        assert_eq!(product.size(), 3);
+        assert_eq!(decrypt(&product), ab);
+        let relinearized = ev.relinearize(&product, &relin_keys).unwrap();
+        assert_eq!(relinearized.size(), 2);
+        assert_eq!(decrypt(&relinearized), ab);
+
+        let abb = slotwise(&ab, &b, |x, y| modular::mod_mul(x, y, T));
+        assert_eq!(decrypt(&ev.multiply_relin(&relinearized, &cb, &relin_keys).unwrap()), abb);
+        let lower = ev.mod_switch_to(&relinearized, 1).unwrap();
+        let at_lower = ev.multiply_relin(&lower, &cb, &relin_keys).unwrap();
+        assert_eq!(at_lower.level(), 1);
+        assert_eq!(decrypt(&at_lower), abb);
+        assert_eq!(decrypt(&ev.square(&ca)), slotwise(&a, &a, |x, y| modular::mod_mul(x, y, T)));
+    }
+}
@@ -0,0 +1,227 @@
+//! Encrypted exponentiation and polynomial evaluation for BFV
+//! Depth-optimal square-and-multiply and Paterson-Stockmeyer
+//!
+//! Depth counts ciphertext-ciphertext multiplications on the critical
+//! path; scalar multiplications and additions are free in depth but do
+//! add noise. Check the depth against the noise budget before running.
+
+use super::bfv::{Ciphertext, Evaluator, Plaintext, RelinKeys};
+
+/// Multiplicative depth consumed by `exponentiate` (ceil(log2 k))
+pub fn exponentiate_depth(exponent: u64) -> u32 {
+    if exponent <= 1 { 0 } else { 64 - (exponent - 1).leading_zeros() }
+}
+
+/// Multiplicative depth consumed by `evaluate_polynomial` for the given
+/// degree (ceil(log2 d), which is optimal)
+pub fn polynomial_depth(degree: usize) -> u32 {
+    exponentiate_depth(degree as u64)
+}
+
+/// Baby-step count k = 2^a and giant-step count m with a + m = depth
+fn paterson_stockmeyer_split(degree: usize) -> (usize, u32) {
+    let depth = polynomial_depth(degree);
+    let a = depth.div_ceil(2).max(1);
+    (1usize << a, depth.saturating_sub(a))
+}
+
+impl Evaluator {
+    /// ct^k by right-to-left square-and-multiply with relinearization
+    ///
+    /// Partial products are combined in ascending order, so the depth is
+    /// `exponentiate_depth(k)`.
+    pub fn exponentiate(&self, ct: &Ciphertext, exponent: u64, relin_keys: &RelinKeys) -> Result<Ciphertext, String> {
+        if exponent == 0 {
+            return Err("exponent must be at least 1".to_string());
+        }
+
+        let mut power = ct.clone();
+        let mut result: Option<Ciphertext> = None;
+        let mut k = exponent;
+        loop {
+            if k & 1 == 1 {
+                result = Some(match result {
+                    None => power.clone(),
+                    Some(acc) => self.multiply_relin(&acc, &power, relin_keys)?,
+                });
+            }
+            k >>= 1;
+            if k == 0 {
+                break;
+            }
+            power = self.multiply_relin(&power, &power, relin_keys)?;
+        }
+        Ok(result.unwrap())
+    }
+
+    /// p(ct) = Σ coeffs[i]·ct^i with coefficients modulo t
+    ///
+    /// Paterson-Stockmeyer: baby steps ct^1..ct^k with k = 2^a, giant steps
+    /// ct^(k·2^j), and a recursive split p = q·ct^(k·2^(m-1)) + r. The depth
+    /// is `polynomial_depth(degree)`.
+    pub fn evaluate_polynomial(&self, ct: &Ciphertext, coeffs: &[u64], relin_keys: &RelinKeys) -> Result<Ciphertext, String> {
+        if coeffs.is_empty() {
+            return Err("polynomial has no coefficients".to_string());
+        }
+        let degree = coeffs.iter().rposition(|&c| c != 0).unwrap_or(0);
+        let coeffs = &coeffs[..=degree];
+        if degree == 0 {
+            return Ok(self.constant_like(ct, coeffs[0]));
+        }
+
+        let (k, giant_count) = paterson_stockmeyer_split(degree);
+
+        // Baby steps: x^i = x^ceil(i/2) · x^floor(i/2) has depth ceil(log2 i)
+        let mut baby: Vec<Ciphertext> = vec![ct.clone()];
+        for i in 2..=k.min(degree) {
+            let next = self.multiply_relin(&baby[i.div_ceil(2) - 1], &baby[i / 2 - 1], relin_keys)?;
+            baby.push(next);
+        }
+        // Giant steps: x^(k·2^j)
+        let mut giant: Vec<Ciphertext> = Vec::new();
+        if giant_count > 0 {
+            let mut g = if k <= degree { baby[k - 1].clone() } else { self.exponentiate(ct, k as u64, relin_keys)? };
+            giant.push(g.clone());
+            for _ in 1..giant_count {
+                g = self.multiply_relin(&g, &g, relin_keys)?;
+                giant.push(g.clone());
+            }
+        }
+
+        self.paterson_stockmeyer(ct, coeffs, k, &baby, &giant, relin_keys)
+    }
+
+    fn paterson_stockmeyer(
+        &self,
+        ct: &Ciphertext,
+        coeffs: &[u64],
+        k: usize,
+        baby: &[Ciphertext],
+        giant: &[Ciphertext],
+        relin_keys: &RelinKeys,
+    ) -> Result<Ciphertext, String> {
+        if coeffs.len() <= k + 1 || giant.is_empty() {
+            return Ok(self.evaluate_leaf(ct, coeffs, baby));
+        }
+
+        // Largest giant step not above the degree
+        let j = (0..giant.len()).rev()
+            .find(|&j| k << j < coeffs.len())
+            .unwrap_or(0);
+        let split = k << j;
+        let (low, high) = coeffs.split_at(split);
+
+        let mut result = if high.len() == 1 {
+            self.multiply_by_scalar(&giant[j], high[0])
+        } else {
+            let q = self.paterson_stockmeyer(ct, high, k, baby, &giant[..j], relin_keys)?;
+            self.multiply_relin(&q, &giant[j], relin_keys)?
+        };
+        if low.iter().any(|&c| c != 0) {
+            let r = self.paterson_stockmeyer(ct, low, k, baby, &giant[..j], relin_keys)?;
+            self.add_inplace(&mut result, &r);
+        }
+        Ok(result)
+    }
+
+    /// Σ c_i·x^i for i <= k with scalar multiplications only
+    fn evaluate_leaf(&self, ct: &Ciphertext, coeffs: &[u64], baby: &[Ciphertext]) -> Ciphertext {
+        let mut result: Option<Ciphertext> = None;
+        for (i, &c) in coeffs.iter().enumerate().skip(1) {
+            if c == 0 {
+                continue;
+            }
+            let term = self.multiply_by_scalar(&baby[i - 1], c);
+            match result.as_mut() {
+                None => result = Some(term),
+                Some(acc) => self.add_inplace(acc, &term),
+            }
+        }
+        match result {
+            None => self.constant_like(ct, coeffs[0]),
+            Some(mut acc) => {
+                if coeffs[0] != 0 {
+                    let mut constant = Plaintext::zero(acc.polys[0].limbs[0].len());
+                    constant.coeffs[0] = coeffs[0];
+                    self.add_plain_inplace(&mut acc, &constant);
+                }
+                acc
+            }
+        }
+    }
+
+    /// Trivial encryption of a constant at the level of `ct` (the noise
+    /// estimate of `ct` is kept as a safe upper bound)
+    fn constant_like(&self, ct: &Ciphertext, value: u64) -> Ciphertext {
+        let mut zero = self.multiply_by_scalar(ct, 0);
+        zero.polys.truncate(2);
+        let mut constant = Plaintext::zero(ct.polys[0].limbs[0].len());
+        constant.coeffs[0] = value;
+        self.add_plain_inplace(&mut zero, &constant);
+        zero
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fhe::{BFVContext, BFVParameters, BatchEncoder, Decryptor, Encryptor, KeyGenerator};
+    use crate::modular;
+
+    const T: u64 = 65537;
+
+    #[test]
+    fn reported_depths() {
+        let exponents = [(1u64, 0u32), (2, 1), (3, 2), (4, 2), (5, 3), (8, 3), (9, 4), (16, 4), (17, 5)];
+        for (k, depth) in exponents {
+            assert_eq!(exponentiate_depth(k), depth, "x^{}", k);
+            assert_eq!(polynomial_depth(k as usize), depth, "degree {}", k);
+        }
+        assert_eq!(polynomial_depth(0), 0);
+        for degree in 1..64 {
+            let (k, giant) = paterson_stockmeyer_split(degree);
+            assert!(k.is_power_of_two());
+            assert_eq!(k.trailing_zeros() + giant, polynomial_depth(degree).max(1), "degree {}", degree);
+        }
+    }
+
+    #[test]
+    fn exponentiation_and_paterson_stockmeyer() {
+        let params = BFVParameters::new(1024, T, &[60, 60, 60, 60, 60, 60]).unwrap();
+        let context = BFVContext::with_params(params).unwrap();
+        let encoder = BatchEncoder::new(&context).unwrap();
+        let keygen = KeyGenerator::new(&context);
+        let relin_keys = keygen.relin_keys();
+        let encryptor = Encryptor::new(&context, &keygen.public_key());
+        let decryptor = Decryptor::new(&context, &keygen.secret_key());
+        let evaluator = Evaluator::new(&context);
+
+        let x: Vec<u64> = (0..1024u64).map(|i| (i * 977 + 13) % T).collect();
+        let ct = encryptor.encrypt(&encoder.encode(&x).unwrap());
+        let decrypt = |ct: &Ciphertext| encoder.decode(&decryptor.decrypt(ct));
+
+        for k in [1u64, 2, 3, 5, 8, 13, 16] {
+            let power = evaluator.exponentiate(&ct, k, &relin_keys).unwrap();
+            let expected: Vec<u64> = x.iter().map(|&v| modular::mod_pow(v, k, T)).collect();
+            assert_eq!(decrypt(&power), expected, "x^{}", k);
+        }
+        assert!(evaluator.exponentiate(&ct, 0, &relin_keys).is_err());
+
+        let horner = |coeffs: &[u64], v: u64| {
+            coeffs.iter().rev().fold(0, |acc, &c| modular::mod_add(modular::mod_mul(acc, v, T), c, T))
+        };
+        let mut polynomials: Vec<Vec<u64>> = [0u64, 1, 2, 3, 4, 5, 7, 8, 9, 15, 16, 20]
+            .iter()
+            .map(|&degree| (0..=degree).map(|i| (i * 7919 + 3) % T).collect())
+            .collect();
+        polynomials.push(vec![5, 0, 0, 0, 0, 0, 0, 0, 0, 1]); // sparse, only x^9 above the constant
+        polynomials.push(vec![1, 2, 3, 0, 0, 0]); // trailing zeros are trimmed
+        polynomials.push(vec![0, 0, 0]);
+        for coeffs in &polynomials {
+            let result = evaluator.evaluate_polynomial(&ct, coeffs, &relin_keys).unwrap();
+            let expected: Vec<u64> = x.iter().map(|&v| horner(coeffs, v)).collect();
+            assert_eq!(decrypt(&result), expected, "coefficients {:?}", coeffs);
+        }
+        assert!(evaluator.evaluate_polynomial(&ct, &[], &relin_keys).is_err());
+    }
+}
@@ -0,0 +1,529 @@
+//! BGV scheme operations
+//! Brakerski-Gentry-Vaikuntanathan scheme on the BFV modulus chain
+//!
+//! BGV keeps the message in the low bits: c0 + c1·s = m + t·e (mod Q).
+//! Contexts, keys and encoders are the BFV ones; build the context from
+//! parameters with `SchemeType::Bgv` so key noise is a multiple of t.
+//! Dropping q_l divides the plaintext by q_l mod t; ciphertexts track that
+//! as a correction factor which decryption removes.
+
+use super::super::modular;
+use super::bfv::{
+    self, log2_add, BFVContext, Ciphertext, GaloisKeys, Plaintext, PlaintextNtt, PublicKey,
+    RelinKeys, SchemeType, SecretKey,
+};
+use super::keyswitch::{self, KeySwitchKey};
+use super::poly::{self, RnsPoly};
+use num_bigint::BigInt;
+use num_traits::ToPrimitive;
+
+fn check_scheme(context: &BFVContext) -> Result<(), String> {
+    if context.params.scheme != SchemeType::Bgv {
+        return Err("BGV needs a context built with SchemeType::Bgv".to_string());
+    }
+    Ok(())
+}
+
+// ==================== ENCRYPTION ====================
+
+pub struct Encryptor {
+    pub(crate) context: BFVContext,
+    pub(crate) public_key: PublicKey,
+}
+
+impl Encryptor {
+    pub fn new(context: &BFVContext, public_key: &PublicKey) -> Result<Self, String> {
+        check_scheme(context)?;
+        Ok(Self { context: context.clone(), public_key: public_key.clone() })
+    }
+
+    /// ct = (b·u + t·e1 + m, a·u + t·e2) at the top level
+    pub fn encrypt(&self, plain: &Plaintext) -> Ciphertext {
+        let ctx = &self.context;
+        let n = ctx.params.poly_degree;
+        let t = ctx.params.plain_modulus;
+        let tables = &ctx.ntt;
+        let t_limbs = vec![t; tables.len()];
+
+        let u = RnsPoly::from_signed(&poly::sample_ternary(n), tables);
+        let e1 = RnsPoly::from_signed(&poly::sample_gaussian(n, poly::ERROR_STD_DEV), tables)
+            .mul_scalars(&t_limbs, tables);
+        let e2 = RnsPoly::from_signed(&poly::sample_gaussian(n, poly::ERROR_STD_DEV), tables)
+            .mul_scalars(&t_limbs, tables);
+
+        let c0 = self.public_key.b.mul(&u, tables)
+            .add(&e1, tables)
+            .add(&ctx.lift_plain(plain, ctx.max_level()), tables);
+        let c1 = self.public_key.a.mul(&u, tables).add(&e2, tables);
+
+        Ciphertext { polys: vec![c0, c1], noise_bits: ctx.fresh_noise_bits(), correction_factor: 1 }
+    }
+}
+
+pub struct Decryptor {
+    context: BFVContext,
+    decryptor: bfv::Decryptor,
+}
+
+impl Decryptor {
+    pub fn new(context: &BFVContext, secret_key: &SecretKey) -> Result<Self, String> {
+        check_scheme(context)?;
+        Ok(Self { context: context.clone(), decryptor: bfv::Decryptor::new(context, secret_key) })
+    }
+
+    /// m = correction_factor^-1 · [c0 + c1·s + ...]_Q mod t
+    pub fn decrypt(&self, ct: &Ciphertext) -> Plaintext {
+        let t = self.context.params.plain_modulus;
+        let cf_inv = modular::mod_inv(ct.correction_factor % t, t);
+        let coeffs = self.centered_coeffs(ct).into_iter()
+            .map(|x| {
+                let m = (x % t).to_i64().unwrap().rem_euclid(t as i64) as u64;
+                modular::mod_mul(m, cf_inv, t)
+            })
+            .collect();
+        Plaintext { coeffs }
+    }
+
+    /// Remaining noise budget in bits: log2(Q) - log2(||m + t·e||_∞) - 1
+    pub fn invariant_noise_budget(&self, ct: &Ciphertext) -> u32 {
+        let q_bits = self.context.bases[ct.level()].product.bits();
+        let norm_bits = self.centered_coeffs(ct).iter().map(|x| x.bits()).max().unwrap_or(0);
+        q_bits.saturating_sub(norm_bits + 1) as u32
+    }
+
+    fn centered_coeffs(&self, ct: &Ciphertext) -> Vec<BigInt> {
+        let basis = &self.context.bases[ct.level()];
+        let x = self.decryptor.dot_product_with_secret(ct);
+        let mut residues = vec![0u64; ct.level() + 1];
+        (0..self.context.params.poly_degree)
+            .map(|k| {
+                for (r, limb) in residues.iter_mut().zip(&x.limbs) {
+                    *r = limb[k];
+                }
+                basis.compose_centered(&residues)
+            })
+            .collect()
+    }
+}
+
+// ==================== EVALUATOR ====================
+
+/// BGV evaluator; the scheme-independent plaintext and scalar products
+/// are shared with the BFV evaluator
+pub struct Evaluator {
+    context: BFVContext,
+    bfv: bfv::Evaluator,
+}
+
+impl Evaluator {
+    pub fn new(context: &BFVContext) -> Result<Self, String> {
+        check_scheme(context)?;
+        Ok(Self { context: context.clone(), bfv: bfv::Evaluator::new(context) })
+    }
+
+    // --- Modulus switching ---
+
+    /// Drops q_l: c' = (c - δ) / q_l with δ ≡ c (mod q_l), δ ≡ 0 (mod t)
+    ///
+    /// The plaintext becomes q_l^-1·m mod t, which is folded into the
+    /// correction factor. Switching after each multiplication keeps the
+    /// noise roughly constant.
+    pub fn mod_switch_to_next(&self, ct: &Ciphertext) -> Result<Ciphertext, String> {
+        let mut result = ct.clone();
+        self.mod_switch_to_next_inplace(&mut result)?;
+        Ok(result)
+    }
+
+    pub fn mod_switch_to_next_inplace(&self, ct: &mut Ciphertext) -> Result<(), String> {
+        let level = ct.level();
+        if level == 0 {
+            return Err("ciphertext is already at level 0".to_string());
+        }
+
+        let ctx = &self.context;
+        let t = ctx.params.plain_modulus;
+        let q_last = ctx.params.cipher_moduli[level];
+        let inv_last = &ctx.inv_last[level];
+        for c in ct.polys.iter_mut() {
+            let last = c.limbs.pop().unwrap();
+            let delta = keyswitch::remainders(&last, q_last, Some(t));
+            for (j, limb) in c.limbs.iter_mut().enumerate() {
+                let q = ctx.params.cipher_moduli[j];
+                for (x, &d) in limb.iter_mut().zip(&delta) {
+                    let d = d.rem_euclid(q as i128) as u64;
+                    *x = modular::mod_mul(modular::mod_sub(*x, d, q), inv_last[j], q);
+                }
+            }
+        }
+
+        let q_inv_t = modular::mod_inv(q_last % t, t);
+        ct.correction_factor = modular::mod_mul(ct.correction_factor, q_inv_t, t);
+        ct.noise_bits = log2_add(ct.noise_bits - (q_last as f64).log2(), ctx.mod_switch_noise_bits());
+        Ok(())
+    }
+
+    /// Switches down to `level` (no-op if already there)
+    pub fn mod_switch_to(&self, ct: &Ciphertext, level: usize) -> Result<Ciphertext, String> {
+        let mut result = ct.clone();
+        self.mod_switch_to_inplace(&mut result, level)?;
+        Ok(result)
+    }
+
+    pub fn mod_switch_to_inplace(&self, ct: &mut Ciphertext, level: usize) -> Result<(), String> {
+        if level > ct.level() {
+            return Err(format!(
+                "cannot switch a level-{} ciphertext up to level {}",
+                ct.level(), level
+            ));
+        }
+        while ct.level() > level {
+            self.mod_switch_to_next_inplace(ct)?;
+        }
+        Ok(())
+    }
+
+    /// Brings `a` and `b` to a common level and `b` to the correction
+    /// factor of `a`
+    fn align(&self, a: &mut Ciphertext, b: &Ciphertext) -> Ciphertext {
+        let mut b = b.clone();
+        if a.level() > b.level() {
+            self.mod_switch_to_inplace(a, b.level()).unwrap();
+        } else {
+            self.mod_switch_to_inplace(&mut b, a.level()).unwrap();
+        }
+        if b.correction_factor != a.correction_factor {
+            let t = self.context.params.plain_modulus;
+            let ratio = modular::mod_mul(a.correction_factor, modular::mod_inv(b.correction_factor, t), t);
+            self.bfv.multiply_by_scalar_inplace(&mut b, ratio);
+            b.correction_factor = a.correction_factor;
+        }
+        b
+    }
+
+    // --- Ciphertext-Ciphertext ---
+
+    pub fn add(&self, a: &Ciphertext, b: &Ciphertext) -> Ciphertext {
+        let mut result = a.clone();
+        self.add_inplace(&mut result, b);
+        result
+    }
+
+    pub fn add_inplace(&self, a: &mut Ciphertext, b: &Ciphertext) {
+        let mut b = self.align(a, b);
+        let tables = self.context.tables(a.level());
+        if a.size() < b.size() {
+            std::mem::swap(a, &mut b);
+        }
+        for (x, y) in a.polys.iter_mut().zip(&b.polys) {
+            *x = x.add(y, tables);
+        }
+        a.noise_bits = log2_add(a.noise_bits, b.noise_bits);
+    }
+
+    pub fn sub(&self, a: &Ciphertext, b: &Ciphertext) -> Ciphertext {
+        let mut result = a.clone();
+        self.sub_inplace(&mut result, b);
+        result
+    }
+
+    pub fn sub_inplace(&self, a: &mut Ciphertext, b: &Ciphertext) {
+        let b = self.bfv.negate(b);
+        self.add_inplace(a, &b);
+    }
+
+    pub fn negate(&self, ct: &Ciphertext) -> Ciphertext {
+        self.bfv.negate(ct)
+    }
+
+    pub fn negate_inplace(&self, ct: &mut Ciphertext) {
+        self.bfv.negate_inplace(ct);
+    }
+
+    /// Tensor product ct1 ⊗ ct2 mod Q_l; no scaling is needed in BGV
+    ///
+    /// The result has size |ct1| + |ct2| - 1 and correction factor
+    /// cf1·cf2 mod t.
+    pub fn multiply(&self, a: &Ciphertext, b: &Ciphertext) -> Ciphertext {
+        let mut result = a.clone();
+        self.multiply_inplace(&mut result, b);
+        result
+    }
+
+    pub fn multiply_inplace(&self, a: &mut Ciphertext, b: &Ciphertext) {
+        let mut b = b.clone();
+        if a.level() > b.level() {
+            self.mod_switch_to_inplace(a, b.level()).unwrap();
+        } else {
+            self.mod_switch_to_inplace(&mut b, a.level()).unwrap();
+        }
+        let ctx = &self.context;
+        let t = ctx.params.plain_modulus;
+        let n = ctx.params.poly_degree;
+        let tables = ctx.tables(a.level());
+
+        a.polys = poly::tensor(&a.polys, &b.polys, tables);
+        a.correction_factor = modular::mod_mul(a.correction_factor, b.correction_factor, t);
+        // Product of two random-looking polynomials grows by about sqrt(N)
+        a.noise_bits += b.noise_bits + 0.5 * (n as f64).log2() + 1.0;
+    }
+
+    pub fn square(&self, ct: &Ciphertext) -> Ciphertext {
+        self.multiply(ct, ct)
+    }
+
+    pub fn square_inplace(&self, ct: &mut Ciphertext) {
+        let copy = ct.clone();
+        self.multiply_inplace(ct, &copy);
+    }
+
+    /// Reduces a size-3 ciphertext back to size 2
+    pub fn relinearize(&self, ct: &Ciphertext, relin_keys: &RelinKeys) -> Result<Ciphertext, String> {
+        let mut result = ct.clone();
+        self.relinearize_inplace(&mut result, relin_keys)?;
+        Ok(result)
+    }
+
+    pub fn relinearize_inplace(&self, ct: &mut Ciphertext, relin_keys: &RelinKeys) -> Result<(), String> {
+        match ct.size() {
+            2 => return Ok(()),
+            3 => {}
+            size => return Err(format!("cannot relinearize a size-{} ciphertext, expected 3", size)),
+        }
+        let c2 = ct.polys.pop().unwrap();
+        self.add_key_switched(ct, &c2, &relin_keys.key);
+        Ok(())
+    }
+
+    /// Multiplication followed by relinearization
+    pub fn multiply_relin(&self, a: &Ciphertext, b: &Ciphertext, relin_keys: &RelinKeys) -> Result<Ciphertext, String> {
+        let mut result = self.multiply(a, b);
+        self.relinearize_inplace(&mut result, relin_keys)?;
+        Ok(result)
+    }
+
+    /// ct += switch(d) for a component d under another key
+    fn add_key_switched(&self, ct: &mut Ciphertext, d: &RnsPoly, key: &KeySwitchKey) {
+        let level = ct.level();
+        let tables = self.context.tables(level);
+        let (k0, k1) = self.context.keyswitch.switch(d, key);
+        ct.polys[0] = ct.polys[0].add(&k0, tables);
+        ct.polys[1] = ct.polys[1].add(&k1, tables);
+        ct.noise_bits = log2_add(ct.noise_bits, self.context.key_switch_noise_bits(level));
+    }
+
+    // --- Rotations ---
+
+    /// Applies X -> X^g and switches back to s with the matching Galois key
+    pub fn apply_galois(&self, ct: &Ciphertext, galois_elt: u64, galois_keys: &GaloisKeys) -> Result<Ciphertext, String> {
+        if ct.size() != 2 {
+            return Err(format!("cannot rotate a size-{} ciphertext, relinearize first", ct.size()));
+        }
+        let key = galois_keys.get(galois_elt)?;
+        let tables = self.context.tables(ct.level());
+
+        let c1 = ct.polys[1].automorphism(galois_elt, tables);
+        let mut result = Ciphertext {
+            polys: vec![ct.polys[0].automorphism(galois_elt, tables), RnsPoly::zero(c1.limbs[0].len(), c1.limbs.len())],
+            noise_bits: ct.noise_bits,
+            correction_factor: ct.correction_factor,
+        };
+        self.add_key_switched(&mut result, &c1, key);
+        Ok(result)
+    }
+
+    /// Rotates both batching rows left by `step` (right if negative)
+    pub fn rotate_rows(&self, ct: &Ciphertext, step: i64, galois_keys: &GaloisKeys) -> Result<Ciphertext, String> {
+        let g = poly::galois_element_for_step(step, self.context.params.poly_degree);
+        if g == 1 {
+            return Ok(ct.clone());
+        }
+        self.apply_galois(ct, g, galois_keys)
+    }
+
+    /// Swaps the two batching rows
+    pub fn rotate_columns(&self, ct: &Ciphertext, galois_keys: &GaloisKeys) -> Result<Ciphertext, String> {
+        self.apply_galois(ct, poly::galois_element_conjugate(self.context.params.poly_degree), galois_keys)
+    }
+
+    // --- Plaintext-Ciphertext ---
+
+    pub fn add_plain(&self, ct: &Ciphertext, plain: &Plaintext) -> Ciphertext {
+        let mut result = ct.clone();
+        self.add_plain_inplace(&mut result, plain);
+        result
+    }
+
+    /// c0 += cf·m, so the sum decrypts under the ciphertext's correction factor
+    pub fn add_plain_inplace(&self, ct: &mut Ciphertext, plain: &Plaintext) {
+        let level = ct.level();
+        let scaled = self.scaled_plain(plain, ct.correction_factor);
+        let lifted = self.context.lift_plain(&scaled, level);
+        ct.polys[0] = ct.polys[0].add(&lifted, self.context.tables(level));
+    }
+
+    pub fn sub_plain(&self, ct: &Ciphertext, plain: &Plaintext) -> Ciphertext {
+        let mut result = ct.clone();
+        self.sub_plain_inplace(&mut result, plain);
+        result
+    }
+
+    pub fn sub_plain_inplace(&self, ct: &mut Ciphertext, plain: &Plaintext) {
+        let level = ct.level();
+        let scaled = self.scaled_plain(plain, ct.correction_factor);
+        let lifted = self.context.lift_plain(&scaled, level);
+        ct.polys[0] = ct.polys[0].sub(&lifted, self.context.tables(level));
+    }
+
+    fn scaled_plain(&self, plain: &Plaintext, factor: u64) -> Plaintext {
+        let t = self.context.params.plain_modulus;
+        Plaintext { coeffs: plain.coeffs.iter().map(|&m| modular::mod_mul(m % t, factor, t)).collect() }
+    }
+
+    pub fn transform_plain_to_ntt(&self, plain: &Plaintext) -> PlaintextNtt {
+        self.bfv.transform_plain_to_ntt(plain)
+    }
+
+    pub fn mul_plain(&self, ct: &Ciphertext, plain: &Plaintext) -> Ciphertext {
+        self.bfv.mul_plain(ct, plain)
+    }
+
+    pub fn mul_plain_inplace(&self, ct: &mut Ciphertext, plain: &Plaintext) {
+        self.bfv.mul_plain_inplace(ct, plain);
+    }
+
+    pub fn mul_plain_ntt(&self, ct: &Ciphertext, plain: &PlaintextNtt) -> Ciphertext {
+        self.bfv.mul_plain_ntt(ct, plain)
+    }
+
+    pub fn mul_plain_ntt_inplace(&self, ct: &mut Ciphertext, plain: &PlaintextNtt) {
+        self.bfv.mul_plain_ntt_inplace(ct, plain);
+    }
+
+    /// Multiplies by an integer scalar modulo t
+    pub fn multiply_by_scalar(&self, ct: &Ciphertext, scalar: u64) -> Ciphertext {
+        self.bfv.multiply_by_scalar(ct, scalar)
+    }
+
+    pub fn multiply_by_scalar_inplace(&self, ct: &mut Ciphertext, scalar: u64) {
+        self.bfv.multiply_by_scalar_inplace(ct, scalar);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fhe::{BFVParameters, BatchEncoder, KeyGenerator};
+    use crate::modular::mod_mul;
+
+    const T: u64 = 65537;
+
+    fn bgv_context() -> BFVContext {
+        let params = BFVParameters::new(1024, T, &[50, 40, 40, 40, 60]).unwrap().with_scheme(SchemeType::Bgv);
+        BFVContext::with_params(params).unwrap()
+    }
+
+    fn slots(f: impl Fn(u64) -> u64) -> Vec<u64> {
+        (0..1024).map(|i| f(i) % T).collect()
+    }
+
+    fn slotwise(a: &[u64], b: &[u64], op: impl Fn(u64, u64) -> u64) -> Vec<u64> {
+        a.iter().zip(b).map(|(&x, &y)| op(x, y)).collect()
+    }
+
+    #[test]
+    fn rejects_bfv_context() {
+        let context = BFVContext::new();
+        let keygen = KeyGenerator::new(&context);
+        assert!(Encryptor::new(&context, &keygen.public_key()).is_err());
+        assert!(Decryptor::new(&context, &keygen.secret_key()).is_err());
+        assert!(Evaluator::new(&context).is_err());
+    }
+
+    #[test]
+    fn encrypt_decrypt_and_arithmetic() {
+        let context = bgv_context();
+        let keygen = KeyGenerator::new(&context);
+        let encoder = BatchEncoder::new(&context).unwrap();
+        let encryptor = Encryptor::new(&context, &keygen.public_key()).unwrap();
+        let decryptor = Decryptor::new(&context, &keygen.secret_key()).unwrap();
+        let ev = Evaluator::new(&context).unwrap();
+        let encrypt = |values: &[u64]| encryptor.encrypt(&encoder.encode(values).unwrap());
+        let decrypt = |ct: &Ciphertext| encoder.decode(&decryptor.decrypt(ct));
+        let (a, b) = (slots(|i| i * 977 + 13), slots(|i| i * i * 3 + 7));
+        let (ca, cb) = (encrypt(&a), encrypt(&b));
+        let pb = encoder.encode(&b).unwrap();
+
+        assert_eq!(decrypt(&ca), a);
+        assert_eq!(decrypt(&ev.add(&ca, &cb)), slotwise(&a, &b, |x, y| (x + y) % T));
+        assert_eq!(decrypt(&ev.sub(&ca, &cb)), slotwise(&a, &b, |x, y| (x + T - y) % T));
+        assert_eq!(decrypt(&ev.add_plain(&ca, &pb)), slotwise(&a, &b, |x, y| (x + y) % T));
+        assert_eq!(decrypt(&ev.mul_plain(&ca, &pb)), slotwise(&a, &b, |x, y| mod_mul(x, y, T)));
+        assert_eq!(decrypt(&ev.multiply_by_scalar(&ca, 3)), slotwise(&a, &a, |x, _| mod_mul(x, 3, T)));
+
+        let product = ev.multiply_relin(&ca, &cb, &keygen.relin_keys()).unwrap();
+        assert_eq!(product.polys.len(), 2);
+        assert_eq!(decrypt(&product), slotwise(&a, &b, |x, y| mod_mul(x, y, T)));
+    }
+
+    #[test]
+    fn modulus_switching_tracks_the_correction_factor() {
+        let context = bgv_context();
+        let keygen = KeyGenerator::new(&context);
+        let encoder = BatchEncoder::new(&context).unwrap();
+        let encryptor = Encryptor::new(&context, &keygen.public_key()).unwrap();
+        let decryptor = Decryptor::new(&context, &keygen.secret_key()).unwrap();
+        let ev = Evaluator::new(&context).unwrap();
+        let encrypt = |values: &[u64]| encryptor.encrypt(&encoder.encode(values).unwrap());
+        let decrypt = |ct: &Ciphertext| encoder.decode(&decryptor.decrypt(ct));
+        let (a, b) = (slots(|i| i * 977 + 13), slots(|i| i * i * 3 + 7));
+        let (ca, cb) = (encrypt(&a), encrypt(&b));
+        let rk = keygen.relin_keys();
+        let ab = slotwise(&a, &b, |x, y| mod_mul(x, y, T));
+
+        let switched = ev.mod_switch_to_next(&ev.multiply_relin(&ca, &cb, &rk).unwrap()).unwrap();
+        assert_eq!(switched.level() + 1, ca.level());
+        assert_ne!(switched.correction_factor, 1);
+        assert_eq!(decrypt(&switched), ab);
+
+        // Operands at different levels and correction factors are aligned
+        assert_eq!(decrypt(&ev.add(&switched, &ca)), slotwise(&ab, &a, |x, y| (x + y) % T));
+        assert_eq!(decrypt(&ev.sub(&ca, &switched)), slotwise(&a, &ab, |x, y| (x + T - y) % T));
+        let pb = encoder.encode(&b).unwrap();
+        assert_eq!(decrypt(&ev.add_plain(&switched, &pb)), slotwise(&ab, &b, |x, y| (x + y) % T));
+
+        // Depth three down the chain
+        let abb = ev.multiply_relin(&switched, &ev.mod_switch_to_next(&cb).unwrap(), &rk).unwrap();
+        let abba = ev.multiply_relin(&ev.mod_switch_to_next(&abb).unwrap(), &ca, &rk).unwrap();
+        let abba = ev.mod_switch_to_next(&abba).unwrap();
+        let expected = slotwise(&ab, &b, |x, y| mod_mul(x, y, T));
+        assert_eq!(decrypt(&abba), slotwise(&expected, &a, |x, y| mod_mul(x, y, T)));
+        assert!(decryptor.invariant_noise_budget(&abba) > 0);
+    }
+
+    #[test]
+    fn rotations() {
+        let context = bgv_context();
+        let keygen = KeyGenerator::new(&context);
+        let encoder = BatchEncoder::new(&context).unwrap();
+        let encryptor = Encryptor::new(&context, &keygen.public_key()).unwrap();
+        let decryptor = Decryptor::new(&context, &keygen.secret_key()).unwrap();
+        let ev = Evaluator::new(&context).unwrap();
+        let encrypt = |values: &[u64]| encryptor.encrypt(&encoder.encode(values).unwrap());
+        let decrypt = |ct: &Ciphertext| encoder.decode(&decryptor.decrypt(ct));
+        let (a, b) = (slots(|i| i * 977 + 13), slots(|i| i * i * 3 + 7));
+        let gk = keygen.galois_keys_for_elements(&[5, 25, poly::galois_element_for_step(-1, 1024), 2047]);
+        let switched = ev.mod_switch_to_next(&ev.multiply_relin(&encrypt(&a), &encrypt(&b), &keygen.relin_keys()).unwrap()).unwrap();
+        let ab = slotwise(&a, &b, |x, y| mod_mul(x, y, T));
+
+        for (step, shift) in [(1i64, 1usize), (2, 2), (-1, 511)] {
+            let rotated = decrypt(&ev.rotate_rows(&switched, step, &gk).unwrap());
+            for i in 0..512 {
+                assert_eq!(rotated[i], ab[(i + shift) % 512], "step {}", step);
+                assert_eq!(rotated[512 + i], ab[512 + (i + shift) % 512], "step {}", step);
+            }
+        }
+        let swapped = decrypt(&ev.rotate_columns(&encrypt(&a), &gk).unwrap());
+        assert_eq!(swapped, (0..1024).map(|i| a[(i + 512) % 1024]).collect::<Vec<_>>());
+        assert!(ev.rotate_rows(&switched, 3, &gk).is_err());
+    }
+}
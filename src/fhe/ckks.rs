@@ -1,55 +1,1280 @@
 //! CKKS scheme operations
 //! Cheon-Kim-Kim-Song scheme implementation
+//!
+//! Approximate arithmetic on complex slot vectors. A ciphertext at level l
+//! decrypts modulo Q_l = q_0···q_l to Δ·m + e for its scale Δ; rescaling
+//! divides exactly by q_l in RNS, dropping one level and dividing Δ by q_l.
+//! Scales are tracked exactly in f64 and multiply through products.
+//!
+//! With composite scaling each level q_l is the product of k chain primes,
+//! so scales beyond 2^60 (e.g. two 40-bit primes for Δ = 2^80) keep every
+//! RNS limb in a u64; a rescale then divides by the k top primes in turn.
 
 use super::super::modular;
+use super::bfv::{GaloisKeys, PublicKey, RelinKeys, SecretKey};
+use super::ckks_encoder::CKKSEncoder;
+use super::keyswitch::{self, KeySwitchBasis, KeySwitchKey};
+use super::poly::{self, RnsPoly};
+use crate::ntt::NttTables;
+use crate::rns::RnsBasis;
+use num_complex::Complex64;
 
-/// CKKS encoding simulation (real numbers to polynomial)
-pub fn encode_real(values: &[f64], scaling_factor: f64) -> Vec<i64> {
-    values.iter()
-        .map(|&x| (x * scaling_factor).round() as i64)
-        .collect()
-}
+/// Relative difference below which two scales count as equal
+pub const SCALE_TOLERANCE: f64 = 1e-9;
+
+// ==================== PARAMETERS ====================
 
-/// CKKS decoding simulation (polynomial to real numbers)
-pub fn decode_real(poly: &[i64], scaling_factor: f64) -> Vec<f64> {
-    poly.iter()
-        .map(|&coeff| coeff as f64 / scaling_factor)
-        .collect()
+#[derive(Clone, Debug)]
+pub struct CKKSParameters {
+    pub poly_degree: usize,
+    /// Modulus chain q_0, ..., q_L; q_1..q_L are consumed by rescaling
+    pub cipher_moduli: Vec<u64>,
+    /// Special prime P for key switching
+    pub special_modulus: u64,
+    /// Default encoding scale Δ
+    pub scale: f64,
+    /// Chain primes per level (k > 1 for composite scaling)
+    pub primes_per_level: usize,
+    /// Packed slots n, a power of two up to N/2 (sparse packing below N/2)
+    pub slots: usize,
 }
 
-/// CKKS rescaling simulation (reduce ciphertext modulus)
-pub fn rescaling(ciphertext: &[u64], modulus_from: u64, modulus_to: u64) -> Vec<u64> {
-    let scale_down = modulus_to as f64 / modulus_from as f64;
-    
-    ciphertext.iter()
-        .map(|&coeff| {
-            let scaled = coeff as f64 * scale_down;
-            scaled.round() as u64 % modulus_to
+impl CKKSParameters {
+    /// Chain from prime bit sizes: q_0 (sized for the integer part plus the
+    /// scale), the rescaling primes (close to log2 Δ) and the special prime last
+    pub fn new(poly_degree: usize, bit_sizes: &[u32], scale: f64) -> Result<Self, String> {
+        if bit_sizes.len() < 2 {
+            return Err("need at least one chain prime and the special prime".to_string());
+        }
+        let mut cipher_moduli = modular::generate_ntt_primes(poly_degree, bit_sizes)?;
+        let special_modulus = cipher_moduli.pop().unwrap();
+        Ok(Self {
+            poly_degree,
+            cipher_moduli,
+            special_modulus,
+            scale,
+            primes_per_level: 1,
+            slots: poly_degree / 2,
         })
-        .collect()
+    }
+
+    /// Groups the chain into levels of `k` consecutive primes, q_0 included;
+    /// the chain length must be a multiple of k
+    pub fn with_primes_per_level(mut self, k: usize) -> Self {
+        self.primes_per_level = k;
+        self
+    }
+
+    /// Packs `slots` values, replicated across the N/2 slot positions
+    pub fn with_slots(mut self, slots: usize) -> Self {
+        self.slots = slots;
+        self
+    }
+}
+
+#[derive(Clone)]
+pub struct CKKSContext {
+    pub params: CKKSParameters,
+    pub ntt: Vec<NttTables>,       // one per chain modulus
+    pub bases: Vec<RnsBasis>,      // bases[l] covers the primes of levels 0..=l
+    pub keyswitch: KeySwitchBasis, // chain plus special prime
+    pub encoder: CKKSEncoder,
+    inv_last: Vec<Vec<u64>>,       // inv_last[i][j] = q_i^-1 mod q_j over chain primes
+}
+
+impl CKKSContext {
+    /// N = 8192, chain of 60/40/40 bits, 60-bit special prime, Δ = 2^40
+    pub fn new() -> Self {
+        let params = CKKSParameters::new(8192, &[60, 40, 40, 60], 2f64.powi(40))
+            .expect("default CKKS parameters are valid");
+        Self::with_params(params).expect("default CKKS parameters are valid")
+    }
+
+    /// Validates the parameters and precomputes NTT and CRT tables per level
+    pub fn with_params(params: CKKSParameters) -> Result<Self, String> {
+        let n = params.poly_degree;
+        if params.cipher_moduli.is_empty() {
+            return Err("cipher_moduli must contain at least one prime".to_string());
+        }
+        if !(params.scale.is_finite() && params.scale >= 1.0) {
+            return Err(format!("scale {} must be finite and at least 1", params.scale));
+        }
+        let k = params.primes_per_level;
+        if k == 0 || !params.cipher_moduli.len().is_multiple_of(k) {
+            return Err(format!(
+                "{} chain primes cannot be grouped into levels of {} primes",
+                params.cipher_moduli.len(), k
+            ));
+        }
+        let encoder = CKKSEncoder::with_slots(n, params.slots)?;
+
+        let mut ntt = Vec::with_capacity(params.cipher_moduli.len());
+        for (i, &q) in params.cipher_moduli.iter().enumerate() {
+            if !modular::is_prime(q) {
+                return Err(format!("cipher modulus {} is not prime", q));
+            }
+            if params.cipher_moduli[..i].contains(&q) {
+                return Err(format!("cipher modulus {} appears twice in the chain", q));
+            }
+            ntt.push(NttTables::new(n, q).ok_or_else(|| {
+                format!("cipher modulus {} does not support the NTT for poly_degree {}", q, n)
+            })?);
+        }
+        let p = params.special_modulus;
+        if !modular::is_prime(p) || params.cipher_moduli.contains(&p) {
+            return Err(format!("special_modulus {} must be a prime outside the chain", p));
+        }
+        let special = NttTables::new(n, p).ok_or_else(|| {
+            format!("special_modulus {} does not support the NTT for poly_degree {}", p, n)
+        })?;
+        let keyswitch = KeySwitchBasis::new(&ntt, &special, None);
+
+        let moduli = &params.cipher_moduli;
+        let bases = (1..=moduli.len() / k).map(|l| RnsBasis::new(&moduli[..l * k])).collect();
+        let inv_last = (0..moduli.len())
+            .map(|l| moduli[..l].iter().map(|&q| modular::mod_inv(moduli[l] % q, q)).collect())
+            .collect();
+
+        Ok(Self { params, ntt, bases, keyswitch, encoder, inv_last })
+    }
+
+    /// Highest level L of the modulus chain
+    pub fn max_level(&self) -> usize {
+        self.params.cipher_moduli.len() / self.params.primes_per_level - 1
+    }
+
+    /// Number of RNS limbs at `level`
+    pub fn limb_count(&self, level: usize) -> usize {
+        (level + 1) * self.params.primes_per_level
+    }
+
+    /// q_level: the product of the level's primes, which a rescale from
+    /// `level` divides by
+    pub fn level_modulus(&self, level: usize) -> f64 {
+        let k = self.params.primes_per_level;
+        self.params.cipher_moduli[level * k..(level + 1) * k].iter().map(|&q| q as f64).product()
+    }
+
+    /// NTT tables of the primes up to `level`
+    pub fn tables(&self, level: usize) -> &[NttTables] {
+        &self.ntt[..self.limb_count(level)]
+    }
+
+    /// Number of complex slots n (N/2 unless sparse)
+    pub fn slot_count(&self) -> usize {
+        self.encoder.slot_count()
+    }
+
+    /// The same chain and keys with `slots` packed slots; ciphertexts of
+    /// both contexts are interchangeable, only encoding and decoding differ
+    pub fn with_slot_count(&self, slots: usize) -> Result<Self, String> {
+        let mut context = self.clone();
+        context.encoder = CKKSEncoder::with_slots(self.params.poly_degree, slots)?;
+        context.params.slots = slots;
+        Ok(context)
+    }
+
+    /// Encodes complex values at `scale` and `level`
+    pub fn encode(&self, values: &[Complex64], scale: f64, level: usize) -> Result<Plaintext, String> {
+        if level > self.max_level() {
+            return Err(format!("level {} exceeds the top level {}", level, self.max_level()));
+        }
+        let mut plain = self.encoder.encode(values, scale, self.tables(level))?;
+        plain.primes_per_level = self.params.primes_per_level;
+        Ok(plain)
+    }
+
+    /// Encodes real values at the default scale and the top level
+    pub fn encode_real(&self, values: &[f64]) -> Result<Plaintext, String> {
+        let values: Vec<Complex64> = values.iter().map(|&x| Complex64::new(x, 0.0)).collect();
+        self.encode(&values, self.params.scale, self.max_level())
+    }
+
+    pub fn decode(&self, plain: &Plaintext) -> Vec<Complex64> {
+        self.encoder.decode(plain, &self.bases[plain.level()])
+    }
+
+    pub fn decode_real(&self, plain: &Plaintext) -> Vec<f64> {
+        self.encoder.decode_real(plain, &self.bases[plain.level()])
+    }
+}
+
+impl Default for CKKSContext {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// ==================== DATA TYPES ====================
+
+/// CKKS plaintext: encoded slots as an RNS polynomial at some level,
+/// scaled by `scale`
+#[derive(Clone, Debug)]
+pub struct Plaintext {
+    pub poly: RnsPoly,
+    pub scale: f64,
+    /// Chain primes per level of the context it was encoded in
+    pub primes_per_level: usize,
+}
+
+impl Plaintext {
+    /// Level l in the chain: the plaintext lives modulo q_0···q_l
+    pub fn level(&self) -> usize {
+        self.poly.limbs.len() / self.primes_per_level - 1
+    }
+}
+
+/// CKKS ciphertext (c0, c1, ...) with its scale and level
+#[derive(Clone, Debug)]
+pub struct Ciphertext {
+    pub polys: Vec<RnsPoly>,
+    pub scale: f64,
+    /// Chain primes per level of the context it was encrypted in
+    pub primes_per_level: usize,
+}
+
+impl Ciphertext {
+    /// Number of polynomials (2 for fresh ciphertexts)
+    pub fn size(&self) -> usize {
+        self.polys.len()
+    }
+
+    /// Level l in the chain: the ciphertext lives modulo q_0···q_l
+    pub fn level(&self) -> usize {
+        self.polys[0].limbs.len() / self.primes_per_level - 1
+    }
+}
+
+// ==================== KEYS ====================
+
+/// Key generator over the CKKS chain; keys share the BFV key types
+pub struct KeyGenerator {
+    context: CKKSContext,
+    secret_key: SecretKey,
+}
+
+impl KeyGenerator {
+    /// Samples a fresh ternary secret key
+    pub fn new(context: &CKKSContext) -> Self {
+        let n = context.params.poly_degree;
+        let s = RnsPoly::from_signed(&poly::sample_ternary(n), &context.keyswitch.tables);
+        Self { context: context.clone(), secret_key: SecretKey { s } }
+    }
+
+    /// Samples a sparse ternary secret with `hamming_weight` nonzero
+    /// coefficients, which keeps the overflow of `Evaluator::mod_raise`
+    /// small for bootstrapping
+    pub fn with_hamming_weight(context: &CKKSContext, hamming_weight: usize) -> Self {
+        let n = context.params.poly_degree;
+        let secret = poly::sample_sparse_ternary(n, hamming_weight);
+        let s = RnsPoly::from_signed(&secret, &context.keyswitch.tables);
+        Self { context: context.clone(), secret_key: SecretKey { s } }
+    }
+
+    pub fn secret_key(&self) -> SecretKey {
+        self.secret_key.clone()
+    }
+
+    /// (b, a) = (-(a·s + e), a) at the top level
+    pub fn public_key(&self) -> PublicKey {
+        let ctx = &self.context;
+        let n = ctx.params.poly_degree;
+
+        let s = self.secret_key.s.truncated(ctx.ntt.len());
+        let a = RnsPoly::uniform(n, &ctx.ntt);
+        let e = RnsPoly::from_signed(&poly::sample_gaussian(n, poly::ERROR_STD_DEV), &ctx.ntt);
+        let b = a.mul(&s, &ctx.ntt).add(&e, &ctx.ntt).negate(&ctx.ntt);
+
+        PublicKey { b, a }
+    }
+
+    /// Relinearization keys for size-3 ciphertexts
+    pub fn relin_keys(&self) -> RelinKeys {
+        let tables = &self.context.keyswitch.tables;
+        let s = &self.secret_key.s;
+        let s2 = s.mul(s, tables);
+        RelinKeys { key: self.context.keyswitch.generate_key(s, &s2) }
+    }
+
+    /// Galois keys for the minimal element set covering `steps`, plus
+    /// X -> X^-1 if `conjugate` is set
+    pub fn galois_keys(&self, steps: &[i64], conjugate: bool) -> GaloisKeys {
+        let n = self.context.params.poly_degree;
+        let mut elements = poly::galois_elements_for_steps(steps, n);
+        if conjugate {
+            elements.push(poly::galois_element_conjugate(n));
+        }
+        self.galois_keys_for_elements(&elements)
+    }
+
+    /// Galois keys for arbitrary odd elements g
+    pub fn galois_keys_for_elements(&self, elements: &[u64]) -> GaloisKeys {
+        let tables = &self.context.keyswitch.tables;
+        let s = &self.secret_key.s;
+        let keys = elements.iter()
+            .map(|&g| (g, self.context.keyswitch.generate_key(s, &s.automorphism(g, tables))))
+            .collect();
+        GaloisKeys { keys }
+    }
+}
+
+// ==================== ENCRYPTION ====================
+
+pub struct Encryptor {
+    context: CKKSContext,
+    public_key: PublicKey,
+}
+
+impl Encryptor {
+    pub fn new(context: &CKKSContext, public_key: &PublicKey) -> Self {
+        Self { context: context.clone(), public_key: public_key.clone() }
+    }
+
+    /// ct = (b·u + e1 + m, a·u + e2) at the plaintext's level and scale
+    pub fn encrypt(&self, plain: &Plaintext) -> Ciphertext {
+        let level = plain.level();
+        let n = self.context.params.poly_degree;
+        let tables = self.context.tables(level);
+
+        let u = RnsPoly::from_signed(&poly::sample_ternary(n), tables);
+        let e1 = RnsPoly::from_signed(&poly::sample_gaussian(n, poly::ERROR_STD_DEV), tables);
+        let e2 = RnsPoly::from_signed(&poly::sample_gaussian(n, poly::ERROR_STD_DEV), tables);
+
+        let limbs = tables.len();
+        let c0 = self.public_key.b.truncated(limbs).mul(&u, tables)
+            .add(&e1, tables)
+            .add(&plain.poly, tables);
+        let c1 = self.public_key.a.truncated(limbs).mul(&u, tables).add(&e2, tables);
+
+        Ciphertext { polys: vec![c0, c1], scale: plain.scale, primes_per_level: plain.primes_per_level }
+    }
+}
+
+pub struct Decryptor {
+    pub(crate) context: CKKSContext,
+    secret_key: SecretKey,
+}
+
+impl Decryptor {
+    pub fn new(context: &CKKSContext, secret_key: &SecretKey) -> Self {
+        Self { context: context.clone(), secret_key: secret_key.clone() }
+    }
+
+    /// Plaintext c0 + c1·s + c2·s² + ... at the ciphertext's level and scale
+    pub fn decrypt(&self, ct: &Ciphertext) -> Plaintext {
+        let level = ct.level();
+        let tables = self.context.tables(level);
+        let s = self.secret_key.s.truncated(tables.len());
+
+        // Horner: ((c_k·s + c_{k-1})·s + ...)·s + c0
+        let mut acc = ct.polys[ct.size() - 1].clone();
+        for c in ct.polys[..ct.size() - 1].iter().rev() {
+            acc = acc.mul(&s, tables).add(c, tables);
+        }
+        Plaintext { poly: acc, scale: ct.scale, primes_per_level: ct.primes_per_level }
+    }
+
+    /// Decrypts and decodes all slots
+    pub fn decrypt_complex(&self, ct: &Ciphertext) -> Vec<Complex64> {
+        self.context.decode(&self.decrypt(ct))
+    }
+
+    /// Decrypts and decodes the real parts of all slots
+    pub fn decrypt_real(&self, ct: &Ciphertext) -> Vec<f64> {
+        self.context.decode_real(&self.decrypt(ct))
+    }
+}
+
+// ==================== EVALUATOR ====================
+
+fn relative_difference(a: f64, b: f64) -> f64 {
+    (a - b).abs() / a.abs().max(b.abs())
+}
+
+fn scales_match(a: f64, b: f64) -> bool {
+    relative_difference(a, b) <= SCALE_TOLERANCE
+}
+
+/// CKKS evaluator
+///
+/// With `auto_rescale` every multiplication is followed by a rescale,
+/// constants are encoded at the prime being dropped so the scale is kept,
+/// and additions bring operands at different scales or levels together
+/// by a constant multiplication and rescale of the higher one.
+pub struct Evaluator {
+    pub(crate) context: CKKSContext,
+    pub auto_rescale: bool,
 }
 
-/// CKKS rotation key operation simulation
-pub fn rotate_polynomial(poly: &[u64], steps: isize, modulus: u64) -> Vec<u64> {
-    let n = poly.len();
-    let steps_mod = ((steps % n as isize) + n as isize) as usize % n;
-    
-    let mut rotated = vec![0u64; n];
-    
-    for i in 0..n {
-        let source_idx = i;
-        let target_idx = (i + steps_mod) % n;
-        rotated[target_idx] = poly[source_idx];
+impl Evaluator {
+    pub fn new(context: &CKKSContext) -> Self {
+        Self { context: context.clone(), auto_rescale: false }
+    }
+
+    pub fn with_auto_rescale(context: &CKKSContext) -> Self {
+        Self { context: context.clone(), auto_rescale: true }
+    }
+
+    // --- Levels and scales ---
+
+    /// Divides by q_l in RNS: c' = (c - [c]_{q_l}) / q_l mod Q_{l-1}, with
+    /// the centered remainder so the division rounds; the scale becomes Δ/q_l
+    ///
+    /// A composite q_l is divided out one prime at a time, which adds at
+    /// most one more rounding of 1/2.
+    pub fn rescale_to_next(&self, ct: &Ciphertext) -> Result<Ciphertext, String> {
+        let mut result = ct.clone();
+        self.rescale_to_next_inplace(&mut result)?;
+        Ok(result)
     }
-    
-    // Handle sign changes for odd powers
-    if steps_mod % 2 == 1 {
-        for i in 0..n {
-            if i % 2 == 1 {
-                rotated[i] = modular::mod_sub(0, rotated[i], modulus);
+
+    pub fn rescale_to_next_inplace(&self, ct: &mut Ciphertext) -> Result<(), String> {
+        let level = ct.level();
+        if level == 0 {
+            return Err("cannot rescale a level-0 ciphertext".to_string());
+        }
+        for c in ct.polys.iter_mut() {
+            for _ in 0..ct.primes_per_level {
+                self.divide_by_last(c);
             }
         }
+        ct.scale /= self.context.level_modulus(level);
+        Ok(())
+    }
+
+    /// Drops q_l without dividing: same message and scale one level lower
+    pub fn mod_switch_to_next(&self, ct: &Ciphertext) -> Result<Ciphertext, String> {
+        let mut result = ct.clone();
+        self.mod_switch_to_next_inplace(&mut result)?;
+        Ok(result)
+    }
+
+    pub fn mod_switch_to_next_inplace(&self, ct: &mut Ciphertext) -> Result<(), String> {
+        if ct.level() == 0 {
+            return Err("ciphertext is already at level 0".to_string());
+        }
+        let limbs = self.context.limb_count(ct.level() - 1);
+        for c in ct.polys.iter_mut() {
+            c.limbs.truncate(limbs);
+        }
+        Ok(())
+    }
+
+    /// Drops primes down to `level` (no-op if already there)
+    pub fn mod_switch_to(&self, ct: &Ciphertext, level: usize) -> Result<Ciphertext, String> {
+        let mut result = ct.clone();
+        self.mod_switch_to_inplace(&mut result, level)?;
+        Ok(result)
+    }
+
+    pub fn mod_switch_to_inplace(&self, ct: &mut Ciphertext, level: usize) -> Result<(), String> {
+        if level > ct.level() {
+            return Err(format!(
+                "cannot switch a level-{} ciphertext up to level {}",
+                ct.level(), level
+            ));
+        }
+        let limbs = self.context.limb_count(level);
+        for c in ct.polys.iter_mut() {
+            c.limbs.truncate(limbs);
+        }
+        Ok(())
+    }
+
+    /// Lifts a level-0 ciphertext to the top level by reading its centered
+    /// residues mod q_0 as integers; it then decrypts to m + q_0·I(X)
+    /// for a small integer polynomial I
+    pub fn mod_raise(&self, ct: &Ciphertext) -> Result<Ciphertext, String> {
+        if ct.level() != 0 {
+            return Err(format!("mod_raise expects a level-0 ciphertext, got level {}", ct.level()));
+        }
+        let n = self.context.params.poly_degree;
+        let base = &self.context.bases[0];
+        let top = &self.context.bases[self.context.max_level()];
+        let polys = ct.polys.iter()
+            .map(|c| {
+                let mut limbs = vec![vec![0u64; n]; top.moduli.len()];
+                let mut residues = vec![0u64; c.limbs.len()];
+                for k in 0..n {
+                    for (r, limb) in residues.iter_mut().zip(&c.limbs) {
+                        *r = limb[k];
+                    }
+                    let lifted = top.decompose(&base.compose_centered(&residues));
+                    for (limb, r) in limbs.iter_mut().zip(lifted) {
+                        limb[k] = r;
+                    }
+                }
+                RnsPoly { limbs }
+            })
+            .collect();
+        Ok(Ciphertext { polys, scale: ct.scale, primes_per_level: ct.primes_per_level })
+    }
+
+    /// Brings the scale to `target` at the cost of one level
+    fn adjust_scale(&self, ct: &mut Ciphertext, target: f64) -> Result<(), String> {
+        if ct.level() == 0 {
+            return Err(format!("cannot adjust scale {:e} to {:e} at level 0", ct.scale, target));
+        }
+        *ct = self.multiply_const_to(ct, 1.0, ct.level() - 1, target)?;
+        Ok(())
+    }
+
+    /// Brings `a` and a copy of `b` to a common level, and in auto-rescale
+    /// mode to a common scale; fails on a remaining scale mismatch
+    fn match_operands(&self, a: &mut Ciphertext, b: &Ciphertext) -> Result<Ciphertext, String> {
+        let mut b = b.clone();
+        if self.auto_rescale && !scales_match(a.scale, b.scale) {
+            if b.level() > a.level() {
+                self.adjust_scale(&mut b, a.scale)?;
+            } else {
+                self.adjust_scale(a, b.scale)?;
+            }
+        }
+        self.align_levels(a, &mut b)?;
+        if !scales_match(a.scale, b.scale) {
+            return Err(format!(
+                "scale mismatch: {:e} vs {:e} (relative difference {:.2e}) at level {}; \
+                 rescale the operands to a common scale or enable auto_rescale",
+                a.scale, b.scale, relative_difference(a.scale, b.scale), a.level()
+            ));
+        }
+        Ok(b)
+    }
+
+    fn align_levels(&self, a: &mut Ciphertext, b: &mut Ciphertext) -> Result<(), String> {
+        let level = a.level().min(b.level());
+        self.mod_switch_to_inplace(a, level)?;
+        self.mod_switch_to_inplace(b, level)
+    }
+
+    fn rescale_if_auto(&self, ct: &mut Ciphertext) -> Result<(), String> {
+        if self.auto_rescale {
+            self.rescale_to_next_inplace(ct)?;
+        }
+        Ok(())
+    }
+
+    // --- Ciphertext-Ciphertext ---
+
+    pub fn add(&self, a: &Ciphertext, b: &Ciphertext) -> Result<Ciphertext, String> {
+        let mut result = a.clone();
+        self.add_inplace(&mut result, b)?;
+        Ok(result)
+    }
+
+    pub fn add_inplace(&self, a: &mut Ciphertext, b: &Ciphertext) -> Result<(), String> {
+        let mut b = self.match_operands(a, b)?;
+        let tables = self.context.tables(a.level());
+        if a.size() < b.size() {
+            std::mem::swap(&mut a.polys, &mut b.polys);
+        }
+        for (x, y) in a.polys.iter_mut().zip(&b.polys) {
+            *x = x.add(y, tables);
+        }
+        Ok(())
+    }
+
+    pub fn sub(&self, a: &Ciphertext, b: &Ciphertext) -> Result<Ciphertext, String> {
+        let mut result = a.clone();
+        self.sub_inplace(&mut result, b)?;
+        Ok(result)
+    }
+
+    pub fn sub_inplace(&self, a: &mut Ciphertext, b: &Ciphertext) -> Result<(), String> {
+        self.add_inplace(a, &self.negate(b))
+    }
+
+    pub fn negate(&self, ct: &Ciphertext) -> Ciphertext {
+        let mut result = ct.clone();
+        self.negate_inplace(&mut result);
+        result
+    }
+
+    pub fn negate_inplace(&self, ct: &mut Ciphertext) {
+        let tables = self.context.tables(ct.level());
+        for c in ct.polys.iter_mut() {
+            *c = c.negate(tables);
+        }
+    }
+
+    /// Multiplies every slot by i, i.e. the polynomial by X^(N/2); exact
+    /// and free of levels
+    pub fn multiply_by_i(&self, ct: &Ciphertext) -> Ciphertext {
+        let tables = self.context.tables(ct.level());
+        let half = self.context.params.poly_degree / 2;
+        let polys = ct.polys.iter().map(|c| c.mul_monomial(half, tables)).collect();
+        Ciphertext { polys, scale: ct.scale, primes_per_level: ct.primes_per_level }
+    }
+
+    /// Tensor product at the common level; the scale is Δ_a·Δ_b
+    ///
+    /// The result has size |ct1| + |ct2| - 1 and should be relinearized.
+    pub fn multiply(&self, a: &Ciphertext, b: &Ciphertext) -> Result<Ciphertext, String> {
+        let mut result = a.clone();
+        self.multiply_inplace(&mut result, b)?;
+        Ok(result)
+    }
+
+    pub fn multiply_inplace(&self, a: &mut Ciphertext, b: &Ciphertext) -> Result<(), String> {
+        self.tensor_inplace(a, b)?;
+        self.rescale_if_auto(a)
+    }
+
+    fn tensor_inplace(&self, a: &mut Ciphertext, b: &Ciphertext) -> Result<(), String> {
+        let mut b = b.clone();
+        self.align_levels(a, &mut b)?;
+        a.polys = poly::tensor(&a.polys, &b.polys, self.context.tables(a.level()));
+        a.scale *= b.scale;
+        Ok(())
+    }
+
+    pub fn square(&self, ct: &Ciphertext) -> Result<Ciphertext, String> {
+        self.multiply(ct, ct)
+    }
+
+    /// Reduces a size-3 ciphertext back to size 2
+    pub fn relinearize(&self, ct: &Ciphertext, relin_keys: &RelinKeys) -> Result<Ciphertext, String> {
+        let mut result = ct.clone();
+        self.relinearize_inplace(&mut result, relin_keys)?;
+        Ok(result)
+    }
+
+    pub fn relinearize_inplace(&self, ct: &mut Ciphertext, relin_keys: &RelinKeys) -> Result<(), String> {
+        match ct.size() {
+            2 => return Ok(()),
+            3 => {}
+            size => return Err(format!("cannot relinearize a size-{} ciphertext, expected 3", size)),
+        }
+        let tables = self.context.tables(ct.level());
+        let c2 = ct.polys.pop().unwrap();
+        let (k0, k1) = self.context.keyswitch.switch(&c2, &relin_keys.key);
+        ct.polys[0] = ct.polys[0].add(&k0, tables);
+        ct.polys[1] = ct.polys[1].add(&k1, tables);
+        Ok(())
+    }
+
+    /// Multiplication and relinearization (then a rescale in auto mode)
+    pub fn multiply_relin(&self, a: &Ciphertext, b: &Ciphertext, relin_keys: &RelinKeys) -> Result<Ciphertext, String> {
+        let mut result = a.clone();
+        self.tensor_inplace(&mut result, b)?;
+        self.relinearize_inplace(&mut result, relin_keys)?;
+        self.rescale_if_auto(&mut result)?;
+        Ok(result)
+    }
+
+    // --- Rotations ---
+
+    /// Applies X -> X^g and switches back to s with the matching Galois key
+    pub fn apply_galois(&self, ct: &Ciphertext, galois_elt: u64, galois_keys: &GaloisKeys) -> Result<Ciphertext, String> {
+        if ct.size() != 2 {
+            return Err(format!("cannot rotate a size-{} ciphertext, relinearize first", ct.size()));
+        }
+        let key = galois_keys.get(galois_elt)?;
+        let c0 = ct.polys[0].automorphism(galois_elt, self.context.tables(ct.level()));
+        let c1 = ct.polys[1].automorphism(galois_elt, self.context.tables(ct.level()));
+        Ok(self.switch_galois(c0, &c1, key, ct))
+    }
+
+    /// (c0 + switch(c1)_0, switch(c1)_1) for automorphism images c0, c1 of `ct`
+    fn switch_galois(&self, c0: RnsPoly, c1: &RnsPoly, key: &KeySwitchKey, ct: &Ciphertext) -> Ciphertext {
+        let (k0, k1) = self.context.keyswitch.switch(c1, key);
+        let c0 = c0.add(&k0, self.context.tables(ct.level()));
+        Ciphertext { polys: vec![c0, k1], scale: ct.scale, primes_per_level: ct.primes_per_level }
+    }
+
+    /// Rotates the slots left by `step` (right if negative)
+    ///
+    /// Uses the key for 5^step when present and otherwise composes the
+    /// rotation from power-of-two steps. Steps only matter modulo the slot
+    /// count, since a sparse n-slot vector repeats with period n, so a key
+    /// for step ± n serves as well.
+    pub fn rotate(&self, ct: &Ciphertext, step: i64, galois_keys: &GaloisKeys) -> Result<Ciphertext, String> {
+        let n = self.context.params.poly_degree;
+        let slots = self.context.slot_count() as i64;
+        let (step, direct) = self.reduce_step(step, galois_keys);
+        if step == 0 {
+            return Ok(ct.clone());
+        }
+        if let Some(g) = direct {
+            return self.apply_galois(ct, g, galois_keys);
+        }
+
+        let mut result = ct.clone();
+        for bit in (0..slots.trailing_zeros()).filter(|b| step >> b & 1 == 1) {
+            let g = poly::galois_element_for_step(1 << bit, n);
+            result = self.apply_galois(&result, g, galois_keys).map_err(|_| {
+                format!("no Galois key for step {} or for its power-of-two part {}", step, 1 << bit)
+            })?;
+        }
+        Ok(result)
+    }
+
+    /// `step` modulo the slot count, and the Galois element of the first
+    /// of step, reduced and reduced - slots that `galois_keys` has a key for
+    fn reduce_step(&self, step: i64, galois_keys: &GaloisKeys) -> (i64, Option<u64>) {
+        let n = self.context.params.poly_degree;
+        let slots = self.context.slot_count() as i64;
+        let reduced = step.rem_euclid(slots);
+        let direct = [step, reduced, reduced - slots]
+            .into_iter()
+            .map(|s| poly::galois_element_for_step(s, n))
+            .find(|g| galois_keys.keys.contains_key(g));
+        (reduced, direct)
+    }
+
+    /// Rotations of one ciphertext by several steps with a shared
+    /// decomposition of c1
+    ///
+    /// The digits are decomposed and transformed once; each automorphism
+    /// is then a permutation of their NTT slots followed by the key inner
+    /// product. Steps are reduced as in `rotate`, and results are identical
+    /// to it with a direct key, which every step needs here.
+    pub fn rotate_hoisted(&self, ct: &Ciphertext, steps: &[i64], galois_keys: &GaloisKeys) -> Result<Vec<Ciphertext>, String> {
+        if ct.size() != 2 {
+            return Err(format!("cannot rotate a size-{} ciphertext, relinearize first", ct.size()));
+        }
+        let n = self.context.params.poly_degree;
+        let tables = self.context.tables(ct.level());
+        let digits = self.context.keyswitch.decompose(&ct.polys[1]);
+
+        steps.iter()
+            .map(|&step| {
+                let (reduced, direct) = self.reduce_step(step, galois_keys);
+                if reduced == 0 {
+                    return Ok(ct.clone());
+                }
+                let g = direct.ok_or_else(|| format!("no Galois key for rotation step {}", step))?;
+                let key = galois_keys.get(g)?;
+                let perm = poly::galois_ntt_permutation(g, n);
+                let rotated: Vec<RnsPoly> = digits.iter().map(|d| d.permuted(&perm)).collect();
+                let (k0, k1) = self.context.keyswitch.apply(&rotated, key);
+                let c0 = ct.polys[0].automorphism(g, tables).add(&k0, tables);
+                Ok(Ciphertext { polys: vec![c0, k1], scale: ct.scale, primes_per_level: ct.primes_per_level })
+            })
+            .collect()
+    }
+
+    /// Complex conjugation of every slot (X -> X^-1)
+    pub fn conjugate(&self, ct: &Ciphertext, galois_keys: &GaloisKeys) -> Result<Ciphertext, String> {
+        self.apply_galois(ct, poly::galois_element_conjugate(self.context.params.poly_degree), galois_keys)
+    }
+
+    // --- Plaintext-Ciphertext ---
+
+    /// Requires matching scales; the plaintext may sit at a higher level
+    pub fn add_plain(&self, ct: &Ciphertext, plain: &Plaintext) -> Result<Ciphertext, String> {
+        let mut result = ct.clone();
+        self.add_plain_inplace(&mut result, plain)?;
+        Ok(result)
+    }
+
+    pub fn add_plain_inplace(&self, ct: &mut Ciphertext, plain: &Plaintext) -> Result<(), String> {
+        let p = self.plain_at_level(ct, plain)?;
+        if !scales_match(ct.scale, plain.scale) {
+            return Err(format!(
+                "scale mismatch: ciphertext {:e} vs plaintext {:e} (relative difference {:.2e}); \
+                 encode the plaintext at the ciphertext's scale",
+                ct.scale, plain.scale, relative_difference(ct.scale, plain.scale)
+            ));
+        }
+        ct.polys[0] = ct.polys[0].add(&p, self.context.tables(ct.level()));
+        Ok(())
+    }
+
+    pub fn sub_plain(&self, ct: &Ciphertext, plain: &Plaintext) -> Result<Ciphertext, String> {
+        let negated = Plaintext {
+            poly: plain.poly.negate(&self.context.ntt),
+            scale: plain.scale,
+            primes_per_level: plain.primes_per_level,
+        };
+        self.add_plain(ct, &negated)
+    }
+
+    /// Slot-wise product with a plaintext; the scale is Δ_ct·Δ_pt
+    pub fn mul_plain(&self, ct: &Ciphertext, plain: &Plaintext) -> Result<Ciphertext, String> {
+        let mut result = ct.clone();
+        self.mul_plain_inplace(&mut result, plain)?;
+        Ok(result)
+    }
+
+    pub fn mul_plain_inplace(&self, ct: &mut Ciphertext, plain: &Plaintext) -> Result<(), String> {
+        let p = self.plain_at_level(ct, plain)?;
+        let tables = self.context.tables(ct.level());
+        let mut p_ntt = p;
+        for (limb, tab) in p_ntt.limbs.iter_mut().zip(tables) {
+            tab.forward(limb);
+        }
+        for c in ct.polys.iter_mut() {
+            for ((limb, tab), w) in c.limbs.iter_mut().zip(tables).zip(&p_ntt.limbs) {
+                tab.forward(limb);
+                for (x, &y) in limb.iter_mut().zip(w) {
+                    *x = modular::mod_mul(*x, y, tab.modulus);
+                }
+                tab.inverse(limb);
+            }
+        }
+        ct.scale *= plain.scale;
+        self.rescale_if_auto(ct)
+    }
+
+    /// Plaintext polynomial restricted to the ciphertext's level
+    fn plain_at_level(&self, ct: &mut Ciphertext, plain: &Plaintext) -> Result<RnsPoly, String> {
+        if plain.level() < ct.level() {
+            self.mod_switch_to_inplace(ct, plain.level())?;
+        }
+        Ok(plain.poly.truncated(self.context.limb_count(ct.level())))
+    }
+
+    // --- Constants ---
+
+    /// Adds `value` to every slot at the ciphertext's scale
+    pub fn add_const(&self, ct: &Ciphertext, value: Complex64) -> Result<Ciphertext, String> {
+        let mut result = ct.clone();
+        let n = self.context.params.poly_degree;
+        let tables = self.context.tables(ct.level());
+        // the constant c = a + bi is the polynomial a + b·X^(N/2)
+        for (idx, x) in [(0, value.re), (n / 2, value.im)] {
+            let c = (x * ct.scale).round();
+            if !c.is_finite() || c.abs() >= 2f64.powi(126) {
+                return Err(format!("constant {} is too large for scale {:e}", x, ct.scale));
+            }
+            let c = c as i128;
+            for (limb, tab) in result.polys[0].limbs.iter_mut().zip(tables) {
+                let q = tab.modulus;
+                limb[idx] = modular::mod_add(limb[idx], c.rem_euclid(q as i128) as u64, q);
+            }
+        }
+        Ok(result)
+    }
+
+    /// Multiplies every slot by `value`
+    ///
+    /// The constant is encoded at q_l in auto-rescale mode, so the
+    /// following rescale restores the input scale exactly, and at the
+    /// default scale otherwise.
+    pub fn multiply_const(&self, ct: &Ciphertext, value: Complex64) -> Result<Ciphertext, String> {
+        let level = ct.level();
+        let n = self.context.params.poly_degree;
+        let tables = self.context.tables(level);
+        let const_scale = if self.auto_rescale {
+            self.context.level_modulus(level)
+        } else {
+            self.context.params.scale
+        };
+
+        let mut coeffs = [0i128; 2];
+        for (c, x) in coeffs.iter_mut().zip([value.re, value.im]) {
+            let scaled = (x * const_scale).round();
+            if !scaled.is_finite() || scaled.abs() >= 2f64.powi(126) {
+                return Err(format!("constant {} is too large for scale {:e}", x, const_scale));
+            }
+            *c = scaled as i128;
+        }
+
+        let mut result = ct.clone();
+        if value.im == 0.0 {
+            let scalars: Vec<u64> = tables.iter()
+                .map(|t| coeffs[0].rem_euclid(t.modulus as i128) as u64)
+                .collect();
+            for c in result.polys.iter_mut() {
+                *c = c.mul_scalars(&scalars, tables);
+            }
+        } else {
+            // a + bi is the polynomial a + b·X^(N/2)
+            let limbs = tables.iter()
+                .map(|t| {
+                    let mut limb = vec![0u64; n];
+                    limb[0] = coeffs[0].rem_euclid(t.modulus as i128) as u64;
+                    limb[n / 2] = coeffs[1].rem_euclid(t.modulus as i128) as u64;
+                    limb
+                })
+                .collect();
+            let constant = RnsPoly { limbs };
+            for c in result.polys.iter_mut() {
+                *c = c.mul(&constant, tables);
+            }
+        }
+        result.scale *= const_scale;
+        self.rescale_if_auto(&mut result)?;
+        Ok(result)
+    }
+
+    /// Multiplies by `value` and rescales once so that the result sits
+    /// exactly at `scale` on `level` (below the ciphertext's level)
+    ///
+    /// The constant is encoded at scale·q_(level+1)/Δ, which absorbs any
+    /// scale difference; its rounding only perturbs the value.
+    pub fn multiply_const_to(&self, ct: &Ciphertext, value: f64, level: usize, scale: f64) -> Result<Ciphertext, String> {
+        if level >= ct.level() {
+            return Err(format!(
+                "cannot move a level-{} ciphertext to level {} with a rescale",
+                ct.level(), level
+            ));
+        }
+        let mut result = self.mod_switch_to(ct, level + 1)?;
+        let q_next = self.context.level_modulus(level + 1);
+        let c = (value * scale * q_next / result.scale).round();
+        if !c.is_finite() || c.abs() >= 2f64.powi(126) {
+            return Err(format!(
+                "cannot bring scale {:e} to {:e}: constant {:e} is out of range",
+                result.scale, scale, c
+            ));
+        }
+
+        let tables = self.context.tables(level + 1);
+        let scalars: Vec<u64> = tables.iter()
+            .map(|t| (c as i128).rem_euclid(t.modulus as i128) as u64)
+            .collect();
+        for p in result.polys.iter_mut() {
+            *p = p.mul_scalars(&scalars, tables);
+        }
+        self.rescale_to_next_inplace(&mut result)?;
+        result.scale = scale;
+        Ok(result)
+    }
+
+    fn divide_by_last(&self, c: &mut RnsPoly) {
+        let level = c.level();
+        let q_last = self.context.params.cipher_moduli[level];
+        let inv_last = &self.context.inv_last[level];
+        let last = c.limbs.pop().unwrap();
+        let delta = keyswitch::remainders(&last, q_last, None);
+        for (j, limb) in c.limbs.iter_mut().enumerate() {
+            let q = self.context.params.cipher_moduli[j];
+            for (x, &d) in limb.iter_mut().zip(&delta) {
+                let d = d.rem_euclid(q as i128) as u64;
+                *x = modular::mod_mul(modular::mod_sub(*x, d, q), inv_last[j], q);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn context(bit_sizes: &[u32]) -> CKKSContext {
+        let params = CKKSParameters::new(2048, bit_sizes, 2f64.powi(40)).unwrap();
+        CKKSContext::with_params(params).unwrap()
+    }
+
+    fn max_error(a: &[f64], b: &[f64]) -> f64 {
+        a.iter().zip(b).map(|(x, y)| (x - y).abs()).fold(0.0, f64::max)
+    }
+
+    fn max_complex_error(a: &[Complex64], b: &[Complex64]) -> f64 {
+        a.iter().zip(b).map(|(x, y)| (x - y).norm()).fold(0.0, f64::max)
+    }
+
+    fn sine(i: usize) -> f64 {
+        (i as f64 * 0.01).sin() * 2.0
+    }
+
+    #[test]
+    fn context_validation() {
+        let context = context(&[60, 40, 40, 60]);
+        assert_eq!(context.max_level(), 2);
+        assert_eq!(context.slot_count(), 1024);
+        assert_eq!(context.bases.len(), 3);
+
+        let mut params = CKKSParameters::new(2048, &[60, 40, 60], 2f64.powi(40)).unwrap();
+        params.cipher_moduli.push(params.cipher_moduli[1]);
+        assert!(CKKSContext::with_params(params).is_err());
+        let params = CKKSParameters::new(2048, &[60, 40, 60], 0.5).unwrap();
+        assert!(CKKSContext::with_params(params).is_err());
+        assert!(CKKSParameters::new(2048, &[60], 2f64.powi(40)).is_err());
+        assert!(context.encode(&[], 2f64.powi(40), 3).is_err());
+    }
+
+    #[test]
+    fn encrypt_decrypt() {
+        let context = context(&[60, 40, 40, 60]);
+        let keygen = KeyGenerator::new(&context);
+        let encryptor = Encryptor::new(&context, &keygen.public_key());
+        let decryptor = Decryptor::new(&context, &keygen.secret_key());
+
+        let x: Vec<f64> = (0..1024).map(sine).collect();
+        let ct = encryptor.encrypt(&context.encode_real(&x).unwrap());
+        assert_eq!((ct.size(), ct.level()), (2, 2));
+        assert!(max_error(&decryptor.decrypt_real(&ct), &x) < 1e-6);
+
+        let z: Vec<Complex64> = (0..1024).map(|i| Complex64::new(i as f64 / 1024.0, -0.5)).collect();
+        let ct = encryptor.encrypt(&context.encode(&z, 2f64.powi(40), 0).unwrap());
+        assert_eq!(ct.level(), 0);
+        assert!(max_complex_error(&decryptor.decrypt_complex(&ct), &z) < 1e-6);
+    }
+
+    #[test]
+    fn rescale_divides_by_the_dropped_prime() {
+        let context = context(&[60, 40, 40, 60]);
+        let keygen = KeyGenerator::new(&context);
+        let encryptor = Encryptor::new(&context, &keygen.public_key());
+        let decryptor = Decryptor::new(&context, &keygen.secret_key());
+        let ev = Evaluator::new(&context);
+
+        let z: Vec<Complex64> = (0..1024).map(|i| Complex64::new(i as f64 / 1024.0, -0.5)).collect();
+        let ct = encryptor.encrypt(&context.encode(&z, 2f64.powi(80), 2).unwrap());
+
+        let rescaled = ev.rescale_to_next(&ct).unwrap();
+        assert_eq!(rescaled.level(), 1);
+        assert_eq!(rescaled.scale, 2f64.powi(80) / context.params.cipher_moduli[2] as f64);
+        assert!(max_complex_error(&decryptor.decrypt_complex(&rescaled), &z) < 1e-6);
+
+        let switched = ev.mod_switch_to_next(&rescaled).unwrap();
+        assert_eq!((switched.level(), switched.scale), (0, rescaled.scale));
+        assert!(max_complex_error(&decryptor.decrypt_complex(&switched), &z) < 1e-6);
+        assert!(ev.rescale_to_next(&switched).is_err());
+        assert!(ev.mod_switch_to_next(&switched).is_err());
+        assert!(ev.mod_switch_to(&switched, 1).is_err());
+    }
+
+    #[test]
+    fn multiplication_and_relinearization() {
+        let context = context(&[60, 40, 40, 40, 60]);
+        let keygen = KeyGenerator::new(&context);
+        let encryptor = Encryptor::new(&context, &keygen.public_key());
+        let decryptor = Decryptor::new(&context, &keygen.secret_key());
+        let ev = Evaluator::new(&context);
+        let encrypt = |values: &[f64]| encryptor.encrypt(&context.encode_real(values).unwrap());
+
+        let rk = keygen.relin_keys();
+        let x: Vec<f64> = (0..1024).map(sine).collect();
+        let y: Vec<f64> = (0..1024).map(|i| (i as f64 * 0.03).cos()).collect();
+        let (cx, cy) = (encrypt(&x), encrypt(&y));
+        let xy: Vec<f64> = x.iter().zip(&y).map(|(a, b)| a * b).collect();
+
+        let tensor = ev.multiply(&cx, &cy).unwrap();
+        assert_eq!(tensor.size(), 3);
+        assert_eq!(tensor.scale, cx.scale * cy.scale);
+        assert!(max_error(&decryptor.decrypt_real(&tensor), &xy) < 1e-5);
+        let product = ev.rescale_to_next(&ev.relinearize(&tensor, &rk).unwrap()).unwrap();
+        assert_eq!((product.size(), product.level()), (2, 2));
+        assert!(max_error(&decryptor.decrypt_real(&product), &xy) < 1e-5);
+        assert!(ev.relinearize(&ev.multiply(&tensor, &cx).unwrap(), &rk).is_err());
+
+        let plain = context.encode_real(&y).unwrap();
+        let product = ev.rescale_to_next(&ev.mul_plain(&cx, &plain).unwrap()).unwrap();
+        assert!(max_error(&decryptor.decrypt_real(&product), &xy) < 1e-5);
+
+        let half = ev.rescale_to_next(&ev.multiply_const(&cx, Complex64::new(0.5, 0.0)).unwrap()).unwrap();
+        let expected: Vec<f64> = x.iter().map(|a| a * 0.5).collect();
+        assert!(max_error(&decryptor.decrypt_real(&half), &expected) < 1e-5);
+        let imaginary = ev.rescale_to_next(&ev.multiply_const(&cx, Complex64::new(0.0, 1.0)).unwrap()).unwrap();
+        let expected: Vec<Complex64> = x.iter().map(|&a| Complex64::new(0.0, a)).collect();
+        assert!(max_complex_error(&decryptor.decrypt_complex(&imaginary), &expected) < 1e-5);
+        let shifted = ev.add_const(&cx, Complex64::new(1.5, 0.0)).unwrap();
+        let expected: Vec<f64> = x.iter().map(|a| a + 1.5).collect();
+        assert!(max_error(&decryptor.decrypt_real(&shifted), &expected) < 1e-6);
+    }
+
+    #[test]
+    fn scale_mismatch_is_an_error() {
+        let context = context(&[60, 40, 40, 60]);
+        let keygen = KeyGenerator::new(&context);
+        let encryptor = Encryptor::new(&context, &keygen.public_key());
+        let decryptor = Decryptor::new(&context, &keygen.secret_key());
+        let ev = Evaluator::new(&context);
+        let encrypt = |values: &[f64]| encryptor.encrypt(&context.encode_real(values).unwrap());
+
+        let x: Vec<f64> = (0..1024).map(sine).collect();
+        let cx = encrypt(&x);
+        let product = ev.rescale_to_next(&ev.multiply_relin(&cx, &cx, &keygen.relin_keys()).unwrap()).unwrap();
+
+        assert!(ev.add(&product, &cx).unwrap_err().contains("scale mismatch"));
+        assert!(ev.sub(&cx, &product).unwrap_err().contains("scale mismatch"));
+        let plain = context.encode_real(&x).unwrap();
+        assert!(ev.add_plain(&product, &plain).unwrap_err().contains("scale mismatch"));
+
+        // Levels alone are aligned when scales agree
+        let lower = ev.mod_switch_to_next(&cx).unwrap();
+        let sum = ev.add(&cx, &lower).unwrap();
+        assert_eq!(sum.level(), 1);
+        let doubled: Vec<f64> = x.iter().map(|a| 2.0 * a).collect();
+        assert!(max_error(&decryptor.decrypt_real(&sum), &doubled) < 1e-6);
+    }
+
+    #[test]
+    fn auto_rescale_keeps_operands_compatible() {
+        let context = context(&[60, 40, 40, 40, 60]);
+        let keygen = KeyGenerator::new(&context);
+        let encryptor = Encryptor::new(&context, &keygen.public_key());
+        let decryptor = Decryptor::new(&context, &keygen.secret_key());
+        let ev = Evaluator::with_auto_rescale(&context);
+        let encrypt = |values: &[f64]| encryptor.encrypt(&context.encode_real(values).unwrap());
+
+        let rk = keygen.relin_keys();
+        let x: Vec<f64> = (0..1024).map(sine).collect();
+        let y: Vec<f64> = (0..1024).map(|i| (i as f64 * 0.03).cos()).collect();
+        let (cx, cy) = (encrypt(&x), encrypt(&y));
+
+        let product = ev.multiply_relin(&cx, &cy, &rk).unwrap();
+        assert_eq!(product.level(), 2);
+        let sum = ev.add(&product, &cx).unwrap();
+        let expected: Vec<f64> = x.iter().zip(&y).map(|(a, b)| a * b + a).collect();
+        assert!(max_error(&decryptor.decrypt_real(&sum), &expected) < 1e-5);
+
+        // multiply_const keeps the scale exactly, so the chain continues
+        let scaled = ev.multiply_const(&sum, Complex64::new(3.0, 0.0)).unwrap();
+        assert_eq!(scaled.scale, sum.scale);
+        let chained = ev.add(&scaled, &cy).unwrap();
+        let expected: Vec<f64> = expected.iter().zip(&y).map(|(a, b)| 3.0 * a + b).collect();
+        assert!(max_error(&decryptor.decrypt_real(&chained), &expected) < 1e-4);
+        let squared = ev.multiply_relin(&chained, &chained, &rk).unwrap();
+        let expected: Vec<f64> = expected.iter().map(|a| a * a).collect();
+        assert!(max_error(&decryptor.decrypt_real(&squared), &expected) < 1e-3);
+    }
+
+    #[test]
+    fn galois_key_sets_are_minimal() {
+        assert_eq!(poly::galois_elements_for_steps(&[0, 1, 1025, -1023, 3], 2048).len(), 2);
+        let keygen = KeyGenerator::new(&context(&[60, 40, 60]));
+        let keys = keygen.galois_keys(&[1, 3, -2, 1025, 2, 4, 8, 16, 32, 64, 128, 256, 512], true);
+        assert_eq!(keys.keys.len(), 13);
+        assert!(keys.keys.contains_key(&poly::galois_element_conjugate(2048)));
+    }
+
+    #[test]
+    fn rotation_and_conjugation() {
+        let context = context(&[60, 40, 60]);
+        let keygen = KeyGenerator::new(&context);
+        let encryptor = Encryptor::new(&context, &keygen.public_key());
+        let decryptor = Decryptor::new(&context, &keygen.secret_key());
+        let ev = Evaluator::new(&context);
+
+        let keys = keygen.galois_keys(&[1, 3, -2, 2, 4, 8, 16, 32, 64, 128, 256, 512], true);
+        let z: Vec<Complex64> = (0..1024).map(|i| Complex64::new(i as f64 / 100.0, (i as f64 * 0.1).sin())).collect();
+        let ct = encryptor.encrypt(&context.encode(&z, context.params.scale, 1).unwrap());
+
+        // Direct keys (1, 3, -2) and compositions of power-of-two steps
+        for step in [1i64, 3, -2, 7, 1000, -1, 1024] {
+            let rotated = decryptor.decrypt_complex(&ev.rotate(&ct, step, &keys).unwrap());
+            let expected: Vec<Complex64> = (0..1024).map(|i| z[(i as i64 + step).rem_euclid(1024) as usize]).collect();
+            assert!(max_complex_error(&rotated, &expected) < 1e-5, "step {}", step);
+        }
+        let conjugated = decryptor.decrypt_complex(&ev.conjugate(&ct, &keys).unwrap());
+        let expected: Vec<Complex64> = z.iter().map(|x| x.conj()).collect();
+        assert!(max_complex_error(&conjugated, &expected) < 1e-5);
+
+        let sparse_keys = keygen.galois_keys(&[1], false);
+        assert!(ev.rotate(&ct, 2, &sparse_keys).is_err());
+        assert!(ev.conjugate(&ct, &sparse_keys).is_err());
+        let tensor = ev.multiply(&ct, &ct).unwrap();
+        assert!(ev.rotate(&tensor, 1, &keys).is_err());
+    }
+
+    #[test]
+    fn hoisted_rotations_match_rotate() {
+        let context = context(&[50, 40, 40, 60]);
+        let keygen = KeyGenerator::new(&context);
+        let encryptor = Encryptor::new(&context, &keygen.public_key());
+        let ev = Evaluator::new(&context);
+
+        let steps: Vec<i64> = (1..=16).chain([-3, 0, 1000]).collect();
+        let keys = keygen.galois_keys(&steps, false);
+        let x: Vec<f64> = (0..1024).map(|i| i as f64).collect();
+        let ct = encryptor.encrypt(&context.encode_real(&x).unwrap());
+
+        let hoisted = ev.rotate_hoisted(&ct, &steps, &keys).unwrap();
+        assert_eq!(hoisted.len(), steps.len());
+        for (rotated, &step) in hoisted.iter().zip(&steps) {
+            assert_eq!(rotated.polys, ev.rotate(&ct, step, &keys).unwrap().polys, "step {}", step);
+        }
+        // Steps are taken modulo the slot count
+        let wrapped = ev.rotate_hoisted(&ct, &[1025, -24, 1024], &keys).unwrap();
+        assert_eq!(wrapped[0].polys, hoisted[0].polys);
+        assert_eq!(wrapped[1].polys, hoisted[18].polys);
+        assert_eq!(wrapped[2].polys, ct.polys);
+        assert!(ev.rotate_hoisted(&ct, &[5, 17], &keys).is_err());
+    }
+
+    /// Precision in bits of encryption, x·y and (x·y)² with auto-rescaling
+    fn precision_bits(context: &CKKSContext) -> [f64; 3] {
+        let keygen = KeyGenerator::new(context);
+        let encryptor = Encryptor::new(context, &keygen.public_key());
+        let decryptor = Decryptor::new(context, &keygen.secret_key());
+        let ev = Evaluator::with_auto_rescale(context);
+        let rk = keygen.relin_keys();
+        let x: Vec<f64> = (0..1024).map(|i| (i as f64 * 0.37).sin()).collect();
+        let y: Vec<f64> = (0..1024).map(|i| (i as f64 * 0.11).cos()).collect();
+        let xy: Vec<f64> = x.iter().zip(&y).map(|(a, b)| a * b).collect();
+        let encrypt = |values: &[f64]| encryptor.encrypt(&context.encode_real(values).unwrap());
+        let bits = |ct: &Ciphertext, expected: &[f64]| -max_error(&decryptor.decrypt_real(ct), expected).log2();
+
+        let cx = encrypt(&x);
+        let product = ev.multiply_relin(&cx, &encrypt(&y), &rk).unwrap();
+        let square = ev.multiply_relin(&product, &product, &rk).unwrap();
+        [bits(&cx, &x), bits(&product, &xy), bits(&square, &xy.iter().map(|v| v * v).collect::<Vec<_>>())]
+    }
+
+    fn composite_context() -> CKKSContext {
+        let params = CKKSParameters::new(2048, &[60, 60, 40, 40, 40, 40, 60], 2f64.powi(80)).unwrap().with_primes_per_level(2);
+        CKKSContext::with_params(params).unwrap()
+    }
+
+    #[test]
+    fn composite_levels_group_primes() {
+        let context = composite_context();
+        let keygen = KeyGenerator::new(&context);
+        let encryptor = Encryptor::new(&context, &keygen.public_key());
+        let decryptor = Decryptor::new(&context, &keygen.secret_key());
+        let ev = Evaluator::new(&context);
+        assert_eq!((context.max_level(), context.limb_count(2)), (2, 6));
+        let moduli = &context.params.cipher_moduli;
+        assert_eq!(context.level_modulus(2), moduli[4] as f64 * moduli[5] as f64);
+
+        let x: Vec<f64> = (0..1024).map(sine).collect();
+        let cx = encryptor.encrypt(&context.encode_real(&x).unwrap());
+        assert_eq!((cx.level(), cx.polys[0].limbs.len()), (2, 6));
+        let rescaled = ev.rescale_to_next(&ev.multiply_const(&cx, Complex64::new(0.3, 0.2)).unwrap()).unwrap();
+        assert_eq!((rescaled.level(), rescaled.polys[0].limbs.len()), (1, 4));
+        let expected: Vec<Complex64> = x.iter().map(|&a| Complex64::new(0.3, 0.2) * a).collect();
+        assert!(max_complex_error(&decryptor.decrypt_complex(&rescaled), &expected) < 1e-9);
+
+        let keys = keygen.galois_keys(&[5], false);
+        let rotated = decryptor.decrypt_real(&ev.rotate(&cx, 5, &keys).unwrap());
+        assert!(max_error(&rotated, &(0..1024).map(|i| x[(i + 5) % 1024]).collect::<Vec<_>>()) < 1e-9);
+        let doubled = ev.multiply_const_to(&cx, 2.0, 0, cx.scale).unwrap();
+        assert_eq!((doubled.level(), doubled.scale), (0, cx.scale));
+        assert!(max_error(&decryptor.decrypt_real(&doubled), &x.iter().map(|a| 2.0 * a).collect::<Vec<_>>()) < 1e-9);
+
+        let params = CKKSParameters::new(2048, &[60, 40, 40, 60], 2f64.powi(40)).unwrap().with_primes_per_level(2);
+        assert!(CKKSContext::with_params(params).is_err());
+    }
+
+    #[test]
+    fn composite_scaling_gains_precision() {
+        let single = precision_bits(&context(&[60, 40, 40, 60]));
+        let composite = precision_bits(&composite_context());
+        for (s, c) in single.iter().zip(&composite) {
+            assert!(c - s >= 16.0, "{:.1} -> {:.1} bits", s, c);
+        }
+    }
+
+    #[test]
+    fn sparse_rotations_reduce_steps_modulo_the_slot_count() {
+        let params = CKKSParameters::new(2048, &[60, 40, 60], 2f64.powi(40)).unwrap().with_slots(64);
+        let context = CKKSContext::with_params(params).unwrap();
+        let keygen = KeyGenerator::new(&context);
+        let encryptor = Encryptor::new(&context, &keygen.public_key());
+        let decryptor = Decryptor::new(&context, &keygen.secret_key());
+        let ev = Evaluator::new(&context);
+        let keys = keygen.galois_keys(&[1, 2, 4, 8, 16, 32], false);
+        let z: Vec<Complex64> = (0..64).map(|i| Complex64::new((i as f64 * 0.37).sin(), (i as f64 * 0.11).cos())).collect();
+        let ct = encryptor.encrypt(&context.encode(&z, 2f64.powi(40), 1).unwrap());
+
+        let steps = [0i64, 1, 64, 65, -63, 2 - 64 * 3, 37];
+        for &step in &steps {
+            let rotated = decryptor.decrypt_complex(&ev.rotate(&ct, step, &keys).unwrap());
+            let expected: Vec<Complex64> = (0..64).map(|i| z[(i as i64 + step).rem_euclid(64) as usize]).collect();
+            assert!(max_complex_error(&rotated, &expected) < 1e-5, "step {}", step);
+        }
+
+        // hoisting reduces the same way; 37 has no direct key
+        let direct = &steps[..6];
+        let hoisted = ev.rotate_hoisted(&ct, direct, &keys).unwrap();
+        for (rotated, &step) in hoisted.iter().zip(direct) {
+            assert_eq!(rotated.polys, ev.rotate(&ct, step, &keys).unwrap().polys, "step {}", step);
+        }
+        assert!(ev.rotate_hoisted(&ct, &[37], &keys).is_err());
     }
-    
-    rotated
 }
@@ -0,0 +1,428 @@
+//! CKKS bootstrapping
+//! ModRaise, CoeffToSlot, EvalMod and SlotToCoeff
+//!
+//! A level-0 ciphertext is lifted to the top modulus, where it decrypts to
+//! t = Δ·m + q_0·I(X) with |I| bounded by K for a sparse secret. The
+//! ciphertext is relabelled with scale q_0, so its coefficients read as
+//! t/q_0 = I + Δ·m/q_0. CoeffToSlot moves these coefficients into the
+//! slots by the inverse special FFT, whose butterfly layers are merged
+//! into a few diagonal matrices applied baby-step giant-step, and divides
+//! them by K. EvalMod computes sin(2πx)/(2π) ≈ x - I from a Chebyshev fit
+//! of cos(2π(K·y - 1/4)/2^r) on y = x/K ∈ [-1, 1] and r double-angle
+//! steps, and SlotToCoeff runs the forward FFT back. The bit reversal of
+//! both transforms is skipped, since EvalMod acts slot-wise.
+//!
+//! Sparse packing with n < N/2 slots first sums the rotations by multiples
+//! of n, which projects onto the subring the message lives in, and then
+//! packs real and imaginary coefficients into one ciphertext of 2n slots,
+//! so EvalMod runs once instead of twice.
+//!
+//! The result keeps the input scale. Its precision is roughly the EvalMod
+//! error times q_0/Δ, so Δ should sit well below q_0, and the levels spent
+//! here should use primes close to q_0.
+
+use super::bfv::{GaloisKeys, RelinKeys};
+use super::ckks::{self, CKKSContext, Ciphertext, Evaluator, KeyGenerator};
+use super::ckks_encoder::CKKSEncoder;
+use super::ckks_polynomial::ChebyshevApproximation;
+use super::linear_transform::LinearTransform;
+use num_complex::Complex64;
+use std::collections::BTreeMap;
+use std::f64::consts::PI;
+
+// ==================== PARAMETERS ====================
+
+#[derive(Clone, Debug)]
+pub struct BootstrapParameters {
+    /// Slots refreshed: N/2 for full packing, a smaller power of two for
+    /// sparse packing
+    pub slots: usize,
+    /// Matrices (and levels) the CoeffToSlot FFT layers are merged into
+    pub coeff_to_slot_levels: usize,
+    /// Matrices (and levels) the SlotToCoeff FFT layers are merged into
+    pub slot_to_coeff_levels: usize,
+    /// Bound K on the overflow |I| after ModRaise; EvalMod is accurate on
+    /// [-K, K] and garbles slots outside it
+    pub k_bound: f64,
+    /// Degree of the Chebyshev approximation of the scaled cosine
+    pub sine_degree: usize,
+    /// Double-angle steps r
+    pub double_angle: usize,
+}
+
+impl BootstrapParameters {
+    /// Defaults for a secret of Hamming weight about 64
+    pub fn new(slots: usize) -> Self {
+        Self {
+            slots,
+            coeff_to_slot_levels: 2,
+            slot_to_coeff_levels: 2,
+            k_bound: 12.0,
+            sine_degree: 30,
+            double_angle: 3,
+        }
+    }
+
+    pub fn with_levels(mut self, coeff_to_slot: usize, slot_to_coeff: usize) -> Self {
+        self.coeff_to_slot_levels = coeff_to_slot;
+        self.slot_to_coeff_levels = slot_to_coeff;
+        self
+    }
+
+    pub fn with_eval_mod(mut self, k_bound: f64, sine_degree: usize, double_angle: usize) -> Self {
+        self.k_bound = k_bound;
+        self.sine_degree = sine_degree;
+        self.double_angle = double_angle;
+        self
+    }
+}
+
+// ==================== TRANSFORM MATRICES ====================
+
+/// Sparse p×p matrix as its nonzero diagonals k -> diag_k, with
+/// diag_k[j] = A[j][(j + k) mod p]
+type DiagonalMatrix = BTreeMap<usize, Vec<Complex64>>;
+
+/// Diagonal matrix with `entries` on the main diagonal
+fn scaling(entries: Vec<Complex64>) -> DiagonalMatrix {
+    BTreeMap::from([(0, entries)])
+}
+
+/// A·B in diagonal form: diagonals a and b contribute
+/// diag_a[j]·diag_b[j + a] to diagonal a + b
+fn diagonal_product(a: &DiagonalMatrix, b: &DiagonalMatrix, p: usize) -> DiagonalMatrix {
+    let mut product = DiagonalMatrix::new();
+    for (&ka, da) in a {
+        for (&kb, db) in b {
+            let diag = product.entry((ka + kb) % p).or_insert_with(|| vec![Complex64::new(0.0, 0.0); p]);
+            for (j, (x, &y)) in diag.iter_mut().zip(da).enumerate() {
+                *x += y * db[(j + ka) % p];
+            }
+        }
+    }
+    product
+}
+
+/// Diagonals of one butterfly layer on blocks of `len`, applied to each
+/// `slots`-chunk of a length-p vector; at most three are nonzero
+fn stage_diagonals(encoder: &CKKSEncoder, p: usize, slots: usize, len: usize, inverse: bool) -> DiagonalMatrix {
+    let lenh = len / 2;
+    let zero = Complex64::new(0.0, 0.0);
+    let one = Complex64::new(1.0, 0.0);
+    let (mut main, mut up, mut down) = (vec![zero; p], vec![zero; p], vec![zero; p]);
+    for r in 0..p {
+        let pos = r % slots % len;
+        let j = pos % lenh;
+        match (pos < lenh, inverse) {
+            // u + w·v and u - w·v
+            (true, false) => (main[r], up[r]) = (one, encoder.fft_root(len, j)),
+            (false, false) => (down[r], main[r]) = (one, -encoder.fft_root(len, j)),
+            // u + v and (u - v)·w
+            (true, true) => (main[r], up[r]) = (one, one),
+            (false, true) => {
+                let w = encoder.ifft_root(len, j);
+                (down[r], main[r]) = (w, -w);
+            }
+        }
+    }
+    let mut diagonals = DiagonalMatrix::from([(0, main), (lenh, up)]);
+    // for p = len the two off-diagonals share an offset on disjoint rows
+    let other = diagonals.entry(p - lenh).or_insert_with(|| vec![zero; p]);
+    for (x, y) in other.iter_mut().zip(down) {
+        *x += y;
+    }
+    diagonals
+}
+
+/// The layers `group` in order after `first`, as (k, diag_k) pairs
+fn grouped_stages(
+    encoder: &CKKSEncoder,
+    first: DiagonalMatrix,
+    group: &[usize],
+    slots: usize,
+    inverse: bool,
+) -> DiagonalMatrix {
+    let p = first[&0].len();
+    group.iter().fold(first, |acc, &len| {
+        diagonal_product(&stage_diagonals(encoder, p, slots, len, inverse), &acc, p)
+    })
+}
+
+/// FFT layer lengths split into `groups` consecutive runs of near-equal size
+fn group_stages(stages: &[usize], groups: usize) -> Vec<Vec<usize>> {
+    let (base, extra) = (stages.len() / groups, stages.len() % groups);
+    let mut rest = stages;
+    (0..groups)
+        .map(|k| {
+            let (head, tail) = rest.split_at(base + usize::from(k < extra));
+            rest = tail;
+            head.to_vec()
+        })
+        .collect()
+}
+
+// ==================== BOOTSTRAPPER ====================
+
+pub struct Bootstrapper {
+    context: CKKSContext,
+    evaluator: Evaluator,
+    pub params: BootstrapParameters,
+    coeff_to_slot: Vec<LinearTransform<ckks::Plaintext>>,
+    slot_to_coeff: Vec<LinearTransform<ckks::Plaintext>>,
+    eval_mod: ChebyshevApproximation,
+}
+
+impl Bootstrapper {
+    /// Precomputes the CoeffToSlot and SlotToCoeff diagonals at the levels
+    /// they are applied at and the EvalMod approximation
+    pub fn new(context: &CKKSContext, params: BootstrapParameters) -> Result<Self, String> {
+        let full = context.params.poly_degree / 2;
+        let slots = params.slots;
+        if !slots.is_power_of_two() || slots < 2 || slots > full {
+            return Err(format!("slot count {} must be a power of two in [2, {}]", slots, full));
+        }
+        let log_slots = slots.trailing_zeros() as usize;
+        for (name, levels) in [
+            ("coeff_to_slot_levels", params.coeff_to_slot_levels),
+            ("slot_to_coeff_levels", params.slot_to_coeff_levels),
+        ] {
+            if levels == 0 || levels > log_slots {
+                return Err(format!("{} = {} must lie in [1, {}] for {} slots", name, levels, log_slots, slots));
+            }
+        }
+        if !(params.k_bound.is_finite() && params.k_bound > 0.0) {
+            return Err(format!("k_bound {} must be positive", params.k_bound));
+        }
+
+        // CoeffToSlot divides by K, so the fit runs on y = x/K in [-1, 1]
+        let (k_bound, period) = (params.k_bound, 2.0f64.powi(params.double_angle as i32));
+        let eval_mod = ChebyshevApproximation::fit(
+            |y| (2.0 * PI * (k_bound * y - 0.25) / period).cos(),
+            -1.0,
+            1.0,
+            params.sine_degree,
+        )?;
+
+        let depth = params.coeff_to_slot_levels + eval_mod.depth() + params.double_angle + params.slot_to_coeff_levels;
+        let top = context.max_level();
+        if depth > top {
+            return Err(format!("bootstrapping needs {} levels, the modulus chain has {}", depth, top));
+        }
+
+        // the transforms act on the full slot vector whatever the context's packing
+        let packing = context.with_slot_count(full)?;
+        let encoder = &packing.encoder;
+        let sparse = slots < full;
+        let gap = (full / slots) as f64;
+        let p = if sparse { 2 * slots } else { slots };
+
+        // CoeffToSlot: (1/2K)·U^-1 on the real half, -(i/2K)·U^-1 on the
+        // imaginary half of a sparse packing, with 1/gap undoing the sum
+        let inverse_scale = 1.0 / (2.0 * slots as f64 * gap * k_bound);
+        let factors = [Complex64::new(inverse_scale, 0.0), Complex64::new(0.0, -inverse_scale)];
+        let stages: Vec<usize> = (1..=log_slots).rev().map(|k| 1 << k).collect();
+        let mut coeff_to_slot = Vec::new();
+        for (k, group) in group_stages(&stages, params.coeff_to_slot_levels).iter().enumerate() {
+            let first = if k == 0 {
+                (0..p).map(|r| factors[r / slots]).collect()
+            } else {
+                vec![Complex64::new(1.0, 0.0); p]
+            };
+            let diagonals = grouped_stages(encoder, scaling(first), group, slots, true);
+            coeff_to_slot.push(LinearTransform::from_diagonals_ckks(&packing, p, diagonals.into_iter().collect(), top - k)?);
+        }
+
+        // SlotToCoeff: U/(2π), combining the sparse halves as re + i·im
+        let stages: Vec<usize> = (1..=log_slots).map(|k| 1 << k).collect();
+        let groups = group_stages(&stages, params.slot_to_coeff_levels);
+        let start = top - params.coeff_to_slot_levels - eval_mod.depth() - params.double_angle;
+        let mut slot_to_coeff = Vec::new();
+        for (k, group) in groups.iter().enumerate() {
+            let first = vec![Complex64::new(if k == 0 { 1.0 / (2.0 * PI) } else { 1.0 }, 0.0); p];
+            let mut diagonals = grouped_stages(encoder, scaling(first), group, slots, false);
+            if sparse && k + 1 == groups.len() {
+                // both halves become re + i·im
+                let (one, i) = (Complex64::new(1.0, 0.0), Complex64::i());
+                let combine = DiagonalMatrix::from([
+                    (0, (0..p).map(|r| if r < slots { one } else { i }).collect()),
+                    (slots, (0..p).map(|r| if r < slots { i } else { one }).collect()),
+                ]);
+                diagonals = diagonal_product(&combine, &diagonals, p);
+            }
+            slot_to_coeff.push(LinearTransform::from_diagonals_ckks(&packing, p, diagonals.into_iter().collect(), start - k)?);
+        }
+
+        Ok(Self {
+            context: context.clone(),
+            evaluator: Evaluator::new(&packing),
+            params,
+            coeff_to_slot,
+            slot_to_coeff,
+            eval_mod,
+        })
+    }
+
+    /// Levels consumed: the output sits this far below the top level
+    pub fn depth(&self) -> usize {
+        self.params.coeff_to_slot_levels
+            + self.eval_mod.depth()
+            + self.params.double_angle
+            + self.params.slot_to_coeff_levels
+    }
+
+    /// Rotation steps the transforms and the sparse subring sum need;
+    /// conjugation is needed as well
+    pub fn rotation_steps(&self) -> Vec<i64> {
+        let mut steps: Vec<i64> = self.coeff_to_slot.iter()
+            .chain(&self.slot_to_coeff)
+            .flat_map(|t| t.rotation_steps())
+            .collect();
+        let mut step = self.params.slots;
+        while step < self.full_slots() {
+            steps.push(step as i64);
+            step *= 2;
+        }
+        steps.sort_unstable();
+        steps.dedup();
+        steps
+    }
+
+    /// Galois keys for `rotation_steps` and conjugation
+    pub fn galois_keys(&self, keygen: &KeyGenerator) -> GaloisKeys {
+        keygen.galois_keys(&self.rotation_steps(), true)
+    }
+
+    /// Refreshes a ciphertext to `max_level - depth` at its scale
+    ///
+    /// The encoded coefficients Δ·m must stay well below q_0, and the
+    /// secret must keep ModRaise's overflow within `k_bound`.
+    pub fn bootstrap(&self, ct: &Ciphertext, relin_keys: &RelinKeys, galois_keys: &GaloisKeys) -> Result<Ciphertext, String> {
+        let ev = &self.evaluator;
+        if ct.size() != 2 {
+            return Err(format!("cannot bootstrap a size-{} ciphertext, relinearize first", ct.size()));
+        }
+        let input_scale = ct.scale;
+        let q0 = self.context.level_modulus(0);
+
+        // ModRaise: coefficients read as t/q_0 = I + Δ·m/q_0
+        let mut ct = ev.mod_raise(&ev.mod_switch_to(ct, 0)?)?;
+        ct.scale = q0;
+
+        // trace onto the sparse subring, multiplying by N/(2n)
+        let mut step = self.params.slots;
+        while step < self.full_slots() {
+            let rotated = ev.rotate(&ct, step as i64, galois_keys)?;
+            ev.add_inplace(&mut ct, &rotated)?;
+            step *= 2;
+        }
+
+        // CoeffToSlot, then real and imaginary parts via conjugation
+        for transform in &self.coeff_to_slot {
+            ct = transform.evaluate(ev, &ct, galois_keys)?;
+        }
+        let conj = ev.conjugate(&ct, galois_keys)?;
+        let mut parts = vec![ev.add(&ct, &conj)?];
+        if self.params.slots == self.full_slots() {
+            parts.push(ev.multiply_by_i(&ev.sub(&conj, &ct)?));
+        }
+
+        let parts = parts.iter()
+            .map(|part| self.eval_mod(part, relin_keys))
+            .collect::<Result<Vec<_>, _>>()?;
+        let mut ct = parts[0].clone();
+        if let Some(imag) = parts.get(1) {
+            ev.add_inplace(&mut ct, &ev.multiply_by_i(imag))?;
+        }
+
+        for transform in &self.slot_to_coeff {
+            ct = transform.evaluate(ev, &ct, galois_keys)?;
+        }
+        // values are Δ·m/q_0 at the current scale
+        ct.scale *= input_scale / q0;
+        Ok(ct)
+    }
+
+    fn full_slots(&self) -> usize {
+        self.context.params.poly_degree / 2
+    }
+
+    /// sin(2πx) from cos(2π(x - 1/4)/2^r) and cos 2θ = 2cos²θ - 1
+    fn eval_mod(&self, ct: &Ciphertext, relin_keys: &RelinKeys) -> Result<Ciphertext, String> {
+        let mut result = self.evaluator.evaluate_chebyshev(ct, &self.eval_mod, relin_keys)?;
+        for _ in 0..self.params.double_angle {
+            result = self.evaluator.chebyshev_double(&result, relin_keys)?;
+        }
+        Ok(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fhe::ckks::{CKKSParameters, Decryptor, Encryptor};
+
+    /// q_0, 16 bootstrapping levels and the special prime near 2^60, and
+    /// two 40-bit levels that remain after bootstrapping
+    fn context() -> CKKSContext {
+        let mut bit_sizes = vec![60, 40, 40];
+        bit_sizes.extend([60; 17]);
+        let params = CKKSParameters::new(1024, &bit_sizes, 2f64.powi(40)).unwrap();
+        CKKSContext::with_params(params).unwrap()
+    }
+
+    fn bootstrap_precision(context: &CKKSContext, slots: usize) -> f64 {
+        let bootstrapper = Bootstrapper::new(context, BootstrapParameters::new(slots)).unwrap();
+        let keygen = KeyGenerator::with_hamming_weight(context, 64);
+        let encryptor = Encryptor::new(context, &keygen.public_key());
+        let decryptor = Decryptor::new(context, &keygen.secret_key());
+
+        let values: Vec<Complex64> = (0..slots)
+            .map(|i| Complex64::new((i as f64 * 0.37).sin(), 0.5 * (i as f64 * 0.11).cos()))
+            .collect();
+        let replicated: Vec<Complex64> = (0..context.slot_count()).map(|i| values[i % slots]).collect();
+        let ct = encryptor.encrypt(&context.encode(&replicated, 2f64.powi(40), 0).unwrap());
+        let refreshed = bootstrapper
+            .bootstrap(&ct, &keygen.relin_keys(), &bootstrapper.galois_keys(&keygen))
+            .unwrap();
+
+        assert_eq!(refreshed.level(), context.max_level() - bootstrapper.depth());
+        assert!((refreshed.scale / ct.scale - 1.0).abs() < ckks::SCALE_TOLERANCE);
+        decryptor.decrypt_complex(&refreshed).iter().enumerate()
+            .map(|(i, z)| (z - values[i % slots]).norm())
+            .fold(0.0, f64::max)
+    }
+
+    #[test]
+    fn transforms_have_few_diagonals() {
+        let context = context();
+        let bootstrapper = Bootstrapper::new(&context, BootstrapParameters::new(512)).unwrap();
+        // s merged layers have at most 2^(s+1) - 1 diagonals; the nine
+        // layers for 512 slots are split 5 + 4
+        for transform in bootstrapper.coeff_to_slot.iter().chain(&bootstrapper.slot_to_coeff) {
+            assert!(transform.diagonal_count() <= 63, "{} diagonals", transform.diagonal_count());
+        }
+        assert!(bootstrapper.rotation_steps().iter().all(|&s| s > 0 && s < 512));
+    }
+
+    #[test]
+    fn rejects_invalid_parameters() {
+        let context = context();
+        assert!(Bootstrapper::new(&context, BootstrapParameters::new(48)).is_err());
+        assert!(Bootstrapper::new(&context, BootstrapParameters::new(1024)).is_err());
+        assert!(Bootstrapper::new(&context, BootstrapParameters::new(32).with_levels(0, 2)).is_err());
+        assert!(Bootstrapper::new(&context, BootstrapParameters::new(32).with_levels(6, 2)).is_err());
+        assert!(Bootstrapper::new(&context, BootstrapParameters::new(32).with_eval_mod(12.0, 4095, 3)).is_err());
+    }
+
+    #[test]
+    fn sparse_bootstrapping_precision() {
+        let error = bootstrap_precision(&context(), 32);
+        assert!(-error.log2() > 14.0, "max error {:e}", error);
+    }
+
+    #[test]
+    fn full_bootstrapping_precision() {
+        let error = bootstrap_precision(&context(), 512);
+        assert!(-error.log2() > 14.0, "max error {:e}", error);
+    }
+}
@@ -0,0 +1,346 @@
+//! Approximate comparison for CKKS
+//! Sign, compare, max, min and ReLU from composite minimax polynomials
+//!
+//! sign(x) is approximated on [-1, -ε] ∪ [ε, 1] by a composition of odd
+//! minimax polynomials (Lee et al.): stage i is the Remez optimum on
+//! [ε_i, 1] with error δ_i, scaled by 1/(1 + δ_i) so that its outputs for
+//! x ≥ ε_i land in [ε_(i+1), 1] with ε_(i+1) = (1 - δ_i)/(1 + δ_i). Stages
+//! are added until δ <= 2^-precision_bits; the last one is left unscaled.
+//! A stage of degree d costs ceil(log2(d + 1)) levels
+//! (`ChebyshevApproximation::depth`), so low degrees give more, shallower
+//! stages and high degrees fewer, deeper ones.
+//!
+//! Input ranges:
+//! - `sign(x)`: x in [-1, 1]; slots with |x| < ε land anywhere in [-1, 1].
+//! - `compare(a, b)`, `max`, `min`: a - b in [-1, 1], e.g. a, b in [0, 1];
+//!   differences below ε in magnitude are not resolved and `compare`
+//!   returns a value between 0 and 1 for them.
+//! - `relu(x)`: x in [-1, 1]; for |x| < ε the error stays below |x|.
+//!
+//! Scale the inputs by 1/B beforehand for data in [-B, B]; ε then refers
+//! to the scaled gap.
+
+use super::bfv::RelinKeys;
+use super::ckks::{Ciphertext, Evaluator};
+use super::ckks_polynomial::ChebyshevApproximation;
+
+// ==================== COMPOSITE SIGN ====================
+
+/// Composition of odd minimax polynomials approximating sign(x)
+#[derive(Clone, Debug)]
+pub struct CompositeSign {
+    /// Smallest |x| the approximation resolves
+    pub epsilon: f64,
+    /// Stages on [-1, 1], applied in order
+    pub stages: Vec<ChebyshevApproximation>,
+    /// Largest |p(x) - sign(x)| for ε <= |x| <= 1
+    pub max_error: f64,
+}
+
+const MAX_STAGES: usize = 32;
+
+impl CompositeSign {
+    /// Stages of degree `stage_degree` (odd) until the error is at most
+    /// 2^-precision_bits
+    pub fn new(epsilon: f64, precision_bits: u32, stage_degree: usize) -> Result<Self, String> {
+        Self::check(epsilon, stage_degree)?;
+        let target = 2f64.powi(-(precision_bits as i32));
+        let mut degrees = Vec::new();
+        let mut eps = epsilon;
+        loop {
+            degrees.push(stage_degree);
+            let (_, delta) = remez_sign(eps, stage_degree)?;
+            if delta <= target {
+                break;
+            }
+            if degrees.len() == MAX_STAGES {
+                return Err(format!(
+                    "{} stages of degree {} do not reach 2^-{} from ε = {:e}",
+                    MAX_STAGES, stage_degree, precision_bits, epsilon
+                ));
+            }
+            eps = (1.0 - delta) / (1.0 + delta);
+        }
+        Self::with_degrees(epsilon, &degrees)
+    }
+
+    /// One stage per entry of `degrees` (all odd)
+    pub fn with_degrees(epsilon: f64, degrees: &[usize]) -> Result<Self, String> {
+        if degrees.is_empty() {
+            return Err("at least one stage is needed".to_string());
+        }
+        let mut stages = Vec::with_capacity(degrees.len());
+        let mut eps = epsilon;
+        let mut max_error = 1.0;
+        for (i, &degree) in degrees.iter().enumerate() {
+            Self::check(eps, degree)?;
+            let (mut coeffs, delta) = remez_sign(eps, degree)?;
+            if i + 1 < degrees.len() {
+                coeffs.iter_mut().for_each(|c| *c /= 1.0 + delta);
+            }
+            stages.push(ChebyshevApproximation { coeffs, a: -1.0, b: 1.0, max_error: delta });
+            max_error = delta;
+            eps = (1.0 - delta) / (1.0 + delta);
+        }
+        Ok(Self { epsilon, stages, max_error })
+    }
+
+    fn check(epsilon: f64, degree: usize) -> Result<(), String> {
+        if !(epsilon > 0.0 && epsilon < 1.0) {
+            return Err(format!("ε = {} must lie in (0, 1)", epsilon));
+        }
+        if degree.is_multiple_of(2) {
+            return Err(format!("stage degree {} must be odd", degree));
+        }
+        Ok(())
+    }
+
+    /// Levels consumed by `Evaluator::sign`
+    pub fn depth(&self) -> usize {
+        self.stages.iter().map(ChebyshevApproximation::depth).sum()
+    }
+
+    /// Plaintext evaluation
+    pub fn evaluate(&self, x: f64) -> f64 {
+        self.stages.iter().fold(x, |y, stage| stage.evaluate(y))
+    }
+
+    /// Same stages with the last one mapped to factor·p + offset, which
+    /// folds affine post-processing into the polynomial for free
+    fn with_affine_output(&self, factor: f64, offset: f64) -> Self {
+        let mut result = self.clone();
+        let last = result.stages.last_mut().unwrap();
+        last.coeffs.iter_mut().for_each(|c| *c *= factor);
+        last.coeffs[0] += offset;
+        result
+    }
+}
+
+// ==================== REMEZ ====================
+
+/// Odd minimax approximation of 1 on [ε, 1] in the Chebyshev basis:
+/// coefficients c_0..c_d (even ones zero) and the equioscillation error
+fn remez_sign(epsilon: f64, degree: usize) -> Result<(Vec<f64>, f64), String> {
+    let m = degree.div_ceil(2);
+    let basis = |x: f64, k: usize| ((2 * k + 1) as f64 * x.acos()).cos();
+    let value = |c: &[f64], x: f64| -> f64 { c.iter().enumerate().map(|(k, ck)| ck * basis(x, k)).sum() };
+
+    // grid clustered at both ends like the Chebyshev extrema
+    const GRID: usize = 8192;
+    let grid: Vec<f64> = (0..=GRID)
+        .map(|i| epsilon + (1.0 - epsilon) * (1.0 - (std::f64::consts::PI * i as f64 / GRID as f64).cos()) / 2.0)
+        .collect();
+    let mut points: Vec<f64> = (0..=m)
+        .map(|i| epsilon + (1.0 - epsilon) * (1.0 - (std::f64::consts::PI * i as f64 / m as f64).cos()) / 2.0)
+        .collect();
+
+    let mut coeffs = vec![0.0; m];
+    let mut delta = f64::INFINITY;
+    for _ in 0..100 {
+        // Σ c_k·T_(2k+1)(x_i) + (-1)^i·E = 1
+        let mut system: Vec<Vec<f64>> = points.iter().enumerate()
+            .map(|(i, &x)| {
+                let mut row: Vec<f64> = (0..m).map(|k| basis(x, k)).collect();
+                row.push(if i % 2 == 0 { 1.0 } else { -1.0 });
+                row.push(1.0);
+                row
+            })
+            .collect();
+        let solution = solve(&mut system).ok_or_else(|| format!("Remez system is singular for ε = {:e}", epsilon))?;
+        coeffs = solution[..m].to_vec();
+        let levelled = solution[m].abs();
+
+        // one extremum per sign run of the error, largest first at the ends
+        let errors: Vec<f64> = grid.iter().map(|&x| value(&coeffs, x) - 1.0).collect();
+        delta = errors.iter().fold(0.0f64, |d, e| d.max(e.abs()));
+        if delta - levelled <= 1e-9 * delta {
+            break;
+        }
+        let mut extrema: Vec<usize> = Vec::new();
+        for (i, e) in errors.iter().enumerate() {
+            match extrema.last() {
+                Some(&j) if (errors[j] > 0.0) == (*e > 0.0) => {
+                    if e.abs() > errors[j].abs() {
+                        *extrema.last_mut().unwrap() = i;
+                    }
+                }
+                _ => extrema.push(i),
+            }
+        }
+        while extrema.len() > m + 1 {
+            if errors[extrema[0]].abs() < errors[*extrema.last().unwrap()].abs() {
+                extrema.remove(0);
+            } else {
+                extrema.pop();
+            }
+        }
+        if extrema.len() < m + 1 {
+            break;
+        }
+        points = extrema.iter().map(|&i| grid[i]).collect();
+    }
+    if delta.is_nan() || delta >= 1.0 {
+        return Err(format!("degree-{} stage cannot separate signs from ε = {:e}", degree, epsilon));
+    }
+
+    let mut full = vec![0.0; 2 * m];
+    for (k, c) in coeffs.into_iter().enumerate() {
+        full[2 * k + 1] = c;
+    }
+    Ok((full, delta))
+}
+
+/// Gaussian elimination with partial pivoting on an augmented matrix
+fn solve(system: &mut [Vec<f64>]) -> Option<Vec<f64>> {
+    let n = system.len();
+    for col in 0..n {
+        let pivot = (col..n).max_by(|&a, &b| system[a][col].abs().total_cmp(&system[b][col].abs()))?;
+        if system[pivot][col].abs() < 1e-300 {
+            return None;
+        }
+        system.swap(col, pivot);
+        for row in col + 1..n {
+            let factor = system[row][col] / system[col][col];
+            let (upper, lower) = system.split_at_mut(row);
+            for (x, y) in lower[0][col..].iter_mut().zip(&upper[col][col..]) {
+                *x -= factor * y;
+            }
+        }
+    }
+    let mut x = vec![0.0; n];
+    for row in (0..n).rev() {
+        let sum: f64 = (row + 1..n).map(|k| system[row][k] * x[k]).sum();
+        x[row] = (system[row][n] - sum) / system[row][row];
+    }
+    Some(x)
+}
+
+// ==================== EVALUATOR ====================
+
+impl Evaluator {
+    /// sign(x) per slot, `sign.depth()` levels lower at the input scale
+    pub fn sign(&self, ct: &Ciphertext, sign: &CompositeSign, relin_keys: &RelinKeys) -> Result<Ciphertext, String> {
+        self.evaluate_stages(ct, sign, relin_keys)
+    }
+
+    /// 1 where a > b, 0 where a < b (a - b in [-1, 1])
+    pub fn compare(&self, a: &Ciphertext, b: &Ciphertext, sign: &CompositeSign, relin_keys: &RelinKeys) -> Result<Ciphertext, String> {
+        let diff = self.sub(a, b)?;
+        self.evaluate_stages(&diff, &sign.with_affine_output(0.5, 0.5), relin_keys)
+    }
+
+    /// (a + b)/2 + (a - b)·sign(a - b)/2, one level below `sign`
+    pub fn max(&self, a: &Ciphertext, b: &Ciphertext, sign: &CompositeSign, relin_keys: &RelinKeys) -> Result<Ciphertext, String> {
+        self.max_or_min(a, b, sign, 0.5, relin_keys)
+    }
+
+    /// (a + b)/2 - (a - b)·sign(a - b)/2, one level below `sign`
+    pub fn min(&self, a: &Ciphertext, b: &Ciphertext, sign: &CompositeSign, relin_keys: &RelinKeys) -> Result<Ciphertext, String> {
+        self.max_or_min(a, b, sign, -0.5, relin_keys)
+    }
+
+    /// x·(1 + sign(x))/2, one level below `sign`
+    pub fn relu(&self, ct: &Ciphertext, sign: &CompositeSign, relin_keys: &RelinKeys) -> Result<Ciphertext, String> {
+        let step = self.evaluate_stages(ct, &sign.with_affine_output(0.5, 0.5), relin_keys)?;
+        self.multiply_relin_rescale(ct, &step, relin_keys)
+    }
+
+    fn max_or_min(&self, a: &Ciphertext, b: &Ciphertext, sign: &CompositeSign, half: f64, relin_keys: &RelinKeys) -> Result<Ciphertext, String> {
+        let diff = self.sub(a, b)?;
+        let half_sign = self.evaluate_stages(&diff, &sign.with_affine_output(half, 0.0), relin_keys)?;
+        let mut result = self.multiply_relin_rescale(&diff, &half_sign, relin_keys)?;
+        let half_sum = self.multiply_const_to(&self.add(a, b)?, 0.5, result.level(), result.scale)?;
+        self.add_inplace(&mut result, &half_sum)?;
+        Ok(result)
+    }
+
+    fn evaluate_stages(&self, ct: &Ciphertext, sign: &CompositeSign, relin_keys: &RelinKeys) -> Result<Ciphertext, String> {
+        if ct.level() < sign.depth() {
+            return Err(format!(
+                "sign approximation needs {} levels, ciphertext is at level {}",
+                sign.depth(), ct.level()
+            ));
+        }
+        let mut result = ct.clone();
+        for stage in &sign.stages {
+            result = self.evaluate_chebyshev(&result, stage, relin_keys)?;
+        }
+        Ok(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fhe::ckks::{CKKSContext, CKKSParameters, Decryptor, Encryptor, KeyGenerator};
+    use num_complex::Complex64;
+    use rand::Rng;
+
+    #[test]
+    fn composite_sign_meets_its_precision() {
+        for (epsilon, bits, degree) in [(2f64.powi(-8), 12u32, 15usize), (2f64.powi(-8), 12, 7), (0.01, 10, 31), (0.1, 10, 3)] {
+            let sign = CompositeSign::new(epsilon, bits, degree).unwrap();
+            assert!(sign.max_error <= 2f64.powi(-(bits as i32)));
+            let worst = (0..=20000)
+                .map(|i| epsilon + (1.0 - epsilon) * i as f64 / 20000.0)
+                .map(|x| (sign.evaluate(x) - 1.0).abs().max((sign.evaluate(-x) + 1.0).abs()))
+                .fold(0.0, f64::max);
+            assert!(worst <= 2f64.powi(-(bits as i32)) * 1.01, "ε {} degree {}: {:e}", epsilon, degree, worst);
+            assert_eq!(sign.depth(), sign.stages.iter().map(|s| s.depth()).sum::<usize>());
+        }
+
+        assert!(CompositeSign::new(0.0, 10, 15).is_err());
+        assert!(CompositeSign::new(0.1, 10, 8).is_err());
+        assert!(CompositeSign::with_degrees(0.1, &[]).is_err());
+    }
+
+    #[test]
+    fn encrypted_comparisons() {
+        let mut bit_sizes = vec![60u32];
+        bit_sizes.extend([50u32; 22]);
+        bit_sizes.push(60);
+        let params = CKKSParameters::new(2048, &bit_sizes, 2f64.powi(50)).unwrap();
+        let context = CKKSContext::with_params(params).unwrap();
+        let keygen = KeyGenerator::new(&context);
+        let encryptor = Encryptor::new(&context, &keygen.public_key());
+        let decryptor = Decryptor::new(&context, &keygen.secret_key());
+        let relin_keys = keygen.relin_keys();
+
+        let epsilon = 2f64.powi(-8);
+        let sign = CompositeSign::new(epsilon, 12, 15).unwrap();
+        let mut rng = rand::thread_rng();
+        let slots = context.slot_count();
+        let a: Vec<f64> = (0..slots).map(|_| rng.gen_range(0.0..1.0)).collect();
+        let b: Vec<f64> = (0..slots).map(|_| rng.gen_range(0.0..1.0)).collect();
+        let encrypt = |values: &[f64]| {
+            let values: Vec<Complex64> = values.iter().map(|&x| x.into()).collect();
+            encryptor.encrypt(&context.encode(&values, 2f64.powi(50), context.max_level()).unwrap())
+        };
+        let (ca, cb) = (encrypt(&a), encrypt(&b));
+
+        for evaluator in [Evaluator::new(&context), Evaluator::with_auto_rescale(&context)] {
+            let diff = evaluator.sub(&ca, &cb).unwrap();
+            let signs = decryptor.decrypt_real(&evaluator.sign(&diff, &sign, &relin_keys).unwrap());
+            let greater = decryptor.decrypt_real(&evaluator.compare(&ca, &cb, &sign, &relin_keys).unwrap());
+            let maxima = decryptor.decrypt_real(&evaluator.max(&ca, &cb, &sign, &relin_keys).unwrap());
+            let minima = decryptor.decrypt_real(&evaluator.min(&ca, &cb, &sign, &relin_keys).unwrap());
+            let relu = evaluator.relu(&diff, &sign, &relin_keys).unwrap();
+            assert!((relu.scale / diff.scale - 1.0).abs() < 1e-9);
+            let relu = decryptor.decrypt_real(&relu);
+
+            for i in 0..slots {
+                let d = a[i] - b[i];
+                if d.abs() >= epsilon {
+                    assert!((signs[i] - d.signum()).abs() < 1e-3, "sign({})", d);
+                    assert!((greater[i] - if d > 0.0 { 1.0 } else { 0.0 }).abs() < 1e-3, "compare({}, {})", a[i], b[i]);
+                }
+                assert!((maxima[i] - a[i].max(b[i])).abs() < epsilon);
+                assert!((minima[i] - a[i].min(b[i])).abs() < epsilon);
+                assert!((relu[i] - d.max(0.0)).abs() < epsilon);
+            }
+        }
+
+        let shallow = encryptor.encrypt(&context.encode(&[Complex64::new(0.5, 0.0)], 2f64.powi(50), 2).unwrap());
+        assert!(Evaluator::new(&context).sign(&shallow, &sign, &relin_keys).is_err());
+    }
+}
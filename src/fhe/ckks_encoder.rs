@@ -0,0 +1,327 @@
+//! CKKS encoder
+//! Canonical embedding of complex vectors into RNS plaintexts
+//!
+//! Slot `j` holds m(ζ^(5^j)) for ζ = exp(πi/N). The N/2 slots are the
+//! evaluations at the primitive 2N-th roots ordered by powers of 5; the
+//! remaining roots are their conjugates, which real coefficients fix.
+//! Encoding runs the special inverse FFT over that ordering and rounds
+//! the coefficients at the requested scale, so X -> X^(5^k) rotates the
+//! slots left by `k` and X -> X^(2N-1) conjugates them.
+//!
+//! Sparse packing with n < N/2 slots encodes into the subring in X^(N/2n):
+//! the size-n inverse FFT fills every (N/2n)-th coefficient, and the N/2
+//! evaluations repeat the n values with period n. Rotations by `k` then
+//! rotate the n-slot vector, and its keys are those of the full packing.
+
+use super::ckks::Plaintext;
+use super::poly::RnsPoly;
+use crate::ntt::NttTables;
+use crate::rns::RnsBasis;
+use num_complex::Complex64;
+use num_traits::ToPrimitive;
+use std::f64::consts::PI;
+
+#[derive(Clone, Debug)]
+pub struct CKKSEncoder {
+    pub poly_degree: usize,
+    slots: usize,
+    rot_group: Vec<usize>,    // 5^j mod 2N for j < N/2
+    ksi_pows: Vec<Complex64>, // exp(2πi·k/2N) for k = 0..=2N
+}
+
+impl CKKSEncoder {
+    /// Full packing with N/2 slots
+    pub fn new(poly_degree: usize) -> Result<Self, String> {
+        Self::with_slots(poly_degree, poly_degree / 2)
+    }
+
+    /// Sparse packing with `slots` slots, a power of two up to N/2
+    pub fn with_slots(poly_degree: usize, slots: usize) -> Result<Self, String> {
+        if !poly_degree.is_power_of_two() || poly_degree < 4 {
+            return Err(format!("poly_degree {} must be a power of two >= 4", poly_degree));
+        }
+        if !slots.is_power_of_two() || slots > poly_degree / 2 {
+            return Err(format!("slot count {} must be a power of two <= {}", slots, poly_degree / 2));
+        }
+        let m = 2 * poly_degree;
+
+        let mut rot_group = Vec::with_capacity(poly_degree / 2);
+        let mut g = 1usize;
+        for _ in 0..poly_degree / 2 {
+            rot_group.push(g);
+            g = g * 5 % m;
+        }
+        let ksi_pows = (0..=m)
+            .map(|k| Complex64::from_polar(1.0, 2.0 * PI * k as f64 / m as f64))
+            .collect();
+
+        Ok(CKKSEncoder { poly_degree, slots, rot_group, ksi_pows })
+    }
+
+    /// Number of complex slots n (N/2 unless sparse)
+    pub fn slot_count(&self) -> usize {
+        self.slots
+    }
+
+    /// Coefficient stride N/(2n) of a sparse packing
+    fn gap(&self) -> usize {
+        self.poly_degree / (2 * self.slots)
+    }
+
+    /// Encodes up to n complex values at `scale` modulo the moduli of
+    /// `tables` (q_0..q_l for level l); missing slots are zero
+    pub fn encode(&self, values: &[Complex64], scale: f64, tables: &[NttTables]) -> Result<Plaintext, String> {
+        let slots = self.slot_count();
+        if values.len() > slots {
+            return Err(format!("{} values do not fit into {} slots", values.len(), slots));
+        }
+        if !(scale.is_finite() && scale > 0.0) {
+            return Err(format!("scale {} must be positive and finite", scale));
+        }
+
+        let mut vals = values.to_vec();
+        vals.resize(slots, Complex64::new(0.0, 0.0));
+        self.special_ifft(&mut vals);
+
+        // slot vector -> coefficients: real parts low, imaginary parts high,
+        // every gap-th position
+        let (n, gap) = (self.poly_degree, self.gap());
+        let mut coeffs = vec![0i128; n];
+        // Coefficients are held as i128, so chains above 127 bits are capped
+        // at 2^126 rather than letting the cast saturate
+        let log_q: f64 = tables.iter().map(|t| (t.modulus as f64).log2()).sum();
+        let log_bound = (log_q - 1.0).min(126.0);
+        for (i, v) in vals.iter().enumerate() {
+            for (idx, x) in [(i * gap, v.re), (n / 2 + i * gap, v.im)] {
+                let c = (x * scale).round();
+                if !c.is_finite() || c.abs().log2() >= log_bound {
+                    return Err(format!(
+                        "encoded coefficient {:e} exceeds the {:.0}-bit modulus; lower the scale or the input range",
+                        c, log_q
+                    ));
+                }
+                coeffs[idx] = c as i128;
+            }
+        }
+
+        let limbs = tables.iter()
+            .map(|t| coeffs.iter().map(|&c| c.rem_euclid(t.modulus as i128) as u64).collect())
+            .collect();
+        Ok(Plaintext { poly: RnsPoly { limbs }, scale, primes_per_level: 1 })
+    }
+
+    /// Encodes real values (imaginary parts zero)
+    pub fn encode_real(&self, values: &[f64], scale: f64, tables: &[NttTables]) -> Result<Plaintext, String> {
+        let values: Vec<Complex64> = values.iter().map(|&x| Complex64::new(x, 0.0)).collect();
+        self.encode(&values, scale, tables)
+    }
+
+    /// Decodes all n slots; `basis` must cover the plaintext's level
+    pub fn decode(&self, plain: &Plaintext, basis: &RnsBasis) -> Vec<Complex64> {
+        let (slots, gap, half) = (self.slot_count(), self.gap(), self.poly_degree / 2);
+        let mut residues = vec![0u64; plain.poly.limbs.len()];
+        let mut coeff = |k: usize| -> f64 {
+            for (r, limb) in residues.iter_mut().zip(&plain.poly.limbs) {
+                *r = limb[k];
+            }
+            basis.compose_centered(&residues).to_f64().unwrap() / plain.scale
+        };
+
+        let mut vals: Vec<Complex64> = (0..slots)
+            .map(|i| Complex64::new(coeff(i * gap), coeff(half + i * gap)))
+            .collect();
+        self.special_fft(&mut vals);
+        vals
+    }
+
+    /// Real parts of the decoded slots
+    pub fn decode_real(&self, plain: &Plaintext, basis: &RnsBasis) -> Vec<f64> {
+        self.decode(plain, basis).into_iter().map(|z| z.re).collect()
+    }
+
+    /// Largest slot-wise error |decode(plain)_j - expected_j| over the
+    /// first `expected.len()` slots
+    pub fn max_error(&self, plain: &Plaintext, basis: &RnsBasis, expected: &[Complex64]) -> f64 {
+        self.decode(plain, basis).iter().zip(expected)
+            .map(|(a, b)| (a - b).norm())
+            .fold(0.0, f64::max)
+    }
+
+    /// Evaluates at ζ^(5^j): coefficients -> slots
+    fn special_fft(&self, vals: &mut [Complex64]) {
+        bit_reverse(vals);
+        let mut len = 2;
+        while len <= vals.len() {
+            self.fft_stage(vals, len);
+            len *= 2;
+        }
+    }
+
+    /// Inverse of `special_fft`: slots -> coefficients
+    fn special_ifft(&self, vals: &mut [Complex64]) {
+        let n = vals.len();
+        let mut len = n;
+        while len >= 2 {
+            self.ifft_stage(vals, len);
+            len /= 2;
+        }
+        bit_reverse(vals);
+        let inv_n = 1.0 / n as f64;
+        for v in vals.iter_mut() {
+            *v *= inv_n;
+        }
+    }
+
+    /// One butterfly layer of `special_fft` on blocks of `len`
+    fn fft_stage(&self, vals: &mut [Complex64], len: usize) {
+        let lenh = len / 2;
+        for i in (0..vals.len()).step_by(len) {
+            for j in 0..lenh {
+                let u = vals[i + j];
+                let v = vals[i + j + lenh] * self.fft_root(len, j);
+                vals[i + j] = u + v;
+                vals[i + j + lenh] = u - v;
+            }
+        }
+    }
+
+    /// One butterfly layer of `special_ifft` on blocks of `len`
+    fn ifft_stage(&self, vals: &mut [Complex64], len: usize) {
+        let lenh = len / 2;
+        for i in (0..vals.len()).step_by(len) {
+            for j in 0..lenh {
+                let u = vals[i + j] + vals[i + j + lenh];
+                let v = (vals[i + j] - vals[i + j + lenh]) * self.ifft_root(len, j);
+                vals[i + j] = u;
+                vals[i + j + lenh] = v;
+            }
+        }
+    }
+
+    /// Twiddle exp(2πi·(5^j mod 4·len)/(4·len)) of butterfly `j` in a
+    /// `fft_stage` on blocks of `len`
+    pub(crate) fn fft_root(&self, len: usize, j: usize) -> Complex64 {
+        let (m, lenq) = (2 * self.poly_degree, 4 * len);
+        self.ksi_pows[(self.rot_group[j] % lenq) * m / lenq]
+    }
+
+    /// Inverse twiddle of `fft_root`, used by `ifft_stage`
+    pub(crate) fn ifft_root(&self, len: usize, j: usize) -> Complex64 {
+        let (m, lenq) = (2 * self.poly_degree, 4 * len);
+        self.ksi_pows[(lenq - self.rot_group[j] % lenq) * m / lenq]
+    }
+}
+
+fn bit_reverse(vals: &mut [Complex64]) {
+    let n = vals.len();
+    let bits = n.trailing_zeros();
+    if bits == 0 {
+        return;
+    }
+    for i in 0..n {
+        let j = i.reverse_bits() >> (usize::BITS - bits);
+        if i < j {
+            vals.swap(i, j);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::modular::generate_ntt_primes;
+
+    const N: usize = 1024;
+
+    fn chain(bit_sizes: &[u32]) -> (Vec<NttTables>, RnsBasis) {
+        let moduli = generate_ntt_primes(N, bit_sizes).unwrap();
+        let tables = moduli.iter().map(|&q| NttTables::new(N, q).unwrap()).collect();
+        (tables, RnsBasis::new(&moduli))
+    }
+
+    fn plain(poly: RnsPoly, scale: f64) -> Plaintext {
+        Plaintext { poly, scale, primes_per_level: 1 }
+    }
+
+    fn sample(slots: usize) -> Vec<Complex64> {
+        (0..slots).map(|i| Complex64::new((i as f64 * 0.37).sin(), (i as f64 * 0.11).cos())).collect()
+    }
+
+    #[test]
+    fn encode_decode_round_trip() {
+        let (tables, basis) = chain(&[50, 40, 40]);
+        let encoder = CKKSEncoder::new(N).unwrap();
+        let a = sample(512);
+        let pa = encoder.encode(&a, 2f64.powi(40), &tables).unwrap();
+        assert!(encoder.max_error(&pa, &basis, &a) < 1e-9);
+
+        // Real values, unused slots decode to zero
+        let r: Vec<f64> = (0..100).map(|i| i as f64 - 50.0).collect();
+        let pr = encoder.encode_real(&r, 2f64.powi(40), &tables[..1]).unwrap();
+        let decoded = encoder.decode_real(&pr, &RnsBasis::new(&[tables[0].modulus]));
+        assert!(decoded.iter().zip(&r).all(|(d, x)| (d - x).abs() < 1e-9));
+        assert!(decoded[100..].iter().all(|d| d.abs() < 1e-9));
+
+        // Sparse packing repeats the n values
+        let sparse = CKKSEncoder::with_slots(N, 64).unwrap();
+        let b = sample(64);
+        let pb = sparse.encode(&b, 2f64.powi(40), &tables).unwrap();
+        assert!(sparse.max_error(&pb, &basis, &b) < 1e-9);
+    }
+
+    #[test]
+    fn rejects_out_of_range_inputs() {
+        let (tables, _) = chain(&[50]);
+        let encoder = CKKSEncoder::new(N).unwrap();
+        assert!(encoder.encode(&sample(513), 2f64.powi(40), &tables).is_err());
+        assert!(encoder.encode_real(&[1.0], 0.0, &tables).is_err());
+        assert!(encoder.encode_real(&[1e6], 2f64.powi(50), &tables).is_err());
+        assert!(CKKSEncoder::with_slots(N, 48).is_err());
+
+        // A 150-bit chain still rejects coefficients beyond the i128 range
+        let (wide, _) = chain(&[50, 50, 50]);
+        assert!(encoder.encode_real(&[1.0], 2f64.powi(120), &wide).is_ok());
+        assert!(encoder.encode_real(&[1e6], 2f64.powi(120), &wide).is_err());
+    }
+
+    #[test]
+    fn polynomial_product_multiplies_slots() {
+        let (tables, basis) = chain(&[50, 40, 40]);
+        let encoder = CKKSEncoder::new(N).unwrap();
+        let scale = 2f64.powi(40);
+        let a = sample(512);
+        let b: Vec<Complex64> = (0..512).map(|i| Complex64::new((i as f64 * 0.05).cos(), 0.3)).collect();
+        let (pa, pb) = (encoder.encode(&a, scale, &tables).unwrap(), encoder.encode(&b, scale, &tables).unwrap());
+
+        let product = plain(pa.poly.mul(&pb.poly, &tables), scale * scale);
+        let expected: Vec<Complex64> = a.iter().zip(&b).map(|(x, y)| x * y).collect();
+        assert!(encoder.max_error(&product, &basis, &expected) < 1e-6);
+    }
+
+    #[test]
+    fn automorphisms_rotate_and_conjugate() {
+        let (tables, basis) = chain(&[50, 40]);
+        let encoder = CKKSEncoder::new(N).unwrap();
+        let scale = 2f64.powi(40);
+        let a = sample(512);
+        let pa = encoder.encode(&a, scale, &tables).unwrap();
+
+        for k in [1usize, 3, 511] {
+            let elt = crate::fhe::poly::galois_element_for_step(k as i64, N);
+            let rotated = plain(pa.poly.automorphism(elt, &tables), scale);
+            let expected: Vec<Complex64> = (0..512).map(|i| a[(i + k) % 512]).collect();
+            assert!(encoder.max_error(&rotated, &basis, &expected) < 1e-9, "step {}", k);
+        }
+        let conjugated = plain(pa.poly.automorphism(2 * N as u64 - 1, &tables), scale);
+        let expected: Vec<Complex64> = a.iter().map(|z| z.conj()).collect();
+        assert!(encoder.max_error(&conjugated, &basis, &expected) < 1e-9);
+
+        // Sparse rotations use the keys of the full packing
+        let sparse = CKKSEncoder::with_slots(N, 64).unwrap();
+        let b = sample(64);
+        let pb = sparse.encode(&b, scale, &tables).unwrap();
+        let rotated = plain(pb.poly.automorphism(5, &tables), scale);
+        let expected: Vec<Complex64> = (0..64).map(|i| b[(i + 1) % 64]).collect();
+        assert!(sparse.max_error(&rotated, &basis, &expected) < 1e-9);
+    }
+}
@@ -0,0 +1,202 @@
+//! Noise flooding for CKKS decryption (IND-CPA-D)
+//! Decrypted values that leave the key holder can reveal the secret
+//! (Li–Micciancio): m' = Δ·m + e, and e depends linearly on s. Flooding
+//! adds a discrete Gaussian of deviation
+//!
+//!   σ = sqrt(24·k·N)·2^(s/2)·B
+//!
+//! to the decrypted coefficients before decoding, for an error bound B,
+//! k released decryptions of the key and s bits of statistical security
+//! (Li, Micciancio, Schultz, Sorrell 2022). B is six standard deviations of
+//! the coefficient error predicted by `NoiseEstimator`, so the estimate
+//! must describe the ciphertext's computation. The flooding noise costs
+//! about s/2 + log2(sqrt(24·k·N)·6) bits of precision; a larger scale or
+//! composite scaling buys them back.
+
+use super::ckks::{Ciphertext, Decryptor};
+use super::ckks_precision::NoiseEstimate;
+use super::poly::{self, RnsPoly};
+use num_complex::Complex64;
+
+/// Tail cut of the flooding distribution, in standard deviations
+const FLOODING_TAIL: f64 = 13.0;
+
+#[derive(Clone, Copy, Debug)]
+pub struct FloodingParameters {
+    /// Statistical security s in bits
+    pub statistical_security: u32,
+    /// Number k of decryptions under the same key that may be released
+    pub queries: u64,
+}
+
+impl FloodingParameters {
+    pub fn new(statistical_security: u32) -> Self {
+        Self { statistical_security, queries: 1 }
+    }
+
+    pub fn with_queries(mut self, queries: u64) -> Self {
+        self.queries = queries;
+        self
+    }
+}
+
+/// Flooded decryption and its predicted cost in precision
+#[derive(Clone, Debug)]
+pub struct FloodedDecryption {
+    pub values: Vec<Complex64>,
+    /// Deviation σ of the flooding noise per coefficient
+    pub flooding_std: f64,
+    /// Predicted mean precision without flooding
+    pub precision_bits: f64,
+    /// Predicted mean precision of `values`
+    pub flooded_precision_bits: f64,
+}
+
+impl FloodedDecryption {
+    /// Bits of precision the flooding costs
+    pub fn lost_bits(&self) -> f64 {
+        self.precision_bits - self.flooded_precision_bits
+    }
+}
+
+impl Decryptor {
+    /// Decrypts, floods the coefficients and decodes all slots
+    ///
+    /// `estimate` is the `NoiseEstimator` prediction for `ct`; it must be
+    /// at the ciphertext's level and scale.
+    pub fn decrypt_flooded(
+        &self,
+        ct: &Ciphertext,
+        estimate: &NoiseEstimate,
+        params: &FloodingParameters,
+    ) -> Result<FloodedDecryption, String> {
+        if estimate.level != ct.level() || (estimate.scale / ct.scale - 1.0).abs() > 1e-9 {
+            return Err(format!(
+                "estimate at level {} and scale {:e} does not describe a ciphertext at level {} and scale {:e}",
+                estimate.level, estimate.scale, ct.level(), ct.scale
+            ));
+        }
+        if params.queries == 0 {
+            return Err("at least one query must be allowed".to_string());
+        }
+        let n = self.context.params.poly_degree;
+        let coeff_std = estimate.error_std * ct.scale / (n as f64).sqrt();
+        let bound = (6.0 * coeff_std).max(1.0);
+        let flooding_std = (24.0 * params.queries as f64 * n as f64).sqrt()
+            * 2f64.powf(params.statistical_security as f64 / 2.0)
+            * bound;
+
+        let tables = self.context.tables(ct.level());
+        let log_q: f64 = tables.iter().map(|t| (t.modulus as f64).log2()).sum();
+        if flooding_std >= 2f64.powi(58) || (FLOODING_TAIL * flooding_std).log2() >= log_q - 2.0 {
+            return Err(format!(
+                "flooding deviation 2^{:.1} does not fit the {:.0}-bit modulus at level {}",
+                flooding_std.log2(), log_q, ct.level()
+            ));
+        }
+
+        let mut plain = self.decrypt(ct);
+        let noise = poly::sample_discrete_gaussian(n, flooding_std, FLOODING_TAIL);
+        plain.poly = plain.poly.add(&RnsPoly::from_signed(&noise, tables), tables);
+
+        let flooded_std = estimate.error_std.hypot((n as f64).sqrt() * flooding_std / ct.scale);
+        Ok(FloodedDecryption {
+            values: self.context.decode(&plain),
+            flooding_std,
+            precision_bits: estimate.precision_bits(),
+            flooded_precision_bits: -flooded_std.log2(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fhe::ckks::{CKKSContext, CKKSParameters, Encryptor, Evaluator, KeyGenerator};
+    use crate::fhe::ckks_precision::{NoiseEstimator, PrecisionReport};
+
+    /// x² under auto-rescaling, its noise estimate and its expected slots
+    fn encrypted_square(context: &CKKSContext, keygen: &KeyGenerator) -> (Ciphertext, NoiseEstimate, Vec<Complex64>) {
+        let scale = context.params.scale;
+        let encryptor = Encryptor::new(context, &keygen.public_key());
+        let evaluator = Evaluator::with_auto_rescale(context);
+        let estimator = NoiseEstimator::with_auto_rescale(context);
+
+        let slots = context.slot_count();
+        let x: Vec<Complex64> = (0..slots)
+            .map(|i| Complex64::new((i as f64 * 0.37).sin(), (i as f64 * 0.11).cos()))
+            .collect();
+        let rms = (x.iter().map(|z| z.norm_sqr()).sum::<f64>() / slots as f64).sqrt();
+        let ct = encryptor.encrypt(&context.encode(&x, scale, context.max_level()).unwrap());
+        let fresh = estimator.fresh(rms, scale, context.max_level());
+        (
+            evaluator.multiply_relin(&ct, &ct, &keygen.relin_keys()).unwrap(),
+            estimator.multiply(&fresh, &fresh).unwrap(),
+            x.iter().map(|z| z * z).collect(),
+        )
+    }
+
+    fn single() -> CKKSContext {
+        CKKSContext::with_params(CKKSParameters::new(4096, &[60, 40, 40, 60], 2f64.powi(40)).unwrap()).unwrap()
+    }
+
+    fn composite() -> CKKSContext {
+        let params = CKKSParameters::new(4096, &[60, 60, 40, 40, 40, 40, 60], 2f64.powi(80)).unwrap().with_primes_per_level(2);
+        CKKSContext::with_params(params).unwrap()
+    }
+
+    #[test]
+    fn flooded_precision_matches_the_prediction() {
+        for context in [single(), composite()] {
+            let keygen = KeyGenerator::new(&context);
+            let decryptor = Decryptor::new(&context, &keygen.secret_key());
+            let (square, estimate, expected) = encrypted_square(&context, &keygen);
+            for security in [20, 40] {
+                let flooded = decryptor.decrypt_flooded(&square, &estimate, &FloodingParameters::new(security)).unwrap();
+                let measured = PrecisionReport::compare(&flooded.values, &expected).unwrap().complex.mean_bits;
+                assert!(
+                    (measured - flooded.flooded_precision_bits).abs() < 1.5,
+                    "s = {}: predicted {:.1} measured {:.1}", security, flooded.flooded_precision_bits, measured
+                );
+                assert!(flooded.lost_bits() > security as f64 / 2.0 - 1.0);
+            }
+        }
+    }
+
+    #[test]
+    fn flooding_is_fresh_per_decryption() {
+        let context = single();
+        let keygen = KeyGenerator::new(&context);
+        let decryptor = Decryptor::new(&context, &keygen.secret_key());
+        let (square, estimate, _) = encrypted_square(&context, &keygen);
+        let params = FloodingParameters::new(20);
+        let first = decryptor.decrypt_flooded(&square, &estimate, &params).unwrap();
+        let second = decryptor.decrypt_flooded(&square, &estimate, &params).unwrap();
+        assert_ne!(first.values, second.values);
+
+        // k queries scale the deviation by sqrt(k)
+        let many = decryptor.decrypt_flooded(&square, &estimate, &params.with_queries(16)).unwrap();
+        assert!((many.flooding_std / first.flooding_std - 4.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn rejects_mismatched_or_oversized_flooding() {
+        let context = single();
+        let keygen = KeyGenerator::new(&context);
+        let decryptor = Decryptor::new(&context, &keygen.secret_key());
+        let (square, estimate, _) = encrypted_square(&context, &keygen);
+        let wrong_level = NoiseEstimate { level: estimate.level + 1, ..estimate };
+        assert!(decryptor.decrypt_flooded(&square, &wrong_level, &FloodingParameters::new(20)).is_err());
+        assert!(decryptor.decrypt_flooded(&square, &estimate, &FloodingParameters::new(20).with_queries(0)).is_err());
+        assert!(decryptor.decrypt_flooded(&square, &estimate, &FloodingParameters::new(128)).is_err());
+    }
+
+    #[test]
+    fn discrete_gaussian_deviation() {
+        let samples = poly::sample_discrete_gaussian(100_000, 1e12, FLOODING_TAIL);
+        let mean = samples.iter().map(|&x| x as f64).sum::<f64>() / samples.len() as f64;
+        let std = (samples.iter().map(|&x| (x as f64 - mean).powi(2)).sum::<f64>() / samples.len() as f64).sqrt();
+        assert!((std / 1e12 - 1.0).abs() < 0.02, "{:e}", std);
+        assert!(samples.iter().all(|&x| (x as f64).abs() <= FLOODING_TAIL * 1e12));
+    }
+}
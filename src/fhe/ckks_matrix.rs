@@ -0,0 +1,254 @@
+//! Encrypted matrix multiplication for CKKS
+//! Jiang–Kim–Lauter–Song product of two packed d×d matrices
+//!
+//! A d×d matrix sits row-major in d² slots, replicated across the slot
+//! vector, so d² must divide N/2. With the permutations
+//! σ(A)_(i,j) = A_(i,i+j), τ(B)_(i,j) = B_(i+j,j), φ(A)_(i,j) = A_(i,j+1) and
+//! ψ(B)_(i,j) = B_(i+1,j) (indices mod d), the product is
+//! A·B = Σ_k φ^k(σ(A)) ⊙ ψ^k(τ(B)). σ, τ and each φ^k are linear transforms
+//! of one level; ψ^k is a rotation by d·k. The products add up as size-3
+//! ciphertexts and are relinearized once, for a total depth of 3.
+
+use super::bfv::{GaloisKeys, RelinKeys};
+use super::ckks::{self, CKKSContext, Ciphertext, Encryptor, Decryptor, Evaluator, KeyGenerator};
+use super::linear_transform::LinearTransform;
+use num_complex::Complex64;
+
+/// Packed d×d matrix in one ciphertext
+#[derive(Clone, Debug)]
+pub struct EncryptedMatrix {
+    pub dimension: usize,
+    pub ciphertext: Ciphertext,
+}
+
+impl EncryptedMatrix {
+    /// Encrypts a square matrix row-major at `scale` and `level`
+    pub fn encrypt(context: &CKKSContext, encryptor: &Encryptor, matrix: &[Vec<f64>], scale: f64, level: usize) -> Result<Self, String> {
+        let d = matrix.len();
+        check_dimension(context, d)?;
+        if let Some(bad) = matrix.iter().position(|row| row.len() != d) {
+            return Err(format!("row {} has {} entries, expected {}", bad, matrix[bad].len(), d));
+        }
+        let values: Vec<Complex64> = (0..context.slot_count())
+            .map(|l| Complex64::new(matrix[(l / d) % d][l % d], 0.0))
+            .collect();
+        let plain = context.encode(&values, scale, level)?;
+        Ok(Self { dimension: d, ciphertext: encryptor.encrypt(&plain) })
+    }
+
+    pub fn decrypt(&self, decryptor: &Decryptor) -> Vec<Vec<f64>> {
+        let d = self.dimension;
+        let values = decryptor.decrypt_real(&self.ciphertext);
+        (0..d).map(|i| values[i * d..(i + 1) * d].to_vec()).collect()
+    }
+}
+
+fn check_dimension(context: &CKKSContext, d: usize) -> Result<(), String> {
+    let slots = context.slot_count();
+    if d < 2 || !d.is_power_of_two() || d * d > slots {
+        return Err(format!("dimension {} must be a power of two with d² <= {} slots", d, slots));
+    }
+    Ok(())
+}
+
+/// Plaintext reference product
+pub fn matmul_plain(a: &[Vec<f64>], b: &[Vec<f64>]) -> Vec<Vec<f64>> {
+    let d = a.len();
+    (0..d)
+        .map(|i| (0..d).map(|j| (0..d).map(|k| a[i][k] * b[k][j]).sum()).collect())
+        .collect()
+}
+
+/// Rotation steps of each permutation; Galois keys for `all()` suffice
+#[derive(Clone, Debug)]
+pub struct PermutationSteps {
+    pub sigma: Vec<i64>,
+    pub tau: Vec<i64>,
+    pub phi: Vec<i64>,
+    pub psi: Vec<i64>,
+}
+
+impl PermutationSteps {
+    pub fn all(&self) -> Vec<i64> {
+        let mut steps: Vec<i64> = [&self.sigma, &self.tau, &self.phi, &self.psi]
+            .into_iter()
+            .flatten()
+            .copied()
+            .collect();
+        steps.sort_unstable();
+        steps.dedup();
+        steps
+    }
+}
+
+/// Pre-encoded σ, τ and φ^k transforms for d×d products at one level
+pub struct MatrixMultiplier {
+    pub dimension: usize,
+    pub level: usize,
+    sigma: LinearTransform<ckks::Plaintext>,
+    tau: LinearTransform<ckks::Plaintext>,
+    phi: Vec<LinearTransform<ckks::Plaintext>>, // φ^k for k = 1..d
+}
+
+impl MatrixMultiplier {
+    /// Transforms for inputs at `level` (at least `depth()`)
+    pub fn new(context: &CKKSContext, dimension: usize, level: usize) -> Result<Self, String> {
+        let d = dimension;
+        check_dimension(context, d)?;
+        if level < Self::depth() || level > context.max_level() {
+            return Err(format!(
+                "level {} must lie in [{}, {}] for a depth-{} product",
+                level, Self::depth(), context.max_level(), Self::depth()
+            ));
+        }
+
+        // the matrix mapping slot vectors under the index map out(i, j) = in(src(i, j))
+        let permutation = |src: &dyn Fn(usize, usize) -> (usize, usize)| -> Vec<Vec<Complex64>> {
+            let mut rows = vec![vec![Complex64::new(0.0, 0.0); d * d]; d * d];
+            for i in 0..d {
+                for j in 0..d {
+                    let (si, sj) = src(i, j);
+                    rows[i * d + j][si * d + sj] = Complex64::new(1.0, 0.0);
+                }
+            }
+            rows
+        };
+
+        let sigma = LinearTransform::new_ckks(context, &permutation(&|i, j| (i, (i + j) % d)), level)?;
+        let tau = LinearTransform::new_ckks(context, &permutation(&|i, j| ((i + j) % d, j)), level)?;
+        let phi = (1..d)
+            .map(|k| LinearTransform::new_ckks(context, &permutation(&|i, j| (i, (j + k) % d)), level - 1))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Self { dimension: d, level, sigma, tau, phi })
+    }
+
+    /// Levels consumed by `encrypted_matmul`
+    pub fn depth() -> usize {
+        3
+    }
+
+    pub fn permutation_steps(&self) -> PermutationSteps {
+        let d = self.dimension as i64;
+        let mut phi: Vec<i64> = self.phi.iter().flat_map(|t| t.rotation_steps()).collect();
+        phi.sort_unstable();
+        phi.dedup();
+        PermutationSteps {
+            sigma: self.sigma.rotation_steps(),
+            tau: self.tau.rotation_steps(),
+            phi,
+            psi: (1..d).map(|k| d * k).collect(),
+        }
+    }
+
+    pub fn galois_keys(&self, keygen: &KeyGenerator) -> GaloisKeys {
+        keygen.galois_keys(&self.permutation_steps().all(), false)
+    }
+
+    /// A·B at the product of the input scales (rescaled by q_l when the
+    /// evaluator does not auto-rescale), `depth()` levels below `level`
+    pub fn encrypted_matmul(
+        &self,
+        evaluator: &Evaluator,
+        a: &EncryptedMatrix,
+        b: &EncryptedMatrix,
+        relin_keys: &RelinKeys,
+        galois_keys: &GaloisKeys,
+    ) -> Result<EncryptedMatrix, String> {
+        let d = self.dimension;
+        if a.dimension != d || b.dimension != d {
+            return Err(format!(
+                "cannot multiply {}×{} by {}×{} with a {}×{} multiplier",
+                a.dimension, a.dimension, b.dimension, b.dimension, d, d
+            ));
+        }
+        let a0 = self.sigma.evaluate(evaluator, &a.ciphertext, galois_keys)?;
+        let b0 = self.tau.evaluate(evaluator, &b.ciphertext, galois_keys)?;
+
+        let mut acc = evaluator.multiply(&evaluator.mod_switch_to(&a0, a0.level() - 1)?, &b0)?;
+        for (k, phi) in (1..d).zip(&self.phi) {
+            let ak = phi.evaluate(evaluator, &a0, galois_keys)?;
+            let bk = evaluator.rotate(&b0, (d * k) as i64, galois_keys)?;
+            evaluator.add_inplace(&mut acc, &evaluator.multiply(&ak, &bk)?)?;
+        }
+        evaluator.relinearize_inplace(&mut acc, relin_keys)?;
+        if !evaluator.auto_rescale {
+            evaluator.rescale_to_next_inplace(&mut acc)?;
+        }
+        Ok(EncryptedMatrix { dimension: d, ciphertext: acc })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fhe::ckks::CKKSParameters;
+    use rand::Rng;
+
+    fn random_matrix(d: usize, rng: &mut impl Rng) -> Vec<Vec<f64>> {
+        (0..d).map(|_| (0..d).map(|_| rng.gen_range(-1.0..1.0)).collect()).collect()
+    }
+
+    fn max_difference(a: &[Vec<f64>], b: &[Vec<f64>]) -> f64 {
+        a.iter().flatten().zip(b.iter().flatten()).map(|(x, y)| (x - y).abs()).fold(0.0, f64::max)
+    }
+
+    #[test]
+    fn plain_product() {
+        let a = vec![vec![1.0, 2.0], vec![3.0, 4.0]];
+        let b = vec![vec![0.0, 1.0], vec![1.0, 0.0]];
+        assert_eq!(matmul_plain(&a, &b), vec![vec![2.0, 1.0], vec![4.0, 3.0]]);
+    }
+
+    #[test]
+    fn encrypted_product() {
+        let mut rng = rand::thread_rng();
+        let params = CKKSParameters::new(2048, &[60, 40, 40, 40, 40, 60], 2f64.powi(40)).unwrap();
+        let context = CKKSContext::with_params(params).unwrap();
+        let keygen = KeyGenerator::new(&context);
+        let encryptor = Encryptor::new(&context, &keygen.public_key());
+        let decryptor = Decryptor::new(&context, &keygen.secret_key());
+        let relin_keys = keygen.relin_keys();
+
+        for d in [2usize, 8, 16] {
+            let multiplier = MatrixMultiplier::new(&context, d, 4).unwrap();
+            let steps = multiplier.permutation_steps();
+            assert_eq!(steps.psi.len(), d - 1);
+            assert!(steps.sigma.len() <= 2 * d - 2 && steps.tau.len() <= 2 * d - 2);
+            let galois_keys = multiplier.galois_keys(&keygen);
+
+            let (a, b) = (random_matrix(d, &mut rng), random_matrix(d, &mut rng));
+            let encrypted_a = EncryptedMatrix::encrypt(&context, &encryptor, &a, 2f64.powi(40), 4).unwrap();
+            let encrypted_b = EncryptedMatrix::encrypt(&context, &encryptor, &b, 2f64.powi(40), 4).unwrap();
+            assert!(max_difference(&encrypted_a.decrypt(&decryptor), &a) < 1e-6);
+
+            for evaluator in [Evaluator::new(&context), Evaluator::with_auto_rescale(&context)] {
+                let product = multiplier
+                    .encrypted_matmul(&evaluator, &encrypted_a, &encrypted_b, &relin_keys, &galois_keys)
+                    .unwrap();
+                assert_eq!(product.ciphertext.level(), 4 - MatrixMultiplier::depth());
+                let error = max_difference(&product.decrypt(&decryptor), &matmul_plain(&a, &b));
+                assert!(error < 1e-4, "d {}: {:e}", d, error);
+            }
+        }
+    }
+
+    #[test]
+    fn rejects_mismatched_dimensions() {
+        let params = CKKSParameters::new(2048, &[60, 40, 40, 40, 60], 2f64.powi(40)).unwrap();
+        let context = CKKSContext::with_params(params).unwrap();
+        let keygen = KeyGenerator::new(&context);
+        let encryptor = Encryptor::new(&context, &keygen.public_key());
+
+        assert!(MatrixMultiplier::new(&context, 3, 3).is_err());
+        assert!(MatrixMultiplier::new(&context, 64, 3).is_err());
+        assert!(MatrixMultiplier::new(&context, 4, 2).is_err());
+        assert!(EncryptedMatrix::encrypt(&context, &encryptor, &[vec![1.0, 2.0], vec![3.0]], 2f64.powi(40), 3).is_err());
+
+        let multiplier = MatrixMultiplier::new(&context, 4, 3).unwrap();
+        let small = EncryptedMatrix::encrypt(&context, &encryptor, &random_matrix(2, &mut rand::thread_rng()), 2f64.powi(40), 3).unwrap();
+        let galois_keys = multiplier.galois_keys(&keygen);
+        assert!(multiplier
+            .encrypted_matmul(&Evaluator::new(&context), &small, &small, &keygen.relin_keys(), &galois_keys)
+            .is_err());
+    }
+}
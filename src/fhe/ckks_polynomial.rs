@@ -0,0 +1,432 @@
+//! Polynomial approximation for CKKS
+//! Chebyshev interpolation and baby-step giant-step Paterson-Stockmeyer
+//!
+//! A closure on [a, b] is interpolated at Chebyshev nodes and evaluated
+//! on y = (2x - a - b)/(b - a) in the Chebyshev basis. Baby steps
+//! T_1..T_k (k = 2^l) and giant steps T_(k·2^j) cost ceil(log2 i) levels
+//! each; the polynomial is split recursively as p = q·T_m + r. Every term
+//! is brought to an exact target (level, scale) by a constant product, so
+//! the result keeps the input scale without mismatches. A term whose T_i
+//! has no level to spare takes its constant on a factor of T_i instead,
+//! and the map to [-1, 1] is folded into T_1 and T_2, so the output sits
+//! `depth()` = ceil(log2(d + 1)) levels below the input for degree d.
+//! x² is then formed without a constant product, which bounds the input
+//! scale by `max_input_scale()`.
+
+use super::bfv::RelinKeys;
+use super::ckks::{Ciphertext, Evaluator};
+use num_complex::Complex64;
+
+/// Chebyshev interpolant of a function on [a, b]
+#[derive(Clone, Debug)]
+pub struct ChebyshevApproximation {
+    /// c_i in p(y) = Σ c_i·T_i(y)
+    pub coeffs: Vec<f64>,
+    pub a: f64,
+    pub b: f64,
+    /// Largest |f(x) - p(x)| on a dense grid of [a, b]
+    pub max_error: f64,
+}
+
+impl ChebyshevApproximation {
+    /// Interpolates `f` at the degree + 1 Chebyshev nodes of [a, b]
+    pub fn fit<F: Fn(f64) -> f64>(f: F, a: f64, b: f64, degree: usize) -> Result<Self, String> {
+        if !a.is_finite() || !b.is_finite() || a >= b {
+            return Err(format!("invalid interval [{}, {}]", a, b));
+        }
+        let count = degree + 1;
+        let nodes: Vec<f64> = (0..count)
+            .map(|j| (std::f64::consts::PI * (j as f64 + 0.5) / count as f64).cos())
+            .collect();
+        let values: Vec<f64> = nodes.iter().map(|&y| f(a + (b - a) * (y + 1.0) / 2.0)).collect();
+        if let Some(bad) = values.iter().position(|v| !v.is_finite()) {
+            return Err(format!("function is not finite at x = {}", a + (b - a) * (nodes[bad] + 1.0) / 2.0));
+        }
+
+        let coeffs = (0..count)
+            .map(|i| {
+                let sum: f64 = nodes.iter().zip(&values)
+                    .map(|(&y, &v)| v * (i as f64 * y.acos()).cos())
+                    .sum();
+                let c = 2.0 * sum / count as f64;
+                if i == 0 { c / 2.0 } else { c }
+            })
+            .collect();
+
+        let mut approx = Self { coeffs, a, b, max_error: 0.0 };
+        const GRID: usize = 4096;
+        approx.max_error = (0..=GRID)
+            .map(|j| a + (b - a) * j as f64 / GRID as f64)
+            .map(|x| (f(x) - approx.evaluate(x)).abs())
+            .fold(0.0, f64::max);
+        Ok(approx)
+    }
+
+    pub fn degree(&self) -> usize {
+        self.coeffs.len() - 1
+    }
+
+    /// Plaintext evaluation by Clenshaw's recurrence
+    pub fn evaluate(&self, x: f64) -> f64 {
+        let y = (2.0 * x - self.a - self.b) / (self.b - self.a);
+        let (mut b1, mut b2) = (0.0, 0.0);
+        for &c in self.coeffs.iter().skip(1).rev() {
+            let b0 = 2.0 * y * b1 - b2 + c;
+            b2 = b1;
+            b1 = b0;
+        }
+        y * b1 - b2 + self.coeffs[0]
+    }
+
+    /// Levels consumed by `Evaluator::evaluate_chebyshev`
+    pub fn depth(&self) -> usize {
+        let plan = Plan::new(self);
+        (-plan.max_target(&plan.trimmed)) as usize
+    }
+
+    /// Largest input scale for a ciphertext whose level has modulus `q`
+    /// (`CKKSContext::level_modulus`): 2√2·q/(b - a), about 1.4·q on [-1, 1]
+    ///
+    /// Above it x² outgrows the scale the powers are kept at, and every
+    /// further power would compound the excess.
+    pub fn max_input_scale(&self, q: f64) -> f64 {
+        2.0 * 2f64.sqrt() * q / (self.b - self.a)
+    }
+}
+
+/// Baby-step size, interval map and level bookkeeping shared by planning
+/// and evaluation
+struct Plan {
+    baby: usize,       // k = 2^l
+    alpha: f64,        // T_1 = α·x + β
+    beta: f64,
+    trimmed: Vec<f64>, // coefficients with negligible terms zeroed
+}
+
+impl Plan {
+    fn new(approx: &ChebyshevApproximation) -> Self {
+        let max_abs = approx.coeffs.iter().fold(0.0f64, |m, c| m.max(c.abs()));
+        let trimmed = approx.coeffs.iter()
+            .map(|&c| if c.abs() <= 1e-14 * max_abs { 0.0 } else { c })
+            .collect();
+        let log_d = usize::BITS - approx.degree().leading_zeros();
+        Self {
+            baby: 1 << log_d.div_ceil(2).max(1),
+            alpha: 2.0 / (approx.b - approx.a),
+            beta: -(approx.a + approx.b) / (approx.b - approx.a),
+            trimmed,
+        }
+    }
+
+    /// Highest level (relative to x) at which c·T_i can be formed:
+    /// -ceil(log2(i + 1))
+    ///
+    /// T_i itself sits at -ceil(log2 i). Powers of two have to spend one
+    /// more level on the constant; any other i folds it into a product.
+    fn term_level(i: usize) -> i64 {
+        -((usize::BITS - i.leading_zeros()) as i64)
+    }
+
+    /// Largest giant step k·2^j not above the degree
+    fn giant(&self, degree: usize) -> usize {
+        let mut m = self.baby;
+        while 2 * m <= degree {
+            m *= 2;
+        }
+        m
+    }
+
+    /// Highest level (relative to x) at which `coeffs` can be evaluated
+    fn max_target(&self, coeffs: &[f64]) -> i64 {
+        let degree = coeffs.len() - 1;
+        if degree < self.baby {
+            return (1..coeffs.len())
+                .filter(|&i| coeffs[i] != 0.0)
+                .map(Self::term_level)
+                .min()
+                .unwrap_or(-1);
+        }
+        let m = self.giant(degree);
+        let (q, r) = split(coeffs, m);
+        let level = Self::term_level(m).min(self.max_target(&q) - 1);
+        if r.iter().any(|&c| c != 0.0) { level.min(self.max_target(&r)) } else { level }
+    }
+}
+
+/// T_2..T_k and the giant steps over the input x
+///
+/// T_1 = α·x + β is never formed: every use takes it from x with a
+/// constant product, which the map to [-1, 1] then costs nothing extra.
+struct PowerBasis<'a> {
+    x: &'a Ciphertext,
+    powers: Vec<Option<Ciphertext>>,
+    relin_keys: &'a RelinKeys,
+}
+
+impl PowerBasis<'_> {
+    fn get(&self, i: usize) -> &Ciphertext {
+        self.powers[i].as_ref().unwrap()
+    }
+}
+
+/// p = q·T_m + r in the Chebyshev basis for m <= deg p < 2m, using
+/// T_m·T_i = (T_(m+i) + T_(m-i)) / 2
+fn split(coeffs: &[f64], m: usize) -> (Vec<f64>, Vec<f64>) {
+    let degree = coeffs.len() - 1;
+    let mut q = vec![0.0; degree - m + 1];
+    q[0] = coeffs[m];
+    for i in 1..q.len() {
+        q[i] = 2.0 * coeffs[m + i];
+    }
+    let mut r = coeffs[..m].to_vec();
+    for i in 1..q.len() {
+        r[m - i] -= q[i] / 2.0;
+    }
+    (q, r)
+}
+
+impl Evaluator {
+    /// p(ct) for a Chebyshev approximation, at the input scale and
+    /// `approx.depth()` levels lower
+    ///
+    /// The input scale must not exceed `approx.max_input_scale`. Slots
+    /// outside [a, b] are not approximated and may blow up.
+    pub fn evaluate_chebyshev(&self, ct: &Ciphertext, approx: &ChebyshevApproximation, relin_keys: &RelinKeys) -> Result<Ciphertext, String> {
+        let depth = approx.depth();
+        if ct.level() < depth {
+            return Err(format!(
+                "degree-{} approximation needs {} levels, ciphertext is at level {}",
+                approx.degree(), depth, ct.level()
+            ));
+        }
+        let plan = Plan::new(approx);
+
+        // T_2..T_k, then T_(2k), T_(4k), ... up to the degree
+        let degree = approx.degree();
+        let mut basis = PowerBasis { x: ct, powers: vec![None; degree.max(plan.baby) + 1], relin_keys };
+        for i in 2..=plan.baby.min(degree.max(1)) {
+            let t = if i == 2 {
+                self.chebyshev_square_input(&plan, &basis)?
+            } else if i % 2 == 0 {
+                self.chebyshev_double(basis.get(i / 2), relin_keys)?
+            } else {
+                // T_i = 2·T_(i/2+1)·T_(i/2) - T_1
+                let ta = basis.get(i / 2 + 1);
+                let tb = match i / 2 {
+                    1 => self.linear_term(&plan, ct, 1.0, ta.level(), ta.scale)?,
+                    half => basis.get(half).clone(),
+                };
+                let mut t = self.chebyshev_product(ta, &tb, relin_keys)?;
+                let t1 = self.linear_term(&plan, ct, -1.0, t.level(), t.scale)?;
+                self.add_inplace(&mut t, &t1)?;
+                t
+            };
+            basis.powers[i] = Some(t);
+        }
+        let mut m = plan.baby;
+        while 2 * m <= degree {
+            let t = self.chebyshev_double(basis.get(m), relin_keys)?;
+            basis.powers[2 * m] = Some(t);
+            m *= 2;
+        }
+
+        let target = (ct.level() as i64 + plan.max_target(&plan.trimmed)) as usize;
+        self.evaluate_split(&plan, &basis, &plan.trimmed, target, ct.scale)
+    }
+
+    fn evaluate_split(&self, plan: &Plan, basis: &PowerBasis, coeffs: &[f64], level: usize, scale: f64) -> Result<Ciphertext, String> {
+        let degree = coeffs.len() - 1;
+        if degree < plan.baby {
+            return self.evaluate_leaf(plan, basis, coeffs, level, scale);
+        }
+
+        // q·T_m lands on (level, scale) after the product's rescale
+        let m = plan.giant(degree);
+        let (q, r) = split(coeffs, m);
+        let t_m = self.mod_switch_to(basis.get(m), level + 1)?;
+        let q_next = self.context.level_modulus(level + 1);
+        let q_ct = self.evaluate_split(plan, basis, &q, level + 1, scale * q_next / t_m.scale)?;
+        let mut result = self.multiply_relin_rescale(&q_ct, &t_m, basis.relin_keys)?;
+        result.scale = scale;
+
+        if r.iter().any(|&c| c != 0.0) {
+            let r_ct = self.evaluate_split(plan, basis, &r, level, scale)?;
+            self.add_inplace(&mut result, &r_ct)?;
+        }
+        Ok(result)
+    }
+
+    /// Σ c_i·T_i for i < k, every term formed on (level, scale)
+    fn evaluate_leaf(&self, plan: &Plan, basis: &PowerBasis, coeffs: &[f64], level: usize, scale: f64) -> Result<Ciphertext, String> {
+        let mut acc = self.linear_term(plan, basis.x, 0.0, level, scale)?;
+        for (i, &c) in coeffs.iter().enumerate().skip(1).filter(|(_, c)| **c != 0.0) {
+            let term = self.chebyshev_term(plan, basis, i, c, level, scale)?;
+            self.add_inplace(&mut acc, &term)?;
+        }
+        self.add_const(&acc, Complex64::new(coeffs[0], 0.0))
+    }
+
+    /// c·T_i on (level, scale)
+    ///
+    /// A T_i above `level` takes one constant product. One already at
+    /// `level` is not a power of two (see `Plan::term_level`) and is
+    /// rebuilt as 2·(c·T_a)·T_b - c·T_(b-a), with b the largest power of
+    /// two below i, so that the constant lands on T_a one level higher.
+    fn chebyshev_term(&self, plan: &Plan, basis: &PowerBasis, i: usize, c: f64, level: usize, scale: f64) -> Result<Ciphertext, String> {
+        if i == 1 {
+            return self.linear_term(plan, basis.x, c, level, scale);
+        }
+        if basis.get(i).level() > level {
+            return self.multiply_const_to(basis.get(i), c, level, scale);
+        }
+        let b = 1 << (usize::BITS - 1 - i.leading_zeros());
+        let tb = self.mod_switch_to(basis.get(b), level + 1)?;
+        let q_next = self.context.level_modulus(level + 1);
+        let ta = self.chebyshev_term(plan, basis, i - b, c, level + 1, scale * q_next / tb.scale)?;
+        let mut result = self.chebyshev_product(&ta, &tb, basis.relin_keys)?;
+        result.scale = scale;
+        let diff = self.chebyshev_term(plan, basis, 2 * b - i, -c, level, scale)?;
+        self.add_inplace(&mut result, &diff)?;
+        Ok(result)
+    }
+
+    /// c·T_1 = c·α·x + c·β on (level, scale), below the input's level
+    fn linear_term(&self, plan: &Plan, x: &Ciphertext, c: f64, level: usize, scale: f64) -> Result<Ciphertext, String> {
+        let term = self.multiply_const_to(x, c * plan.alpha, level, scale)?;
+        self.add_const(&term, Complex64::new(c * plan.beta, 0.0))
+    }
+
+    /// T_2 = 2·T_1² - 1 = 2α²·x² + 4αβ·x + 2β² - 1, one level below x
+    ///
+    /// The factor 2α² is taken into the scale of x², which is exact; an
+    /// integer factor then brings that scale back to about q.
+    fn chebyshev_square_input(&self, plan: &Plan, basis: &PowerBasis) -> Result<Ciphertext, String> {
+        let x = basis.x;
+        let (alpha, beta) = (plan.alpha, plan.beta);
+        let q = self.context.level_modulus(x.level());
+        let mut t = self.multiply_relin_rescale(x, x, basis.relin_keys)?;
+        t.scale /= 2.0 * alpha * alpha;
+        if t.scale > q * (1.0 + 1e-9) {
+            return Err(format!(
+                "input scale {:e} is above {:e}, the most x² leaves room for on this interval; \
+                 encode the input at a lower scale",
+                x.scale, 2f64.sqrt() * alpha * q
+            ));
+        }
+        let k = (q / t.scale).round() as u64;
+        self.multiply_integer_inplace(&mut t, k);
+        if beta != 0.0 {
+            let linear = self.multiply_const_to(x, 4.0 * alpha * beta, t.level(), t.scale)?;
+            self.add_inplace(&mut t, &linear)?;
+        }
+        self.add_const(&t, Complex64::new(2.0 * beta * beta - 1.0, 0.0))
+    }
+
+    /// k·ct at k times the scale: the values stay, the scale grows
+    /// without a rescale
+    fn multiply_integer_inplace(&self, ct: &mut Ciphertext, k: u64) {
+        let tables = self.context.tables(ct.level());
+        let scalars: Vec<u64> = tables.iter().map(|t| k % t.modulus).collect();
+        for p in ct.polys.iter_mut() {
+            *p = p.mul_scalars(&scalars, tables);
+        }
+        ct.scale *= k as f64;
+    }
+
+    /// T_(2i) = 2·T_i² - 1
+    pub(crate) fn chebyshev_double(&self, t: &Ciphertext, relin_keys: &RelinKeys) -> Result<Ciphertext, String> {
+        let square = self.multiply_relin_rescale(t, t, relin_keys)?;
+        let doubled = self.add(&square, &square)?;
+        self.add_const(&doubled, Complex64::new(-1.0, 0.0))
+    }
+
+    /// 2·T_a·T_b = T_(a+b) + T_(a-b)
+    fn chebyshev_product(&self, ta: &Ciphertext, tb: &Ciphertext, relin_keys: &RelinKeys) -> Result<Ciphertext, String> {
+        let product = self.multiply_relin_rescale(ta, tb, relin_keys)?;
+        self.add(&product, &product)
+    }
+
+    pub(crate) fn multiply_relin_rescale(&self, a: &Ciphertext, b: &Ciphertext, relin_keys: &RelinKeys) -> Result<Ciphertext, String> {
+        let mut result = self.multiply_relin(a, b, relin_keys)?;
+        if !self.auto_rescale {
+            self.rescale_to_next_inplace(&mut result)?;
+        }
+        Ok(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fhe::ckks::{CKKSContext, CKKSParameters, Decryptor, Encryptor, KeyGenerator};
+
+    #[test]
+    fn fit_and_plaintext_evaluation() {
+        let approx = ChebyshevApproximation::fit(|x: f64| x.exp(), -2.0, 2.0, 15).unwrap();
+        assert_eq!(approx.degree(), 15);
+        assert!(approx.max_error < 1e-9);
+        assert!((approx.evaluate(0.7) - 0.7f64.exp()).abs() < 1e-9);
+
+        // Polynomials of at most the fitted degree are reproduced
+        let cubic = ChebyshevApproximation::fit(|x: f64| 4.0 * x * x * x - 3.0 * x, -1.0, 1.0, 3).unwrap();
+        assert!(cubic.coeffs[..3].iter().all(|c| c.abs() < 1e-12));
+        assert!((cubic.coeffs[3] - 1.0).abs() < 1e-12);
+
+        assert!(ChebyshevApproximation::fit(|x: f64| x, 1.0, 1.0, 3).is_err());
+        assert!(ChebyshevApproximation::fit(|x: f64| x.ln(), -1.0, 1.0, 4).is_err());
+    }
+
+    #[test]
+    fn depth_is_logarithmic_in_the_degree() {
+        for degree in 1usize..=130 {
+            let coeffs: Vec<f64> = (0..=degree).map(|i| 1.0 / (i + 1) as f64).collect();
+            let log = (usize::BITS - degree.leading_zeros()) as usize;
+            for (a, b) in [(-1.0, 1.0), (0.0, 2.0), (-8.0, 8.0)] {
+                let approx = ChebyshevApproximation { coeffs: coeffs.clone(), a, b, max_error: 0.0 };
+                assert_eq!(approx.depth(), log, "degree {} on [{}, {}]", degree, a, b);
+            }
+        }
+    }
+
+    #[test]
+    fn encrypted_evaluation() {
+        let params = CKKSParameters::new(2048, &[60, 40, 40, 40, 40, 40, 40, 40, 60], 2f64.powi(40)).unwrap();
+        let context = CKKSContext::with_params(params).unwrap();
+        let keygen = KeyGenerator::new(&context);
+        let encryptor = Encryptor::new(&context, &keygen.public_key());
+        let decryptor = Decryptor::new(&context, &keygen.secret_key());
+        let relin_keys = keygen.relin_keys();
+
+        type Case = (fn(f64) -> f64, f64, f64, usize);
+        let cases: [Case; 3] = [
+            (|x| 1.0 / (1.0 + (-x).exp()), -8.0, 8.0, 31),
+            (|x| 1.0 / x, 1.0, 8.0, 31),
+            (|x| x.sin(), -1.0, 1.0, 12),
+        ];
+        for evaluator in [Evaluator::new(&context), Evaluator::with_auto_rescale(&context)] {
+            for &(f, a, b, degree) in &cases {
+                let approx = ChebyshevApproximation::fit(f, a, b, degree).unwrap();
+                let xs: Vec<f64> = (0..1024).map(|i| a + (b - a) * i as f64 / 1023.0).collect();
+                let top = context.max_level();
+                let scale = approx.max_input_scale(context.level_modulus(top)).min(context.params.scale);
+                let values: Vec<Complex64> = xs.iter().map(|&x| Complex64::new(x, 0.0)).collect();
+                let ct = encryptor.encrypt(&context.encode(&values, scale, top).unwrap());
+                let result = evaluator.evaluate_chebyshev(&ct, &approx, &relin_keys).unwrap();
+
+                assert_eq!(ct.level() - result.level(), approx.depth());
+                assert!((result.scale / ct.scale - 1.0).abs() < 1e-9);
+                let error = xs.iter().zip(decryptor.decrypt_real(&result))
+                    .map(|(&x, y)| (approx.evaluate(x) - y).abs())
+                    .fold(0.0, f64::max);
+                assert!(error < 1e-4, "degree {} on [{}, {}]: {:e}", degree, a, b, error);
+            }
+        }
+
+        let deep = ChebyshevApproximation::fit(|x: f64| x.tanh(), -4.0, 4.0, 255).unwrap();
+        let ct = encryptor.encrypt(&context.encode_real(&[0.5]).unwrap());
+        assert!(Evaluator::new(&context).evaluate_chebyshev(&ct, &deep, &relin_keys).is_err());
+        let wide = ChebyshevApproximation::fit(|x: f64| x.tanh(), -8.0, 8.0, 7).unwrap();
+        let error = Evaluator::new(&context).evaluate_chebyshev(&ct, &wide, &relin_keys).unwrap_err();
+        assert!(error.contains("lower scale"), "{}", error);
+    }
+}
@@ -0,0 +1,527 @@
+//! CKKS precision analysis
+//! Measured precision with the secret key and predicted precision without
+//!
+//! Precision is counted in bits: -log2 |x' - x| for a decrypted slot x'
+//! and its expected value x, capped at the 53 bits of an f64. The
+//! analyzer decrypts and reports these per slot, for the complex values
+//! and for their real and imaginary parts separately.
+//!
+//! The estimator follows a ciphertext through the evaluator's operations
+//! and tracks the standard deviation of its slot error. Coefficient noise
+//! of variance v at scale Δ shows up in every slot with variance N·v/Δ²,
+//! and slot errors multiply through products like the values themselves:
+//! (x + ε_x)(y + ε_y) - xy = x·ε_y + y·ε_x + ε_x·ε_y. The noise terms are
+//! the usual independence heuristics: fresh encryption
+//! σ²(1 + N·V_u + N·V_s) plus 1/12 for rounding the encoding, rescaling
+//! (1 + N·V_s)/12, and key switching N·σ²·Σ q_i²/(12·P²) plus the rounding
+//! of the division by P, for ternary variances V_u = 2/3 and V_s = 2/3
+//! (h/N for a secret of Hamming weight h). Plaintext operands are
+//! `encoded` estimates, which carry only the rounding of the encoding.
+//! Encoding and decoding in f64 add about log2(N) ulps of the values,
+//! which bounds the precision near 50 bits at large scales. The predicted
+//! precision -log2 ε tracks the mean of the measured bits to within about
+//! a bit.
+
+use super::bfv::SecretKey;
+use super::ckks::{CKKSContext, Ciphertext, Decryptor};
+use super::poly;
+use num_complex::Complex64;
+use std::fmt;
+
+/// Bits reported for an exact slot
+pub const MAX_PRECISION_BITS: f64 = f64::MANTISSA_DIGITS as f64;
+
+fn precision_bits(error: f64) -> f64 {
+    if error == 0.0 {
+        MAX_PRECISION_BITS
+    } else {
+        (-error.log2()).min(MAX_PRECISION_BITS)
+    }
+}
+
+// ==================== MEASURED PRECISION ====================
+
+/// Distribution of the precision in bits over the compared slots
+#[derive(Clone, Copy, Debug)]
+pub struct PrecisionStats {
+    pub min_bits: f64,
+    pub mean_bits: f64,
+    pub stddev_bits: f64,
+    /// Largest absolute error, which sets `min_bits`
+    pub max_error: f64,
+}
+
+impl PrecisionStats {
+    fn from_errors(errors: &[f64]) -> Self {
+        let bits: Vec<f64> = errors.iter().map(|&e| precision_bits(e)).collect();
+        let count = bits.len() as f64;
+        let mean_bits = bits.iter().sum::<f64>() / count;
+        let variance = bits.iter().map(|b| (b - mean_bits).powi(2)).sum::<f64>() / count;
+        Self {
+            min_bits: bits.iter().copied().fold(f64::INFINITY, f64::min),
+            mean_bits,
+            stddev_bits: variance.sqrt(),
+            max_error: errors.iter().copied().fold(0.0, f64::max),
+        }
+    }
+}
+
+/// Precision of decrypted slots against their expected values
+#[derive(Clone, Debug)]
+pub struct PrecisionReport {
+    /// -log2 |x'_j - x_j| per compared slot
+    pub slot_bits: Vec<f64>,
+    pub complex: PrecisionStats,
+    pub real: PrecisionStats,
+    pub imag: PrecisionStats,
+}
+
+impl PrecisionReport {
+    /// Compares `values` with `expected` over the first `expected.len()` slots
+    pub fn compare(values: &[Complex64], expected: &[Complex64]) -> Result<Self, String> {
+        if expected.is_empty() || expected.len() > values.len() {
+            return Err(format!(
+                "cannot compare {} expected values with {} slots",
+                expected.len(), values.len()
+            ));
+        }
+        let diffs: Vec<Complex64> = values.iter().zip(expected).map(|(v, x)| v - x).collect();
+        let errors: Vec<f64> = diffs.iter().map(|d| d.norm()).collect();
+        let real: Vec<f64> = diffs.iter().map(|d| d.re.abs()).collect();
+        let imag: Vec<f64> = diffs.iter().map(|d| d.im.abs()).collect();
+        Ok(Self {
+            slot_bits: errors.iter().map(|&e| precision_bits(e)).collect(),
+            complex: PrecisionStats::from_errors(&errors),
+            real: PrecisionStats::from_errors(&real),
+            imag: PrecisionStats::from_errors(&imag),
+        })
+    }
+}
+
+impl fmt::Display for PrecisionReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{} slots", self.slot_bits.len())?;
+        for (name, stats) in [("complex", &self.complex), ("real", &self.real), ("imag", &self.imag)] {
+            writeln!(
+                f,
+                "{:>8}: min {:.2} mean {:.2} stddev {:.2} bits (max error {:.2e})",
+                name, stats.min_bits, stats.mean_bits, stats.stddev_bits, stats.max_error
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Decrypts with the secret key and measures the precision of the slots
+pub struct PrecisionAnalyzer {
+    decryptor: Decryptor,
+}
+
+impl PrecisionAnalyzer {
+    pub fn new(context: &CKKSContext, secret_key: &SecretKey) -> Self {
+        Self { decryptor: Decryptor::new(context, secret_key) }
+    }
+
+    /// Precision of `ct` over the first `expected.len()` slots
+    pub fn analyze(&self, ct: &Ciphertext, expected: &[Complex64]) -> Result<PrecisionReport, String> {
+        PrecisionReport::compare(&self.decryptor.decrypt_complex(ct), expected)
+    }
+
+    /// Precision against real expected values
+    pub fn analyze_real(&self, ct: &Ciphertext, expected: &[f64]) -> Result<PrecisionReport, String> {
+        let expected: Vec<Complex64> = expected.iter().map(|&x| Complex64::new(x, 0.0)).collect();
+        self.analyze(ct, &expected)
+    }
+}
+
+// ==================== PREDICTED PRECISION ====================
+
+/// Predicted state of a ciphertext after a sequence of operations
+#[derive(Clone, Copy, Debug)]
+pub struct NoiseEstimate {
+    pub level: usize,
+    pub scale: f64,
+    /// Root mean square of the slot values
+    pub message_rms: f64,
+    /// Standard deviation of the complex slot error
+    pub error_std: f64,
+}
+
+impl NoiseEstimate {
+    /// Expected mean precision in bits, -log2 of the error's standard deviation
+    pub fn precision_bits(&self) -> f64 {
+        precision_bits(self.error_std)
+    }
+}
+
+/// Key-less precision predictions mirroring `ckks::Evaluator`
+pub struct NoiseEstimator {
+    context: CKKSContext,
+    /// Rescale after products as `Evaluator::with_auto_rescale` does
+    pub auto_rescale: bool,
+    /// Variance of the secret's coefficients
+    secret_variance: f64,
+}
+
+impl NoiseEstimator {
+    /// Estimates for a uniform ternary secret (`KeyGenerator::new`)
+    pub fn new(context: &CKKSContext) -> Self {
+        Self { context: context.clone(), auto_rescale: false, secret_variance: 2.0 / 3.0 }
+    }
+
+    pub fn with_auto_rescale(context: &CKKSContext) -> Self {
+        Self { auto_rescale: true, ..Self::new(context) }
+    }
+
+    /// Estimates for a secret of `hamming_weight` nonzero coefficients
+    /// (`KeyGenerator::with_hamming_weight`)
+    pub fn with_hamming_weight(mut self, hamming_weight: usize) -> Self {
+        self.secret_variance = hamming_weight as f64 / self.context.params.poly_degree as f64;
+        self
+    }
+
+    fn n(&self) -> f64 {
+        self.context.params.poly_degree as f64
+    }
+
+    /// Slot error variance of coefficient noise with variance `v` at `scale`
+    fn slot_variance(&self, v: f64, scale: f64) -> f64 {
+        self.n() * v / (scale * scale)
+    }
+
+    /// Coefficient variance of rounding c0 + c1·s
+    fn rounding_variance(&self) -> f64 {
+        (1.0 + self.n() * self.secret_variance) / 12.0
+    }
+
+    /// Fresh encryption of slots with root mean square `message_rms`
+    pub fn fresh(&self, message_rms: f64, scale: f64, level: usize) -> NoiseEstimate {
+        let sigma2 = poly::ERROR_STD_DEV * poly::ERROR_STD_DEV;
+        let v = sigma2 * (1.0 + self.n() * (2.0 / 3.0 + self.secret_variance));
+        self.with_encoding(v, message_rms, scale, level)
+    }
+
+    /// Plaintext encoding of slots with root mean square `message_rms`,
+    /// the operand of `add_plain` and `mul_plain`
+    pub fn encoded(&self, message_rms: f64, scale: f64, level: usize) -> NoiseEstimate {
+        self.with_encoding(0.0, message_rms, scale, level)
+    }
+
+    /// Coefficient noise of variance `v` plus the rounding and f64 error
+    /// of encoding
+    fn with_encoding(&self, v: f64, message_rms: f64, scale: f64, level: usize) -> NoiseEstimate {
+        // the f64 FFTs of encoding and decoding lose about log2 N ulps
+        let float_error = message_rms * self.n().log2() * f64::EPSILON / 2.0;
+        let error_std = (self.slot_variance(v + 1.0 / 12.0, scale) + float_error * float_error).sqrt();
+        NoiseEstimate { level, scale, message_rms, error_std }
+    }
+
+    /// Sum at the lower of the two levels
+    pub fn add(&self, a: &NoiseEstimate, b: &NoiseEstimate) -> NoiseEstimate {
+        NoiseEstimate {
+            level: a.level.min(b.level),
+            scale: a.scale,
+            message_rms: a.message_rms + b.message_rms,
+            error_std: a.error_std.hypot(b.error_std),
+        }
+    }
+
+    /// Difference, with the same noise as the sum
+    pub fn sub(&self, a: &NoiseEstimate, b: &NoiseEstimate) -> NoiseEstimate {
+        self.add(a, b)
+    }
+
+    /// Sum with an `encoded` plaintext at the ciphertext's scale
+    pub fn add_plain(&self, a: &NoiseEstimate, plain: &NoiseEstimate) -> NoiseEstimate {
+        self.add(a, plain)
+    }
+
+    /// Sum with a constant, whose two rounded coefficients add little
+    pub fn add_const(&self, a: &NoiseEstimate, value: Complex64) -> NoiseEstimate {
+        let rounding = 1.0 / (6.0 * a.scale * a.scale);
+        NoiseEstimate {
+            message_rms: a.message_rms.hypot(value.norm()),
+            error_std: (a.error_std.powi(2) + rounding).sqrt(),
+            ..*a
+        }
+    }
+
+    /// Product with relinearization, rescaled in auto mode
+    pub fn multiply(&self, a: &NoiseEstimate, b: &NoiseEstimate) -> Result<NoiseEstimate, String> {
+        self.rescale_if_auto(self.key_switch(&Self::product(a, b)))
+    }
+
+    /// Product with an `encoded` plaintext, rescaled in auto mode
+    pub fn mul_plain(&self, a: &NoiseEstimate, plain: &NoiseEstimate) -> Result<NoiseEstimate, String> {
+        self.rescale_if_auto(Self::product(a, plain))
+    }
+
+    /// Tensor product without key switching
+    fn product(a: &NoiseEstimate, b: &NoiseEstimate) -> NoiseEstimate {
+        let (ea, eb) = (a.error_std, b.error_std);
+        let variance = (a.message_rms * eb).powi(2) + (b.message_rms * ea).powi(2) + (ea * eb).powi(2);
+        NoiseEstimate {
+            level: a.level.min(b.level),
+            scale: a.scale * b.scale,
+            message_rms: a.message_rms * b.message_rms,
+            error_std: variance.sqrt(),
+        }
+    }
+
+    /// Rotation: one key switch
+    pub fn rotate(&self, a: &NoiseEstimate) -> NoiseEstimate {
+        self.key_switch(a)
+    }
+
+    /// Conjugation: one key switch, like a rotation
+    pub fn conjugate(&self, a: &NoiseEstimate) -> NoiseEstimate {
+        self.key_switch(a)
+    }
+
+    /// Multiplication by a constant encoded like `Evaluator::multiply_const`
+    pub fn multiply_const(&self, a: &NoiseEstimate, value: Complex64) -> Result<NoiseEstimate, String> {
+        let const_scale = if self.auto_rescale {
+            self.context.level_modulus(a.level)
+        } else {
+            self.context.params.scale
+        };
+        // the encoded constant is off by up to 1/2 in each part
+        let rounding = a.message_rms * a.message_rms / (6.0 * const_scale * const_scale);
+        let result = NoiseEstimate {
+            level: a.level,
+            scale: a.scale * const_scale,
+            message_rms: a.message_rms * value.norm(),
+            error_std: ((a.error_std * value.norm()).powi(2) + rounding).sqrt(),
+        };
+        self.rescale_if_auto(result)
+    }
+
+    /// Division by q_l and rounding
+    pub fn rescale(&self, a: &NoiseEstimate) -> Result<NoiseEstimate, String> {
+        if a.level == 0 {
+            return Err("cannot rescale a level-0 ciphertext".to_string());
+        }
+        let scale = a.scale / self.context.level_modulus(a.level);
+        let variance = a.error_std.powi(2) + self.slot_variance(self.rounding_variance(), scale);
+        Ok(NoiseEstimate { level: a.level - 1, scale, error_std: variance.sqrt(), ..*a })
+    }
+
+    /// Dropping primes down to `level` leaves the error as it is
+    pub fn mod_switch_to(&self, a: &NoiseEstimate, level: usize) -> Result<NoiseEstimate, String> {
+        if level > a.level {
+            return Err(format!("cannot switch a level-{} ciphertext up to level {}", a.level, level));
+        }
+        Ok(NoiseEstimate { level, ..*a })
+    }
+
+    fn rescale_if_auto(&self, a: NoiseEstimate) -> Result<NoiseEstimate, String> {
+        if self.auto_rescale { self.rescale(&a) } else { Ok(a) }
+    }
+
+    /// Adds the noise of one key switch at the estimate's level
+    fn key_switch(&self, a: &NoiseEstimate) -> NoiseEstimate {
+        let params = &self.context.params;
+        let sigma2 = poly::ERROR_STD_DEV * poly::ERROR_STD_DEV;
+        let p = params.special_modulus as f64;
+        let digits: f64 = params.cipher_moduli[..self.context.limb_count(a.level)].iter()
+            .map(|&q| (q as f64 / p).powi(2) / 12.0)
+            .sum();
+        let v = self.n() * sigma2 * digits + self.rounding_variance();
+        NoiseEstimate { error_std: (a.error_std.powi(2) + self.slot_variance(v, a.scale)).sqrt(), ..*a }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fhe::ckks::{CKKSParameters, Encryptor, Evaluator, KeyGenerator};
+
+    #[test]
+    fn report_statistics() {
+        let expected = [Complex64::new(1.0, 0.0), Complex64::new(0.0, 1.0)];
+        let values = [Complex64::new(1.0 + 2f64.powi(-10), 0.0), Complex64::new(0.0, 1.0 - 2f64.powi(-20))];
+        let report = PrecisionReport::compare(&values, &expected).unwrap();
+        assert_eq!(report.slot_bits, vec![10.0, 20.0]);
+        assert_eq!((report.complex.min_bits, report.complex.mean_bits, report.complex.stddev_bits), (10.0, 15.0, 5.0));
+        assert_eq!(report.complex.max_error, 2f64.powi(-10));
+        assert_eq!((report.real.min_bits, report.imag.min_bits), (10.0, 20.0));
+        assert_eq!(report.imag.mean_bits, (MAX_PRECISION_BITS + 20.0) / 2.0);
+
+        let exact = PrecisionReport::compare(&expected, &expected).unwrap();
+        assert_eq!(exact.complex.min_bits, MAX_PRECISION_BITS);
+        assert!(PrecisionReport::compare(&values, &[]).is_err());
+        assert!(PrecisionReport::compare(&values[..1], &expected).is_err());
+    }
+
+    /// Predicted and measured mean precision along x², rot(x²), c·x and x⁴
+    fn check_estimates(params: CKKSParameters, hamming_weight: Option<usize>, auto_rescale: bool) {
+        let scale = params.scale;
+        let context = CKKSContext::with_params(params).unwrap();
+        let keygen = match hamming_weight {
+            Some(h) => KeyGenerator::with_hamming_weight(&context, h),
+            None => KeyGenerator::new(&context),
+        };
+        let encryptor = Encryptor::new(&context, &keygen.public_key());
+        let analyzer = PrecisionAnalyzer::new(&context, &keygen.secret_key());
+        let (evaluator, mut estimator) = if auto_rescale {
+            (Evaluator::with_auto_rescale(&context), NoiseEstimator::with_auto_rescale(&context))
+        } else {
+            (Evaluator::new(&context), NoiseEstimator::new(&context))
+        };
+        if let Some(h) = hamming_weight {
+            estimator = estimator.with_hamming_weight(h);
+        }
+        let relin_keys = keygen.relin_keys();
+        let galois_keys = keygen.galois_keys(&[1], false);
+
+        let slots = context.slot_count();
+        let x: Vec<Complex64> = (0..slots)
+            .map(|i| Complex64::new((i as f64 * 0.37).sin(), (i as f64 * 0.11).cos()))
+            .collect();
+        let rms = (x.iter().map(|z| z.norm_sqr()).sum::<f64>() / slots as f64).sqrt();
+        let check = |name: &str, ct: &Ciphertext, estimate: &NoiseEstimate, expected: &dyn Fn(usize) -> Complex64| {
+            let expected: Vec<Complex64> = (0..slots).map(expected).collect();
+            let measured = analyzer.analyze(ct, &expected).unwrap().complex.mean_bits;
+            assert_eq!(ct.level(), estimate.level, "{}", name);
+            assert!(
+                (measured - estimate.precision_bits()).abs() < 1.5,
+                "{}: predicted {:.1} measured {:.1} bits", name, estimate.precision_bits(), measured
+            );
+        };
+        let rescale = |ct: &mut Ciphertext, estimate: &mut NoiseEstimate| {
+            if !auto_rescale {
+                evaluator.rescale_to_next_inplace(ct).unwrap();
+                *estimate = estimator.rescale(estimate).unwrap();
+            }
+        };
+
+        let ct = encryptor.encrypt(&context.encode(&x, scale, context.max_level()).unwrap());
+        let fresh = estimator.fresh(rms, scale, context.max_level());
+        check("x", &ct, &fresh, &|i| x[i]);
+
+        let (mut square, mut square_estimate) = (
+            evaluator.multiply_relin(&ct, &ct, &relin_keys).unwrap(),
+            estimator.multiply(&fresh, &fresh).unwrap(),
+        );
+        rescale(&mut square, &mut square_estimate);
+        check("x^2", &square, &square_estimate, &|i| x[i] * x[i]);
+        let rotated = evaluator.rotate(&square, 1, &galois_keys).unwrap();
+        check("rot(x^2)", &rotated, &estimator.rotate(&square_estimate), &|i| x[(i + 1) % slots].powi(2));
+
+        let c = Complex64::new(0.3, 0.4);
+        let (mut scaled, mut scaled_estimate) = (
+            evaluator.multiply_const(&ct, c).unwrap(),
+            estimator.multiply_const(&fresh, c).unwrap(),
+        );
+        rescale(&mut scaled, &mut scaled_estimate);
+        check("c·x", &scaled, &scaled_estimate, &|i| x[i] * c);
+
+        let (mut fourth, mut fourth_estimate) = (
+            evaluator.multiply_relin(&square, &square, &relin_keys).unwrap(),
+            estimator.multiply(&square_estimate, &square_estimate).unwrap(),
+        );
+        rescale(&mut fourth, &mut fourth_estimate);
+        check("x^4", &fourth, &fourth_estimate, &|i| x[i].powi(4));
+    }
+
+    /// Predicted and measured mean precision of x - conj(x), x + y, x + c,
+    /// x·y for a plaintext y, and x at level 0
+    fn check_linear_and_plain_estimates(params: CKKSParameters, auto_rescale: bool) {
+        let scale = params.scale;
+        let context = CKKSContext::with_params(params).unwrap();
+        let keygen = KeyGenerator::new(&context);
+        let encryptor = Encryptor::new(&context, &keygen.public_key());
+        let analyzer = PrecisionAnalyzer::new(&context, &keygen.secret_key());
+        let (evaluator, estimator) = if auto_rescale {
+            (Evaluator::with_auto_rescale(&context), NoiseEstimator::with_auto_rescale(&context))
+        } else {
+            (Evaluator::new(&context), NoiseEstimator::new(&context))
+        };
+        let galois_keys = keygen.galois_keys(&[], true);
+
+        let slots = context.slot_count();
+        let top = context.max_level();
+        let x: Vec<Complex64> = (0..slots)
+            .map(|i| Complex64::new((i as f64 * 0.37).sin(), (i as f64 * 0.11).cos()))
+            .collect();
+        let y: Vec<Complex64> = (0..slots)
+            .map(|i| Complex64::new((i as f64 * 0.23).cos(), 0.5 * (i as f64 * 0.41).sin()))
+            .collect();
+        let rms = |v: &[Complex64]| (v.iter().map(|z| z.norm_sqr()).sum::<f64>() / slots as f64).sqrt();
+        let check = |name: &str, ct: &Ciphertext, estimate: &NoiseEstimate, expected: &dyn Fn(usize) -> Complex64| {
+            let expected: Vec<Complex64> = (0..slots).map(expected).collect();
+            let measured = analyzer.analyze(ct, &expected).unwrap().complex.mean_bits;
+            assert_eq!(ct.level(), estimate.level, "{}", name);
+            assert!(
+                (measured - estimate.precision_bits()).abs() < 1.5,
+                "{}: predicted {:.1} measured {:.1} bits", name, estimate.precision_bits(), measured
+            );
+        };
+
+        let ct = encryptor.encrypt(&context.encode(&x, scale, top).unwrap());
+        let fresh = estimator.fresh(rms(&x), scale, top);
+        let plain = context.encode(&y, scale, top).unwrap();
+        let plain_estimate = estimator.encoded(rms(&y), scale, top);
+
+        let conj = evaluator.conjugate(&ct, &galois_keys).unwrap();
+        let conj_estimate = estimator.conjugate(&fresh);
+        check("conj(x)", &conj, &conj_estimate, &|i| x[i].conj());
+        let diff = evaluator.sub(&ct, &conj).unwrap();
+        check("x - conj(x)", &diff, &estimator.sub(&fresh, &conj_estimate), &|i| x[i] - x[i].conj());
+
+        let sum = evaluator.add_plain(&ct, &plain).unwrap();
+        check("x + y", &sum, &estimator.add_plain(&fresh, &plain_estimate), &|i| x[i] + y[i]);
+        let c = Complex64::new(0.3, -0.4);
+        let shifted = evaluator.add_const(&ct, c).unwrap();
+        check("x + c", &shifted, &estimator.add_const(&fresh, c), &|i| x[i] + c);
+
+        let (mut product, mut product_estimate) = (
+            evaluator.mul_plain(&ct, &plain).unwrap(),
+            estimator.mul_plain(&fresh, &plain_estimate).unwrap(),
+        );
+        if !auto_rescale {
+            evaluator.rescale_to_next_inplace(&mut product).unwrap();
+            product_estimate = estimator.rescale(&product_estimate).unwrap();
+        }
+        check("x·y", &product, &product_estimate, &|i| x[i] * y[i]);
+
+        let bottom = evaluator.mod_switch_to(&ct, 0).unwrap();
+        check("x at level 0", &bottom, &estimator.mod_switch_to(&fresh, 0).unwrap(), &|i| x[i]);
+        assert!(estimator.mod_switch_to(&estimator.fresh(1.0, scale, 0), 1).is_err());
+    }
+
+    #[test]
+    fn linear_and_plain_estimates_track_measured_precision() {
+        for auto_rescale in [false, true] {
+            check_linear_and_plain_estimates(CKKSParameters::new(4096, &[60, 40, 40, 60], 2f64.powi(40)).unwrap(), auto_rescale);
+            check_linear_and_plain_estimates(CKKSParameters::new(4096, &[50, 30, 30, 60], 2f64.powi(30)).unwrap(), auto_rescale);
+        }
+    }
+
+    #[test]
+    fn estimates_track_measured_precision() {
+        for auto_rescale in [false, true] {
+            check_estimates(CKKSParameters::new(4096, &[60, 40, 40, 60], 2f64.powi(40)).unwrap(), None, auto_rescale);
+            check_estimates(CKKSParameters::new(4096, &[50, 30, 30, 60], 2f64.powi(30)).unwrap(), None, auto_rescale);
+            check_estimates(CKKSParameters::new(4096, &[60, 40, 40, 60], 2f64.powi(40)).unwrap(), Some(64), auto_rescale);
+        }
+    }
+
+    #[test]
+    fn estimates_with_composite_scaling() {
+        let params = CKKSParameters::new(4096, &[60, 60, 40, 40, 40, 40, 60], 2f64.powi(80))
+            .unwrap()
+            .with_primes_per_level(2);
+        check_estimates(params, None, true);
+    }
+
+    #[test]
+    fn estimator_rejects_exhausted_levels() {
+        let context = CKKSContext::with_params(CKKSParameters::new(4096, &[60, 40, 60], 2f64.powi(40)).unwrap()).unwrap();
+        let estimator = NoiseEstimator::new(&context);
+        let bottom = estimator.fresh(1.0, 2f64.powi(40), 0);
+        assert!(estimator.rescale(&bottom).is_err());
+        let auto = NoiseEstimator::with_auto_rescale(&context);
+        assert!(auto.multiply(&bottom, &bottom).is_err());
+    }
+}
@@ -0,0 +1,293 @@
+//! Integer and fixed-point encoders for BFV
+//! Single values packed as balanced base-B digits into plaintext coefficients
+//!
+//! An integer v = Σ d_i·B^i is stored as the polynomial Σ d_i·X^i, so
+//! homomorphic addition and multiplication act on the encoded values as
+//! long as no coefficient wraps around the plain modulus t.
+//!
+//! Coefficients are decoded as centered values in (-t/2, t/2]. Since a
+//! wrapped coefficient cannot be told apart from a genuine one in general,
+//! the decoders trust only magnitudes up to `overflow_threshold` (t/4 by
+//! default): a coefficient that exceeded t/2 by less than t/4 lands above
+//! the threshold and decoding fails instead of returning garbage.
+
+use super::bfv::{BFVContext, Plaintext};
+
+/// Balanced digits of a non-negative value, least significant first
+///
+/// Digits lie in (-B/2, B/2], so base 3 gives {-1, 0, 1} and base 2
+/// degenerates to ordinary binary.
+fn balanced_digits(mut value: u128, base: u64) -> Vec<i64> {
+    let b = base as u128;
+    let mut digits = Vec::new();
+    while value > 0 {
+        let r = (value % b) as i64;
+        let d = if r as u64 > base / 2 { r - base as i64 } else { r };
+        digits.push(d);
+        value = if d < 0 { value / b + 1 } else { value / b };
+    }
+    digits
+}
+
+fn to_coeff(digit: i64, plain_modulus: u64) -> u64 {
+    digit.rem_euclid(plain_modulus as i64) as u64
+}
+
+/// Centered coefficients, or an error if one of them looks wrapped
+fn centered_coeffs(plain: &Plaintext, plain_modulus: u64, threshold: u64) -> Result<Vec<i64>, String> {
+    plain.coeffs.iter().enumerate()
+        .map(|(i, &c)| {
+            let c = c % plain_modulus;
+            let magnitude = c.min(plain_modulus - c);
+            if magnitude > threshold {
+                return Err(format!(
+                    "coefficient {} has magnitude {} above {}: it has likely wrapped around t = {}",
+                    i, magnitude, threshold, plain_modulus
+                ));
+            }
+            Ok(if c > plain_modulus / 2 { c as i64 - plain_modulus as i64 } else { c as i64 })
+        })
+        .collect()
+}
+
+fn check_base(base: u64, plain_modulus: u64) -> Result<(), String> {
+    if base < 2 || base >= plain_modulus {
+        return Err(format!("base {} must satisfy 2 <= B < t = {}", base, plain_modulus));
+    }
+    Ok(())
+}
+
+// ==================== INTEGER ENCODER ====================
+
+pub struct IntegerEncoder {
+    base: u64,
+    plain_modulus: u64,
+    poly_degree: usize,
+    /// Largest centered coefficient magnitude accepted by `decode`
+    pub overflow_threshold: u64,
+}
+
+impl IntegerEncoder {
+    pub fn new(context: &BFVContext, base: u64) -> Result<Self, String> {
+        let plain_modulus = context.params.plain_modulus;
+        check_base(base, plain_modulus)?;
+        Ok(Self {
+            base,
+            plain_modulus,
+            poly_degree: context.params.poly_degree,
+            overflow_threshold: plain_modulus / 4,
+        })
+    }
+
+    pub fn base(&self) -> u64 {
+        self.base
+    }
+
+    pub fn encode(&self, value: i64) -> Result<Plaintext, String> {
+        let digits = balanced_digits(value.unsigned_abs() as u128, self.base);
+        if digits.len() > self.poly_degree {
+            return Err(format!("{} needs {} digits, more than poly_degree", value, digits.len()));
+        }
+
+        let sign = if value < 0 { -1 } else { 1 };
+        let mut plain = Plaintext::zero(self.poly_degree);
+        for (c, d) in plain.coeffs.iter_mut().zip(digits) {
+            *c = to_coeff(sign * d, self.plain_modulus);
+        }
+        Ok(plain)
+    }
+
+    /// Evaluates the plaintext at X = B; fails on wrapped coefficients or
+    /// if the value does not fit into an i64
+    pub fn decode(&self, plain: &Plaintext) -> Result<i64, String> {
+        let coeffs = centered_coeffs(plain, self.plain_modulus, self.overflow_threshold)?;
+        let overflow = || "decoded value does not fit into an i64".to_string();
+
+        let mut acc: i128 = 0;
+        for &c in coeffs.iter().rev() {
+            acc = acc.checked_mul(self.base as i128)
+                .and_then(|x| x.checked_add(c as i128))
+                .ok_or_else(overflow)?;
+        }
+        i64::try_from(acc).map_err(|_| overflow())
+    }
+}
+
+// ==================== FIXED-POINT ENCODER ====================
+
+/// Fixed-point encoder: integer digits in the low coefficients, fractional
+/// digits in the high ones
+///
+/// A fractional digit d at B^-k is stored as -d·X^(N-k), because
+/// X^-k = -X^(N-k) modulo X^N + 1; products of fractional parts therefore
+/// keep landing in the top coefficients. Decoding reads [0, N/2) as the
+/// integer part and [N/2, N) as the fractional part.
+pub struct FixedPointEncoder {
+    base: u64,
+    integer_coeff_count: usize,
+    fraction_coeff_count: usize,
+    plain_modulus: u64,
+    poly_degree: usize,
+    /// Largest centered coefficient magnitude accepted by `decode`
+    pub overflow_threshold: u64,
+}
+
+impl FixedPointEncoder {
+    /// `integer_coeff_count` limits the integer digits, `fraction_coeff_count`
+    /// the precision of the fractional part (both at most N/2)
+    pub fn new(
+        context: &BFVContext,
+        base: u64,
+        integer_coeff_count: usize,
+        fraction_coeff_count: usize,
+    ) -> Result<Self, String> {
+        let plain_modulus = context.params.plain_modulus;
+        let n = context.params.poly_degree;
+        check_base(base, plain_modulus)?;
+        if integer_coeff_count == 0 || integer_coeff_count > n / 2 {
+            return Err(format!("integer_coeff_count {} must be in 1..={}", integer_coeff_count, n / 2));
+        }
+        if fraction_coeff_count == 0 || fraction_coeff_count > n / 2 {
+            return Err(format!("fraction_coeff_count {} must be in 1..={}", fraction_coeff_count, n / 2));
+        }
+
+        Ok(Self {
+            base,
+            integer_coeff_count,
+            fraction_coeff_count,
+            plain_modulus,
+            poly_degree: n,
+            overflow_threshold: plain_modulus / 4,
+        })
+    }
+
+    pub fn encode(&self, value: f64) -> Result<Plaintext, String> {
+        if !value.is_finite() {
+            return Err(format!("cannot encode {}", value));
+        }
+        let b = self.base as f64;
+        let magnitude = value.abs();
+        let mut integer_part = magnitude.trunc();
+        if integer_part >= u64::MAX as f64 {
+            return Err(format!("integer part of {} is too large", value));
+        }
+
+        // Plain base-B fraction digits with rounding on the last one
+        let mut fraction = magnitude - integer_part;
+        let mut digits = vec![0i64; self.fraction_coeff_count + 1]; // digits[k] ~ B^-k
+        for d in digits.iter_mut().skip(1) {
+            fraction *= b;
+            *d = fraction.floor() as i64;
+            fraction -= fraction.floor();
+        }
+        if fraction >= 0.5 {
+            digits[self.fraction_coeff_count] += 1;
+        }
+        // Balance the digits into (-B/2, B/2], carrying into the integer part
+        for k in (1..digits.len()).rev() {
+            if digits[k] > self.base as i64 / 2 {
+                digits[k] -= self.base as i64;
+                digits[k - 1] += 1;
+            }
+        }
+        integer_part += digits[0] as f64;
+
+        let integer_digits = balanced_digits(integer_part as u128, self.base);
+        if integer_digits.len() > self.integer_coeff_count {
+            return Err(format!(
+                "integer part of {} needs {} digits, more than integer_coeff_count = {}",
+                value, integer_digits.len(), self.integer_coeff_count
+            ));
+        }
+
+        let sign = if value < 0.0 { -1 } else { 1 };
+        let n = self.poly_degree;
+        let mut plain = Plaintext::zero(n);
+        for (i, d) in integer_digits.into_iter().enumerate() {
+            plain.coeffs[i] = to_coeff(sign * d, self.plain_modulus);
+        }
+        for (k, &d) in digits.iter().enumerate().skip(1) {
+            plain.coeffs[n - k] = to_coeff(-sign * d, self.plain_modulus);
+        }
+        Ok(plain)
+    }
+
+    /// Evaluates integer and fractional halves at X = B; fails on wrapped
+    /// coefficients
+    pub fn decode(&self, plain: &Plaintext) -> Result<f64, String> {
+        let coeffs = centered_coeffs(plain, self.plain_modulus, self.overflow_threshold)?;
+        let n = self.poly_degree;
+        let b = self.base as f64;
+
+        let mut integer = 0.0;
+        for &c in coeffs[..n / 2].iter().rev() {
+            integer = integer * b + c as f64;
+        }
+        let mut fraction = 0.0;
+        for k in (1..=n / 2).rev() {
+            fraction = (fraction - coeffs[n - k] as f64) / b;
+        }
+
+        let value = integer + fraction;
+        if !value.is_finite() {
+            return Err("decoded value is out of the f64 range".to_string());
+        }
+        Ok(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fhe::{Decryptor, Encryptor, Evaluator, KeyGenerator};
+
+    #[test]
+    fn integer_round_trip_in_every_base() {
+        let context = BFVContext::new();
+        for base in [2u64, 3, 4, 10, 16] {
+            let encoder = IntegerEncoder::new(&context, base).unwrap();
+            for v in [0i64, 1, -1, 7, 5000, -123456789, i64::MAX, i64::MIN + 1] {
+                assert_eq!(encoder.decode(&encoder.encode(v).unwrap()).unwrap(), v, "base {} value {}", base, v);
+            }
+        }
+        assert!(IntegerEncoder::new(&context, 1).is_err());
+    }
+
+    #[test]
+    fn fixed_point_round_trip() {
+        let encoder = FixedPointEncoder::new(&BFVContext::new(), 3, 64, 64).unwrap();
+        for v in [3.25f64, -1.5, 0.1, -1234.5678, 0.999999, 0.0] {
+            let decoded = encoder.decode(&encoder.encode(v).unwrap()).unwrap();
+            assert!((decoded - v).abs() < 1e-12, "{} decoded as {}", v, decoded);
+        }
+    }
+
+    #[test]
+    fn homomorphic_arithmetic_and_overflow_detection() {
+        let context = BFVContext::new();
+        let keygen = KeyGenerator::new(&context);
+        let encryptor = Encryptor::new(&context, &keygen.public_key());
+        let decryptor = Decryptor::new(&context, &keygen.secret_key());
+        let evaluator = Evaluator::new(&context);
+
+        let integers = IntegerEncoder::new(&context, 3).unwrap();
+        let ct = encryptor.encrypt(&integers.encode(-1234).unwrap());
+        let result = evaluator.mul_plain(
+            &evaluator.add_plain(&ct, &integers.encode(34).unwrap()),
+            &integers.encode(-567).unwrap(),
+        );
+        assert_eq!(integers.decode(&decryptor.decrypt(&result)).unwrap(), (-1234 + 34) * -567);
+        // 40000 > t/2 wraps every digit of the product
+        let wrapped = evaluator.multiply_by_scalar(&encryptor.encrypt(&integers.encode(1).unwrap()), 40000);
+        assert!(integers.decode(&decryptor.decrypt(&wrapped)).is_err());
+
+        let fixed = FixedPointEncoder::new(&context, 3, 64, 64).unwrap();
+        let ct = encryptor.encrypt(&fixed.encode(3.25).unwrap());
+        let result = evaluator.mul_plain(
+            &evaluator.add_plain(&ct, &fixed.encode(-0.125).unwrap()),
+            &fixed.encode(-1.5).unwrap(),
+        );
+        let decoded = fixed.decode(&decryptor.decrypt(&result)).unwrap();
+        assert!((decoded - 3.125 * -1.5).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,221 @@
+//! Key switching shared by the RLWE schemes
+//! RNS gadget decomposition with one special prime P (hybrid key switching)
+//!
+//! A key switching key from s' to s holds, for every chain prime q_i,
+//! (b_i, a_i) = (-a_i·s + e_i + P·g_i·s', a_i) modulo Q_L·P, where
+//! g_i ≡ 1 (mod q_i) and g_i ≡ 0 (mod q_j), j ≠ i. Switching a polynomial
+//! d at level l sums [d]_{q_i}·(b_i, a_i) and divides by P, which scales
+//! the key noise down by P.
+//!
+//! For BGV the basis carries the plain modulus t: key noise becomes t·e_i
+//! and the division by P subtracts a remainder δ ≡ 0 (mod t), so results
+//! stay correct modulo t without a correction factor.
+
+use crate::modular;
+use crate::ntt::NttTables;
+use super::poly::{self, RnsPoly};
+
+/// Chain moduli q_0..q_L followed by the special prime P
+#[derive(Clone)]
+pub struct KeySwitchBasis {
+    pub tables: Vec<NttTables>,
+    /// Some(t) for BGV
+    pub plain_modulus: Option<u64>,
+    p_mod_q: Vec<u64>,     // P mod q_j
+    p_inv_mod_q: Vec<u64>, // P^-1 mod q_j
+}
+
+/// Key switching key in NTT form, one (b_i, a_i) per chain prime
+#[derive(Clone)]
+pub struct KeySwitchKey {
+    pub b: Vec<RnsPoly>,
+    pub a: Vec<RnsPoly>,
+}
+
+impl KeySwitchBasis {
+    pub fn new(chain: &[NttTables], special: &NttTables, plain_modulus: Option<u64>) -> Self {
+        let p = special.modulus;
+        let mut tables = chain.to_vec();
+        tables.push(special.clone());
+        KeySwitchBasis {
+            tables,
+            plain_modulus,
+            p_mod_q: chain.iter().map(|t| p % t.modulus).collect(),
+            p_inv_mod_q: chain.iter().map(|t| modular::mod_inv(p % t.modulus, t.modulus)).collect(),
+        }
+    }
+
+    /// Number of chain primes L + 1
+    pub fn chain_len(&self) -> usize {
+        self.tables.len() - 1
+    }
+
+    pub fn special(&self) -> &NttTables {
+        &self.tables[self.chain_len()]
+    }
+
+    /// Tables of q_0..q_level followed by P
+    pub fn level_tables(&self, level: usize) -> Vec<&NttTables> {
+        let mut tables: Vec<&NttTables> = self.tables[..=level].iter().collect();
+        tables.push(self.special());
+        tables
+    }
+
+    /// Generates a key switching from `s_from` to `s` (both coefficient form
+    /// over q_0..q_L, P)
+    pub fn generate_key(&self, s: &RnsPoly, s_from: &RnsPoly) -> KeySwitchKey {
+        let n = s.limbs[0].len();
+        let all = &self.tables;
+
+        let mut s_ntt = s.clone();
+        let mut s_from_ntt = s_from.clone();
+        for ((x, y), t) in s_ntt.limbs.iter_mut().zip(s_from_ntt.limbs.iter_mut()).zip(all) {
+            t.forward(x);
+            t.forward(y);
+        }
+
+        let mut b = Vec::with_capacity(self.chain_len());
+        let mut a = Vec::with_capacity(self.chain_len());
+        for i in 0..self.chain_len() {
+            let a_i = RnsPoly::uniform(n, all);
+            let mut e_i = RnsPoly::from_signed(&poly::sample_gaussian(n, poly::ERROR_STD_DEV), all);
+            if let Some(t) = self.plain_modulus {
+                e_i = e_i.mul_scalars(&vec![t; all.len()], all);
+            }
+
+            let mut b_i = RnsPoly::zero(n, all.len());
+            for (j, t) in all.iter().enumerate() {
+                let q = t.modulus;
+                let mut e = e_i.limbs[j].clone();
+                t.forward(&mut e);
+                for (((b, &e), &a), &s) in b_i.limbs[j].iter_mut().zip(&e).zip(&a_i.limbs[j]).zip(&s_ntt.limbs[j]) {
+                    *b = modular::mod_sub(e, modular::mod_mul(a, s, q), q);
+                }
+                if j == i {
+                    let p = self.p_mod_q[i];
+                    for k in 0..n {
+                        let gadget = modular::mod_mul(p, s_from_ntt.limbs[j][k], q);
+                        b_i.limbs[j][k] = modular::mod_add(b_i.limbs[j][k], gadget, q);
+                    }
+                }
+            }
+
+            // a_i is uniform, so its coefficient form can be reused as NTT form
+            b.push(b_i);
+            a.push(a_i);
+        }
+        KeySwitchKey { b, a }
+    }
+
+    /// RNS digits [d]_{q_i} of a level-l polynomial, lifted to q_0..q_l, P
+    /// and transformed to NTT form
+    pub fn decompose(&self, d: &RnsPoly) -> Vec<RnsPoly> {
+        let level = d.level();
+        let tables = self.level_tables(level);
+
+        d.limbs.iter().enumerate()
+            .map(|(i, digit)| {
+                let q_i = self.tables[i].modulus;
+                let limbs = tables.iter()
+                    .map(|t| {
+                        let q = t.modulus;
+                        let mut limb: Vec<u64> = digit.iter()
+                            .map(|&x| {
+                                // centered lift keeps the digits small
+                                if x > q_i / 2 {
+                                    modular::mod_sub(0, (q_i - x) % q, q)
+                                } else {
+                                    x % q
+                                }
+                            })
+                            .collect();
+                        t.forward(&mut limb);
+                        limb
+                    })
+                    .collect();
+                RnsPoly { limbs }
+            })
+            .collect()
+    }
+
+    /// Inner product of decomposed digits with the key, divided by P
+    ///
+    /// Returns (c0, c1) at the digits' level in coefficient form with
+    /// c0 + c1·s ≈ d·s'.
+    pub fn apply(&self, digits: &[RnsPoly], key: &KeySwitchKey) -> (RnsPoly, RnsPoly) {
+        let tables = self.level_tables(digits.len() - 1);
+        let (acc0, acc1) = self.inner_product(digits, key, &tables);
+        (self.mod_down(acc0, &tables), self.mod_down(acc1, &tables))
+    }
+
+    /// Switches a level-l polynomial d: returns (c0, c1) with c0 + c1·s ≈ d·s'
+    pub fn switch(&self, d: &RnsPoly, key: &KeySwitchKey) -> (RnsPoly, RnsPoly) {
+        self.apply(&self.decompose(d), key)
+    }
+
+    /// Σ digit_i·(b_i, a_i) in NTT form over q_0..q_l, P
+    fn inner_product(&self, digits: &[RnsPoly], key: &KeySwitchKey, tables: &[&NttTables]) -> (RnsPoly, RnsPoly) {
+        let level = digits.len() - 1;
+        let special = self.chain_len();
+        let n = digits[0].limbs[0].len();
+
+        let mut acc0 = RnsPoly::zero(n, level + 2);
+        let mut acc1 = RnsPoly::zero(n, level + 2);
+        for (i, digit) in digits.iter().enumerate() {
+            for (j, t) in tables.iter().enumerate() {
+                let key_j = if j <= level { j } else { special };
+                let q = t.modulus;
+                let (b, a) = (&key.b[i].limbs[key_j], &key.a[i].limbs[key_j]);
+                for k in 0..n {
+                    let x = digit.limbs[j][k];
+                    acc0.limbs[j][k] = modular::mod_add(acc0.limbs[j][k], modular::mod_mul(x, b[k], q), q);
+                    acc1.limbs[j][k] = modular::mod_add(acc1.limbs[j][k], modular::mod_mul(x, a[k], q), q);
+                }
+            }
+        }
+        (acc0, acc1)
+    }
+
+    /// Inverse NTT and rounded division by P: (x - δ)/P mod q_j with
+    /// δ ≡ x (mod P), and δ ≡ 0 (mod t) for BGV
+    fn mod_down(&self, mut x: RnsPoly, tables: &[&NttTables]) -> RnsPoly {
+        for (limb, t) in x.limbs.iter_mut().zip(tables) {
+            t.inverse(limb);
+        }
+        let p_limb = x.limbs.pop().unwrap();
+        let delta = remainders(&p_limb, self.special().modulus, self.plain_modulus);
+
+        for (j, limb) in x.limbs.iter_mut().enumerate() {
+            let q = tables[j].modulus;
+            let p_inv = self.p_inv_mod_q[j];
+            for (v, &d) in limb.iter_mut().zip(&delta) {
+                let d = d.rem_euclid(q as i128) as u64;
+                *v = modular::mod_mul(modular::mod_sub(*v, d, q), p_inv, q);
+            }
+        }
+        x
+    }
+}
+
+/// Small remainders δ with δ ≡ r (mod p) for residues r of the dropped
+/// modulus p; with a plain modulus t also δ ≡ 0 (mod t)
+pub fn remainders(residues: &[u64], p: u64, plain_modulus: Option<u64>) -> Vec<i128> {
+    residues.iter()
+        .map(|&r| {
+            let r = if r > p / 2 { r as i128 - p as i128 } else { r as i128 };
+            match plain_modulus {
+                None => r,
+                Some(t) => {
+                    // δ = r + p·k with k ≡ -r·p^-1 (mod t), centered
+                    let t = t as i128;
+                    let p_inv = modular::mod_inv(p % t as u64, t as u64) as i128;
+                    let mut k = (-r).rem_euclid(t) * p_inv % t;
+                    if k > t / 2 {
+                        k -= t;
+                    }
+                    r + p as i128 * k
+                }
+            }
+        })
+        .collect()
+}
@@ -0,0 +1,364 @@
+//! Homomorphic linear transforms
+//! Plaintext matrix times encrypted vector by the diagonal method
+//!
+//! A d×d matrix acts on slot vectors of period d, so d must divide the
+//! slot row (N/2 for both CKKS and BFV batching) and the vector is
+//! replicated across it. Following Halevi–Shoup, A·v = Σ_k diag_k ⊙
+//! rot_k(v) with diag_k[j] = A[j][(j + k) mod d], and only the nonzero
+//! diagonals are encoded. Writing k = g + j for a baby step j < b and a
+//! giant step g, diag_k is stored pre-rotated by -g, so that
+//! A·v = Σ_g rot_g(Σ_j rot_-g(diag_k) ⊙ rot_j(v)) needs one rotation per
+//! distinct j and per distinct g; b is the power of two minimizing that.
+
+use super::bfv::{self, BFVContext, GaloisKeys, PlaintextNtt};
+use super::batch_encoder::BatchEncoder;
+use super::ckks::{self, CKKSContext};
+use num_complex::Complex64;
+use std::collections::BTreeMap;
+
+/// Pre-encoded generalized diagonals of a d×d plaintext matrix
+#[derive(Clone, Debug)]
+pub struct LinearTransform<P> {
+    pub dimension: usize,
+    pub baby_step: usize,
+    /// giant step g -> (baby step j, rot_-g(diag_(g+j)))
+    diagonals: BTreeMap<usize, Vec<(usize, P)>>,
+}
+
+/// Nonzero diagonals (k, diag_k) of a matrix
+type Diagonals<T> = Vec<(usize, Vec<T>)>;
+
+/// Nonzero diagonals of a d×d matrix whose vectors are replicated across
+/// `row_size` slots
+fn split_diagonals<T: Copy, Z: Fn(&T) -> bool>(
+    matrix: &[Vec<T>],
+    row_size: usize,
+    is_zero: Z,
+) -> Result<Diagonals<T>, String> {
+    let d = matrix.len();
+    check_dimension(d, row_size)?;
+    if let Some(bad) = matrix.iter().position(|row| row.len() != d) {
+        return Err(format!("row {} has {} entries, expected {}", bad, matrix[bad].len(), d));
+    }
+
+    Ok((0..d)
+        .map(|k| (k, (0..d).map(|j| matrix[j][(j + k) % d]).collect::<Vec<T>>()))
+        .filter(|(_, diag)| !diag.iter().all(&is_zero))
+        .collect())
+}
+
+fn check_dimension(d: usize, row_size: usize) -> Result<(), String> {
+    if d == 0 || !row_size.is_multiple_of(d) {
+        return Err(format!("matrix dimension {} must divide the {} slots of a row", d, row_size));
+    }
+    Ok(())
+}
+
+/// Power-of-two baby step b minimizing the distinct baby plus giant
+/// rotations for the diagonals of a d×d matrix
+fn baby_step<T>(diagonals: &Diagonals<T>, d: usize) -> usize {
+    let rotations = |b: usize| {
+        let mut babies: Vec<usize> = diagonals.iter().map(|(k, _)| k % b).collect();
+        babies.sort_unstable();
+        babies.dedup();
+        let mut giants: Vec<usize> = diagonals.iter().map(|(k, _)| k - k % b).collect();
+        giants.dedup();
+        babies.len() + giants.len()
+    };
+    (0..=d.trailing_zeros())
+        .map(|e| 1usize << e)
+        .min_by_key(|&b| rotations(b))
+        .unwrap_or(1)
+}
+
+/// rot_-g(diag) replicated across `row_size` slots
+fn pre_rotated<T: Copy>(diag: &[T], g: usize, row_size: usize) -> Vec<T> {
+    let d = diag.len();
+    (0..row_size).map(|i| diag[(i + d - g % d) % d]).collect()
+}
+
+impl<P> LinearTransform<P> {
+    /// Number of encoded (nonzero) diagonals
+    pub fn diagonal_count(&self) -> usize {
+        self.diagonals.values().map(Vec::len).sum()
+    }
+
+    /// Left rotations the evaluation performs; generate Galois keys for
+    /// exactly these
+    pub fn rotation_steps(&self) -> Vec<i64> {
+        let mut steps: Vec<i64> = self.diagonals.values()
+            .flat_map(|terms| terms.iter().map(|&(j, _)| j as i64))
+            .chain(self.diagonals.keys().map(|&g| g as i64))
+            .filter(|&s| s != 0)
+            .collect();
+        steps.sort_unstable();
+        steps.dedup();
+        steps
+    }
+
+    fn baby_steps(&self) -> Vec<i64> {
+        let mut babies: Vec<i64> = self.diagonals.values()
+            .flat_map(|terms| terms.iter().map(|&(j, _)| j as i64))
+            .collect();
+        babies.sort_unstable();
+        babies.dedup();
+        babies
+    }
+}
+
+// ==================== CKKS ====================
+
+impl LinearTransform<ckks::Plaintext> {
+    /// Encodes the diagonals at `level` with scale q_level, so that the
+    /// rescale after the products restores the input scale
+    pub fn new_ckks(context: &CKKSContext, matrix: &[Vec<Complex64>], level: usize) -> Result<Self, String> {
+        let split = split_diagonals(matrix, context.slot_count(), |z| z.norm() == 0.0)?;
+        Self::from_diagonals_ckks(context, matrix.len(), split, level)
+    }
+
+    /// Like `new_ckks` for a d×d matrix given by its diagonals (k, diag_k)
+    /// in increasing k, so structured matrices need not be built densely
+    pub fn from_diagonals_ckks(
+        context: &CKKSContext,
+        dimension: usize,
+        diagonals: Vec<(usize, Vec<Complex64>)>,
+        level: usize,
+    ) -> Result<Self, String> {
+        if level > context.max_level() {
+            return Err(format!("level {} exceeds the maximum level {}", level, context.max_level()));
+        }
+        let slots = context.slot_count();
+        check_dimension(dimension, slots)?;
+        for (k, diag) in &diagonals {
+            if *k >= dimension || diag.len() != dimension {
+                return Err(format!(
+                    "diagonal {} has {} entries; expected {} entries and an offset below {}",
+                    k, diag.len(), dimension, dimension
+                ));
+            }
+        }
+        let split: Diagonals<Complex64> = diagonals.into_iter()
+            .filter(|(_, diag)| diag.iter().any(|z| z.norm() != 0.0))
+            .collect();
+        let baby = baby_step(&split, dimension);
+        let scale = context.level_modulus(level);
+
+        let mut encoded: BTreeMap<usize, Vec<(usize, ckks::Plaintext)>> = BTreeMap::new();
+        for (k, diag) in split {
+            let (g, j) = (k - k % baby, k % baby);
+            let plain = context.encode(&pre_rotated(&diag, g, slots), scale, level)?;
+            encoded.entry(g).or_default().push((j, plain));
+        }
+        Ok(Self { dimension, baby_step: baby, diagonals: encoded })
+    }
+
+    /// Level the diagonals are encoded at; inputs are switched down to it
+    pub fn level(&self) -> usize {
+        self.diagonals.values().next().map_or(0, |terms| terms[0].1.level())
+    }
+
+    pub fn galois_keys(&self, keygen: &ckks::KeyGenerator) -> GaloisKeys {
+        keygen.galois_keys(&self.rotation_steps(), false)
+    }
+
+    /// A·v at the input scale, one level below `level()`; baby-step
+    /// rotations share one hoisted decomposition
+    pub fn evaluate(&self, evaluator: &ckks::Evaluator, ct: &ckks::Ciphertext, galois_keys: &GaloisKeys) -> Result<ckks::Ciphertext, String> {
+        if ct.level() < self.level() {
+            return Err(format!(
+                "ciphertext at level {} is below the transform's level {}",
+                ct.level(), self.level()
+            ));
+        }
+        let ct = evaluator.mod_switch_to(ct, self.level())?;
+        let babies = self.baby_steps();
+        let rotated: BTreeMap<usize, ckks::Ciphertext> = babies.iter()
+            .map(|&j| j as usize)
+            .zip(evaluator.rotate_hoisted(&ct, &babies, galois_keys)?)
+            .collect();
+
+        let mut acc: Option<ckks::Ciphertext> = None;
+        for (&g, terms) in &self.diagonals {
+            let mut inner: Option<ckks::Ciphertext> = None;
+            for (j, plain) in terms {
+                let term = evaluator.mul_plain(&rotated[j], plain)?;
+                match inner.as_mut() {
+                    None => inner = Some(term),
+                    Some(inner) => evaluator.add_inplace(inner, &term)?,
+                }
+            }
+            let inner = evaluator.rotate(&inner.unwrap(), g as i64, galois_keys)?;
+            match acc.as_mut() {
+                None => acc = Some(inner),
+                Some(acc) => evaluator.add_inplace(acc, &inner)?,
+            }
+        }
+        let mut result = acc.ok_or("the matrix has no nonzero diagonal")?;
+        if !evaluator.auto_rescale {
+            evaluator.rescale_to_next_inplace(&mut result)?;
+        }
+        result.scale = ct.scale;
+        Ok(result)
+    }
+}
+
+// ==================== BFV ====================
+
+impl LinearTransform<PlaintextNtt> {
+    /// Batch-encodes the diagonals (entries modulo t) into both rows and
+    /// caches them in NTT form
+    pub fn new_bfv(context: &BFVContext, matrix: &[Vec<u64>]) -> Result<Self, String> {
+        let t = context.params.plain_modulus;
+        let encoder = BatchEncoder::new(context)?;
+        let evaluator = bfv::Evaluator::new(context);
+        let row_size = encoder.row_size();
+        let split = split_diagonals(matrix, row_size, |&x| x % t == 0)?;
+        let baby = baby_step(&split, matrix.len());
+
+        let mut diagonals: BTreeMap<usize, Vec<(usize, PlaintextNtt)>> = BTreeMap::new();
+        for (k, diag) in split {
+            let (g, j) = (k - k % baby, k % baby);
+            let row: Vec<u64> = pre_rotated(&diag, g, row_size).into_iter().map(|x| x % t).collect();
+            let plain = encoder.encode(&[row.clone(), row].concat())?;
+            diagonals.entry(g).or_default().push((j, evaluator.transform_plain_to_ntt(&plain)));
+        }
+        Ok(Self { dimension: matrix.len(), baby_step: baby, diagonals })
+    }
+
+    pub fn galois_keys(&self, keygen: &bfv::KeyGenerator) -> GaloisKeys {
+        keygen.galois_keys(&self.rotation_steps())
+    }
+
+    /// A·v modulo t in each batching row; consumes no level
+    pub fn evaluate(&self, evaluator: &bfv::Evaluator, ct: &bfv::Ciphertext, galois_keys: &GaloisKeys) -> Result<bfv::Ciphertext, String> {
+        let rotated = self.baby_steps().into_iter()
+            .map(|j| Ok((j as usize, evaluator.rotate_rows(ct, j, galois_keys)?)))
+            .collect::<Result<BTreeMap<usize, bfv::Ciphertext>, String>>()?;
+
+        let mut acc: Option<bfv::Ciphertext> = None;
+        for (&g, terms) in &self.diagonals {
+            let mut inner: Option<bfv::Ciphertext> = None;
+            for (j, plain) in terms {
+                let term = evaluator.mul_plain_ntt(&rotated[j], plain);
+                match inner.as_mut() {
+                    None => inner = Some(term),
+                    Some(inner) => evaluator.add_inplace(inner, &term),
+                }
+            }
+            let inner = evaluator.rotate_rows(&inner.unwrap(), g as i64, galois_keys)?;
+            match acc.as_mut() {
+                None => acc = Some(inner),
+                Some(acc) => evaluator.add_inplace(acc, &inner),
+            }
+        }
+        acc.ok_or_else(|| "the matrix has no nonzero diagonal".to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::Rng;
+
+    fn random_complex(rng: &mut impl Rng) -> Complex64 {
+        Complex64::new(rng.gen_range(-1.0..1.0), rng.gen_range(-1.0..1.0))
+    }
+
+    /// d×d matrix whose nonzero diagonals are 0..band
+    fn banded(d: usize, band: usize, rng: &mut impl Rng) -> Vec<Vec<Complex64>> {
+        (0..d)
+            .map(|i| (0..d).map(|j| if (j + d - i) % d < band { random_complex(rng) } else { Complex64::new(0.0, 0.0) }).collect())
+            .collect()
+    }
+
+    #[test]
+    fn split_and_baby_step() {
+        let mut rng = rand::thread_rng();
+        let context = CKKSContext::with_params(ckks::CKKSParameters::new(2048, &[60, 40, 60], 2f64.powi(40)).unwrap()).unwrap();
+        let dense = LinearTransform::new_ckks(&context, &banded(64, 64, &mut rng), 1).unwrap();
+        assert_eq!((dense.diagonal_count(), dense.baby_step), (64, 8));
+        let band = LinearTransform::new_ckks(&context, &banded(64, 3, &mut rng), 1).unwrap();
+        assert_eq!(band.diagonal_count(), 3);
+        assert_eq!(band.rotation_steps(), vec![1, 2]);
+
+        assert!(LinearTransform::new_ckks(&context, &banded(48, 48, &mut rng), 1).is_err());
+        assert!(LinearTransform::new_ckks(&context, &banded(16, 16, &mut rng), 2).is_err());
+        let ragged: Vec<Vec<Complex64>> = (0..4).map(|i| vec![Complex64::new(1.0, 0.0); 4 - i / 3]).collect();
+        assert!(LinearTransform::new_ckks(&context, &ragged, 1).is_err());
+        assert!(LinearTransform::from_diagonals_ckks(&context, 4, vec![(4, vec![Complex64::new(1.0, 0.0); 4])], 1).is_err());
+    }
+
+    #[test]
+    fn ckks_matrix_vector_product() {
+        let mut rng = rand::thread_rng();
+        let params = ckks::CKKSParameters::new(2048, &[60, 40, 40, 60], 2f64.powi(40)).unwrap();
+        let context = CKKSContext::with_params(params).unwrap();
+        let keygen = ckks::KeyGenerator::new(&context);
+        let encryptor = ckks::Encryptor::new(&context, &keygen.public_key());
+        let decryptor = ckks::Decryptor::new(&context, &keygen.secret_key());
+
+        for (d, band) in [(16usize, 16usize), (128, 3), (1024, 1024)] {
+            let matrix = banded(d, band, &mut rng);
+            let transform = LinearTransform::new_ckks(&context, &matrix, 2).unwrap();
+            let galois_keys = transform.galois_keys(&keygen);
+            let v: Vec<Complex64> = (0..d).map(|_| random_complex(&mut rng)).collect();
+            let tiled: Vec<Complex64> = (0..1024).map(|i| v[i % d]).collect();
+            let ct = encryptor.encrypt(&context.encode(&tiled, 2f64.powi(40), 2).unwrap());
+
+            for evaluator in [ckks::Evaluator::new(&context), ckks::Evaluator::with_auto_rescale(&context)] {
+                let result = transform.evaluate(&evaluator, &ct, &galois_keys).unwrap();
+                assert_eq!((result.level(), result.scale), (1, ct.scale));
+                let error = decryptor.decrypt_complex(&result).iter().enumerate()
+                    .map(|(i, z)| (z - (0..d).map(|j| matrix[i % d][j] * v[j]).sum::<Complex64>()).norm())
+                    .fold(0.0, f64::max);
+                assert!(error < 1e-5, "d {} band {}: {:e}", d, band, error);
+            }
+        }
+    }
+
+    #[test]
+    fn diagonals_match_the_dense_construction() {
+        let mut rng = rand::thread_rng();
+        let params = ckks::CKKSParameters::new(2048, &[60, 40, 60], 2f64.powi(40)).unwrap();
+        let context = CKKSContext::with_params(params).unwrap();
+        let matrix = banded(32, 5, &mut rng);
+        let dense = LinearTransform::new_ckks(&context, &matrix, 1).unwrap();
+        let diagonals = (0..32).map(|k| (k, (0..32).map(|j| matrix[j][(j + k) % 32]).collect())).collect();
+        let direct = LinearTransform::from_diagonals_ckks(&context, 32, diagonals, 1).unwrap();
+
+        assert_eq!(direct.baby_step, dense.baby_step);
+        assert_eq!(direct.rotation_steps(), dense.rotation_steps());
+        for (a, b) in direct.diagonals.values().flatten().zip(dense.diagonals.values().flatten()) {
+            assert_eq!((a.0, &a.1.poly), (b.0, &b.1.poly));
+        }
+    }
+
+    #[test]
+    fn bfv_matrix_vector_product() {
+        let mut rng = rand::thread_rng();
+        let context = BFVContext::new();
+        let t = context.params.plain_modulus;
+        let keygen = bfv::KeyGenerator::new(&context);
+        let encryptor = bfv::Encryptor::new(&context, &keygen.public_key());
+        let decryptor = bfv::Decryptor::new(&context, &keygen.secret_key());
+        let evaluator = bfv::Evaluator::new(&context);
+        let encoder = BatchEncoder::new(&context).unwrap();
+        let row = encoder.row_size();
+
+        for d in [8usize, 256] {
+            let matrix: Vec<Vec<u64>> = (0..d).map(|_| (0..d).map(|_| rng.gen_range(0..t)).collect()).collect();
+            let transform = LinearTransform::new_bfv(&context, &matrix).unwrap();
+            let galois_keys = transform.galois_keys(&keygen);
+            // each batching row carries its own vector
+            let vectors: [Vec<u64>; 2] = [0, 1].map(|_| (0..d).map(|_| rng.gen_range(0..t)).collect());
+            let slots: Vec<u64> = (0..2 * row).map(|i| vectors[i / row][i % d]).collect();
+            let ct = encryptor.encrypt(&encoder.encode(&slots).unwrap());
+
+            let result = encoder.decode(&decryptor.decrypt(&transform.evaluate(&evaluator, &ct, &galois_keys).unwrap()));
+            for (i, &x) in result.iter().enumerate() {
+                let expected = (0..d).fold(0u128, |acc, j| (acc + matrix[i % d][j] as u128 * vectors[i / row][j] as u128) % t as u128);
+                assert_eq!(x as u128, expected, "d {} slot {}", d, i);
+            }
+        }
+    }
+}
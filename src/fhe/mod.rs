@@ -1,8 +1,26 @@
 //! Fully Homomorphic Encryption operations
-//! BFV and CKKS scheme implementations
+//! BFV, BGV, CKKS and TFHE scheme implementations
 
 pub mod bfv;
+pub mod bgv;
+pub mod batch_encoder;
+pub mod bfv_polynomial;
 pub mod ckks;
+pub mod ckks_bootstrap;
+pub mod ckks_comparison;
+pub mod ckks_encoder;
+pub mod ckks_flooding;
+pub mod ckks_matrix;
+pub mod ckks_polynomial;
+pub mod ckks_precision;
+pub mod integer_encoder;
+pub mod keyswitch;
+pub mod linear_transform;
+pub mod poly;
+pub mod sanitize;
+pub mod tfhe;
+pub mod tfhe_boolean;
+pub mod tfhe_integer;
 
 /// Common FHE parameters
 pub struct FHEParameters {
@@ -24,5 +42,23 @@ impl Default for FHEParameters {
 }  // ← DIESE Klammer war vergessen!
 
 /// Re-export common types for easier access
-pub use bfv::{BFVParameters, BFVContext};
-// pub use ckks::{CKKSParameters, CKKSContext};  // AUSKOMMENTIERT, weil ckks.rs sie nicht hat!
+pub use bfv::{
+    SchemeType, BFVParameters, BFVContext, Plaintext, PlaintextNtt, Ciphertext,
+    SecretKey, PublicKey, RelinKeys, GaloisKeys, KeyGenerator, Encryptor, Decryptor, Evaluator,
+};
+pub use bfv_polynomial::{exponentiate_depth, polynomial_depth};
+pub use batch_encoder::BatchEncoder;
+pub use ckks_encoder::CKKSEncoder;
+pub use integer_encoder::{IntegerEncoder, FixedPointEncoder};
+pub use ckks::{CKKSParameters, CKKSContext};
+pub use ckks_polynomial::ChebyshevApproximation;
+pub use ckks_flooding::{FloodedDecryption, FloodingParameters};
+pub use ckks_precision::{NoiseEstimate, NoiseEstimator, PrecisionAnalyzer, PrecisionReport};
+pub use ckks_comparison::CompositeSign;
+pub use ckks_bootstrap::{BootstrapParameters, Bootstrapper};
+pub use linear_transform::LinearTransform;
+pub use ckks_matrix::{EncryptedMatrix, MatrixMultiplier};
+pub use sanitize::SanitizeParameters;
+pub use tfhe::{TFHEContext, TFHEParameters, Torus};
+pub use tfhe_boolean::BooleanEvaluator;
+pub use tfhe_integer::{CarryPropagation, EncryptedBool, EncryptedU16, EncryptedU32, EncryptedU8, EncryptedUint, IntegerEvaluator};
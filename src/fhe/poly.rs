@@ -0,0 +1,315 @@
+//! Polynomial helpers shared by the FHE schemes
+//! Sampling and arithmetic in Z_q[X]/(X^N + 1)
+
+use crate::modular;
+use crate::ntt::NttTables;
+use rand::Rng;
+
+/// Standard deviation of the RLWE error distribution
+pub const ERROR_STD_DEV: f64 = 3.2;
+
+// ==================== SAMPLING ====================
+
+/// Uniform ternary polynomial with coefficients in {-1, 0, 1}
+pub fn sample_ternary(n: usize) -> Vec<i64> {
+    let mut rng = rand::thread_rng();
+    (0..n).map(|_| rng.gen_range(-1i64..=1)).collect()
+}
+
+/// Ternary polynomial with exactly `hamming_weight` nonzero coefficients
+pub fn sample_sparse_ternary(n: usize, hamming_weight: usize) -> Vec<i64> {
+    let mut rng = rand::thread_rng();
+    let mut poly = vec![0i64; n];
+    for idx in rand::seq::index::sample(&mut rng, n, hamming_weight.min(n)) {
+        poly[idx] = if rng.gen() { 1 } else { -1 };
+    }
+    poly
+}
+
+/// Rounded Gaussian polynomial, truncated at 6 standard deviations
+pub fn sample_gaussian(n: usize, std_dev: f64) -> Vec<i64> {
+    let mut rng = rand::thread_rng();
+    let bound = (6.0 * std_dev).ceil();
+    (0..n)
+        .map(|_| loop {
+            // Box-Muller transform
+            let u1: f64 = rng.gen_range(f64::EPSILON..1.0);
+            let u2: f64 = rng.gen();
+            let z = (-2.0 * u1.ln()).sqrt() * (2.0 * std::f64::consts::PI * u2).cos() * std_dev;
+            if z.abs() <= bound {
+                break z.round() as i64;
+            }
+        })
+        .collect()
+}
+
+/// Discrete Gaussian polynomial with parameter `std_dev`, cut at
+/// `tail`·std_dev, by rejection from the uniform distribution on the
+/// integers of that range; suited to large flooding deviations, which
+/// must stay below 2^58
+pub fn sample_discrete_gaussian(n: usize, std_dev: f64, tail: f64) -> Vec<i64> {
+    let mut rng = rand::thread_rng();
+    let bound = (tail * std_dev).ceil() as i64;
+    let two_var = 2.0 * std_dev * std_dev;
+    (0..n)
+        .map(|_| loop {
+            let x = rng.gen_range(-bound..=bound);
+            let xf = x as f64;
+            if rng.gen::<f64>() < (-xf * xf / two_var).exp() {
+                break x;
+            }
+        })
+        .collect()
+}
+
+/// Uniform polynomial modulo `modulus`
+pub fn sample_uniform(n: usize, modulus: u64) -> Vec<u64> {
+    let mut rng = rand::thread_rng();
+    (0..n).map(|_| rng.gen_range(0..modulus)).collect()
+}
+
+// ==================== ARITHMETIC ====================
+
+/// Maps signed coefficients to [0, modulus)
+pub fn reduce_signed(poly: &[i64], modulus: u64) -> Vec<u64> {
+    poly.iter()
+        .map(|&c| c.rem_euclid(modulus as i64) as u64)
+        .collect()
+}
+
+pub fn poly_add(a: &[u64], b: &[u64], modulus: u64) -> Vec<u64> {
+    a.iter().zip(b).map(|(&x, &y)| modular::mod_add(x, y, modulus)).collect()
+}
+
+pub fn poly_sub(a: &[u64], b: &[u64], modulus: u64) -> Vec<u64> {
+    a.iter().zip(b).map(|(&x, &y)| modular::mod_sub(x, y, modulus)).collect()
+}
+
+pub fn poly_negate(a: &[u64], modulus: u64) -> Vec<u64> {
+    a.iter().map(|&x| modular::mod_sub(0, x, modulus)).collect()
+}
+
+/// Negacyclic product a·b mod (X^N + 1, q) through the NTT
+pub fn poly_mul(a: &[u64], b: &[u64], tables: &NttTables) -> Vec<u64> {
+    let q = tables.modulus;
+    let mut fa = a.to_vec();
+    let mut fb = b.to_vec();
+    tables.forward(&mut fa);
+    tables.forward(&mut fb);
+    for (x, &y) in fa.iter_mut().zip(fb.iter()) {
+        *x = modular::mod_mul(*x, y, q);
+    }
+    tables.inverse(&mut fa);
+    fa
+}
+
+// ==================== GALOIS AUTOMORPHISMS ====================
+
+/// Galois element 5^step mod 2N; rotates the batching rows left by `step`
+/// (negative steps rotate right)
+pub fn galois_element_for_step(step: i64, n: usize) -> u64 {
+    let two_n = 2 * n as u64;
+    let e = step.rem_euclid(n as i64 / 2) as u64;
+    modular::mod_pow(5, e, two_n)
+}
+
+/// Minimal set of Galois elements for rotating directly by each of `steps`:
+/// steps equal modulo N/2 share one element and step 0 needs none
+pub fn galois_elements_for_steps(steps: &[i64], n: usize) -> Vec<u64> {
+    let mut elements: Vec<u64> = steps.iter()
+        .map(|&k| galois_element_for_step(k, n))
+        .filter(|&g| g != 1)
+        .collect();
+    elements.sort_unstable();
+    elements.dedup();
+    elements
+}
+
+/// Galois element 2N - 1 (X -> X^-1): swaps the two batching rows
+pub fn galois_element_conjugate(n: usize) -> u64 {
+    2 * n as u64 - 1
+}
+
+/// a(X) -> a(X^g) mod (X^N + 1, q) for odd g
+pub fn apply_galois(a: &[u64], galois_elt: u64, modulus: u64) -> Vec<u64> {
+    let n = a.len();
+    let two_n = 2 * n as u64;
+    let mut out = vec![0u64; n];
+    for (i, &x) in a.iter().enumerate() {
+        let j = (i as u64 * galois_elt % two_n) as usize;
+        if j < n {
+            out[j] = x;
+        } else {
+            // X^N = -1
+            out[j - n] = modular::mod_sub(0, x, modulus);
+        }
+    }
+    out
+}
+
+/// Index map of X -> X^g on NTT vectors: out[i] = in[perm[i]]
+///
+/// NTT slot i holds the evaluation at ψ^(2·bitrev(i) + 1) and
+/// a(X^g) at ψ^e is a at ψ^(e·g), so the automorphism only permutes slots.
+pub fn galois_ntt_permutation(galois_elt: u64, n: usize) -> Vec<usize> {
+    let bits = n.trailing_zeros();
+    let two_n = 2 * n as u64;
+    let bitrev = |i: usize| i.reverse_bits() >> (usize::BITS - bits);
+    (0..n)
+        .map(|i| {
+            let e = (2 * bitrev(i) as u64 + 1) * galois_elt % two_n;
+            bitrev(((e - 1) / 2) as usize)
+        })
+        .collect()
+}
+
+// ==================== RNS POLYNOMIALS ====================
+
+/// Polynomial in RNS form: one residue vector per modulus of the chain prefix
+///
+/// Operations pair limb i with `tables[i]`; the table slice may be longer
+/// than the polynomial (e.g. the full chain for a lower-level ciphertext).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RnsPoly {
+    pub limbs: Vec<Vec<u64>>,
+}
+
+impl RnsPoly {
+    pub fn zero(n: usize, limb_count: usize) -> Self {
+        Self { limbs: vec![vec![0u64; n]; limb_count] }
+    }
+
+    /// Reduces a small signed polynomial modulo every table's modulus
+    pub fn from_signed(poly: &[i64], tables: &[NttTables]) -> Self {
+        Self { limbs: tables.iter().map(|t| reduce_signed(poly, t.modulus)).collect() }
+    }
+
+    pub fn uniform(n: usize, tables: &[NttTables]) -> Self {
+        Self { limbs: tables.iter().map(|t| sample_uniform(n, t.modulus)).collect() }
+    }
+
+    /// Index of the highest limb (level in the modulus chain)
+    pub fn level(&self) -> usize {
+        self.limbs.len() - 1
+    }
+
+    /// Copy restricted to the first `limb_count` limbs
+    pub fn truncated(&self, limb_count: usize) -> Self {
+        Self { limbs: self.limbs[..limb_count].to_vec() }
+    }
+
+    pub fn add(&self, other: &Self, tables: &[NttTables]) -> Self {
+        debug_assert_eq!(self.limbs.len(), other.limbs.len());
+        let limbs = self.limbs.iter().zip(&other.limbs).zip(tables)
+            .map(|((a, b), t)| poly_add(a, b, t.modulus))
+            .collect();
+        Self { limbs }
+    }
+
+    pub fn sub(&self, other: &Self, tables: &[NttTables]) -> Self {
+        debug_assert_eq!(self.limbs.len(), other.limbs.len());
+        let limbs = self.limbs.iter().zip(&other.limbs).zip(tables)
+            .map(|((a, b), t)| poly_sub(a, b, t.modulus))
+            .collect();
+        Self { limbs }
+    }
+
+    pub fn negate(&self, tables: &[NttTables]) -> Self {
+        let limbs = self.limbs.iter().zip(tables)
+            .map(|(a, t)| poly_negate(a, t.modulus))
+            .collect();
+        Self { limbs }
+    }
+
+    /// Negacyclic product, limb by limb through the NTT
+    pub fn mul(&self, other: &Self, tables: &[NttTables]) -> Self {
+        debug_assert_eq!(self.limbs.len(), other.limbs.len());
+        let limbs = self.limbs.iter().zip(&other.limbs).zip(tables)
+            .map(|((a, b), t)| poly_mul(a, b, t))
+            .collect();
+        Self { limbs }
+    }
+
+    /// a(X) -> a(X^g) on every limb (coefficient form)
+    pub fn automorphism(&self, galois_elt: u64, tables: &[NttTables]) -> Self {
+        let limbs = self.limbs.iter().zip(tables)
+            .map(|(a, t)| apply_galois(a, galois_elt, t.modulus))
+            .collect();
+        Self { limbs }
+    }
+
+    /// X -> X^g on every limb in NTT form (see `galois_ntt_permutation`)
+    pub fn permuted(&self, perm: &[usize]) -> Self {
+        let limbs = self.limbs.iter()
+            .map(|a| perm.iter().map(|&j| a[j]).collect())
+            .collect();
+        Self { limbs }
+    }
+
+    /// X^k·a(X) on every limb (coefficient form), wrapping negacyclically
+    pub fn mul_monomial(&self, k: usize, tables: &[NttTables]) -> Self {
+        let limbs = self.limbs.iter().zip(tables)
+            .map(|(a, t)| {
+                let n = a.len();
+                let k = k % (2 * n);
+                let mut out = vec![0u64; n];
+                for (i, &x) in a.iter().enumerate() {
+                    let j = (i + k) % (2 * n);
+                    if j < n {
+                        out[j] = x;
+                    } else {
+                        out[j - n] = if x == 0 { 0 } else { t.modulus - x };
+                    }
+                }
+                out
+            })
+            .collect();
+        Self { limbs }
+    }
+
+    /// Multiplies limb i by the scalar `scalars[i]`
+    pub fn mul_scalars(&self, scalars: &[u64], tables: &[NttTables]) -> Self {
+        let limbs = self.limbs.iter().zip(scalars).zip(tables)
+            .map(|((a, &s), t)| {
+                let s_shoup = modular::shoup_precompute(s, t.modulus);
+                a.iter().map(|&x| modular::mod_mul_shoup(x, s, s_shoup, t.modulus)).collect()
+            })
+            .collect();
+        Self { limbs }
+    }
+}
+
+/// Tensor product of ciphertext components, out[i + j] += lhs[i]·rhs[j],
+/// with one forward NTT per input and one inverse NTT per output
+pub fn tensor(lhs: &[RnsPoly], rhs: &[RnsPoly], tables: &[NttTables]) -> Vec<RnsPoly> {
+    let to_ntt = |c: &RnsPoly| {
+        let mut c = c.clone();
+        for (limb, tab) in c.limbs.iter_mut().zip(tables) {
+            tab.forward(limb);
+        }
+        c
+    };
+    let lhs: Vec<RnsPoly> = lhs.iter().map(to_ntt).collect();
+    let rhs: Vec<RnsPoly> = rhs.iter().map(to_ntt).collect();
+    let n = lhs[0].limbs[0].len();
+    let limb_count = lhs[0].limbs.len();
+
+    let mut product = vec![RnsPoly::zero(n, limb_count); lhs.len() + rhs.len() - 1];
+    for (i, x) in lhs.iter().enumerate() {
+        for (j, y) in rhs.iter().enumerate() {
+            for (m, tab) in tables.iter().enumerate().take(limb_count) {
+                let q = tab.modulus;
+                let out = &mut product[i + j].limbs[m];
+                for ((o, &u), &v) in out.iter_mut().zip(&x.limbs[m]).zip(&y.limbs[m]) {
+                    *o = modular::mod_add(*o, modular::mod_mul(u, v, q), q);
+                }
+            }
+        }
+    }
+    for c in product.iter_mut() {
+        for (limb, tab) in c.limbs.iter_mut().zip(tables) {
+            tab.inverse(limb);
+        }
+    }
+    product
+}
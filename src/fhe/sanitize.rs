@@ -0,0 +1,262 @@
+//! Ciphertext sanitization for BFV and BGV (circuit privacy)
+//! Re-randomizes an evaluated ciphertext so that it reveals nothing about
+//! the circuit beyond the decrypted result
+//!
+//! An evaluated ciphertext (c0, c1) carries a noise e that depends on the
+//! circuit and its inputs. Sanitizing adds a fresh public-key encryption
+//! of zero, which makes c1 look freshly sampled, and a flooding noise E
+//! drawn uniformly from [-F, F] to c0 (t·E for BGV, so it stays a
+//! multiple of t). For coefficients |e| <= B, the statistical distance
+//! between E and E + e is at most N·B/(2F) summed over the N coefficients,
+//! so F = 2^s·N·B, rounded up to a power of two, gives s bits of
+//! statistical circuit privacy (Ducas–Stehlé "noise flooding"). B comes
+//! from the heuristic `noise_bits` carried by the ciphertext, which the
+//! evaluator keeps and does not send.
+//!
+//! Flooding costs about s + log2(N) bits of noise budget. Switching to a
+//! lower level first shrinks the noise to that of a modulus switch, so a
+//! small target level often leaves room for a large s. BFV/BGV
+//! bootstrapping is not available in this tree; ciphertexts without
+//! enough budget must be refreshed by the key holder.
+
+use super::super::modular;
+use super::bfv::{self, log2_add, BFVContext, Ciphertext, PublicKey, SchemeType};
+use super::bgv;
+use super::poly::{self, RnsPoly};
+use crate::ntt::NttTables;
+use rand::Rng;
+
+#[derive(Clone, Copy, Debug)]
+pub struct SanitizeParameters {
+    /// Statistical security s in bits
+    pub statistical_security: u32,
+    /// Level to switch down to before flooding (None keeps the level)
+    pub target_level: Option<usize>,
+}
+
+impl SanitizeParameters {
+    pub fn new(statistical_security: u32) -> Self {
+        Self { statistical_security, target_level: None }
+    }
+
+    pub fn with_target_level(mut self, level: usize) -> Self {
+        self.target_level = Some(level);
+        self
+    }
+}
+
+impl bfv::Encryptor {
+    /// Re-randomizes a size-2 BFV ciphertext with s bits of circuit privacy
+    pub fn sanitize(&self, ct: &Ciphertext, params: &SanitizeParameters) -> Result<Ciphertext, String> {
+        let mut ct = ct.clone();
+        if let Some(level) = params.target_level {
+            bfv::Evaluator::new(&self.context).mod_switch_to_inplace(&mut ct, level)?;
+        }
+        flood(&self.context, &self.public_key, ct, params)
+    }
+}
+
+impl bgv::Encryptor {
+    /// Re-randomizes a size-2 BGV ciphertext with s bits of circuit privacy
+    pub fn sanitize(&self, ct: &Ciphertext, params: &SanitizeParameters) -> Result<Ciphertext, String> {
+        let mut ct = ct.clone();
+        if let Some(level) = params.target_level {
+            bgv::Evaluator::new(&self.context)?.mod_switch_to_inplace(&mut ct, level)?;
+        }
+        flood(&self.context, &self.public_key, ct, params)
+    }
+}
+
+/// ct + (b·u + e1 + E, a·u + e2) at the ciphertext's level, with e1, e2
+/// and E scaled by t for BGV
+fn flood(context: &BFVContext, public_key: &PublicKey, mut ct: Ciphertext, params: &SanitizeParameters) -> Result<Ciphertext, String> {
+    if ct.size() != 2 {
+        return Err(format!("relinearize the size-{} ciphertext before sanitizing", ct.size()));
+    }
+    let level = ct.level();
+    let n = context.params.poly_degree;
+    let t = context.params.plain_modulus;
+    let tables = context.tables(level);
+
+    // ||e||_∞ <= 2^noise_bits / t in both schemes; F is rounded up to 2^k
+    let log_t = (t as f64).log2();
+    let k = (params.statistical_security as f64 + (n as f64).log2() + ct.noise_bits - log_t).ceil().max(0.0) as u32;
+    let noise_bits = log2_add(
+        log2_add(ct.noise_bits, context.fresh_noise_bits()),
+        k as f64 + log_t,
+    );
+    let q_bits = context.bases[level].bits();
+    if noise_bits + 1.0 >= q_bits {
+        return Err(format!(
+            "{}-bit sanitization needs about {:.0} bits of noise at level {}, which has {:.0} bits of modulus",
+            params.statistical_security, noise_bits + 1.0, level, q_bits
+        ));
+    }
+
+    let multiple = if context.params.scheme == SchemeType::Bgv { t } else { 1 };
+    let flooding = uniform_power_of_two(n, k, tables).mul_scalars(&vec![multiple; level + 1], tables);
+
+    let error = |tables: &[_]| {
+        RnsPoly::from_signed(&poly::sample_gaussian(n, poly::ERROR_STD_DEV), tables)
+            .mul_scalars(&vec![multiple; level + 1], tables)
+    };
+    let u = RnsPoly::from_signed(&poly::sample_ternary(n), tables);
+    let zero0 = public_key.b.truncated(level + 1).mul(&u, tables)
+        .add(&error(tables), tables)
+        .add(&flooding, tables);
+    let zero1 = public_key.a.truncated(level + 1).mul(&u, tables).add(&error(tables), tables);
+
+    ct.polys[0] = ct.polys[0].add(&zero0, tables);
+    ct.polys[1] = ct.polys[1].add(&zero1, tables);
+    ct.noise_bits = noise_bits;
+    Ok(ct)
+}
+
+/// n integers uniform in [-2^k, 2^k), sampled as k + 1 random bits
+fn uniform_power_of_two(n: usize, k: u32, tables: &[NttTables]) -> RnsPoly {
+    let mut rng = rand::thread_rng();
+    let words = (k as usize + 1).div_ceil(64);
+    let top_bits = (k + 1) - 64 * (words as u32 - 1);
+    let top_mask = if top_bits == 64 { u64::MAX } else { (1u64 << top_bits) - 1 };
+    let samples: Vec<Vec<u64>> = (0..n)
+        .map(|_| {
+            let mut w: Vec<u64> = (0..words).map(|_| rng.gen()).collect();
+            w[0] &= top_mask;
+            w
+        })
+        .collect();
+
+    let limbs = tables.iter()
+        .map(|tab| {
+            let q = tab.modulus;
+            let word_mod_q = ((1u128 << 64) % q as u128) as u64;
+            let offset = modular::mod_pow(2, k as u64, q);
+            samples.iter()
+                .map(|w| {
+                    // Horner over the 64-bit words, most significant first
+                    let r = w.iter().fold(0, |r, &x| modular::mod_add(modular::mod_mul(r, word_mod_q, q), x % q, q));
+                    modular::mod_sub(r, offset, q)
+                })
+                .collect()
+        })
+        .collect();
+    RnsPoly { limbs }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fhe::{BFVParameters, BatchEncoder, KeyGenerator, Plaintext, RelinKeys};
+    use crate::modular::mod_mul;
+
+    const T: u64 = 65537;
+
+    /// The encryption, evaluation and decryption calls of one scheme
+    trait Scheme: Sized {
+        const TYPE: SchemeType;
+        fn new(context: &BFVContext, keygen: &KeyGenerator) -> Self;
+        fn encrypt(&self, plain: &Plaintext) -> Ciphertext;
+        fn multiply_relin(&self, a: &Ciphertext, b: &Ciphertext, relin_keys: &RelinKeys) -> Ciphertext;
+        fn sanitize(&self, ct: &Ciphertext, params: &SanitizeParameters) -> Result<Ciphertext, String>;
+        fn decrypt(&self, ct: &Ciphertext) -> Plaintext;
+        fn invariant_noise_budget(&self, ct: &Ciphertext) -> u32;
+    }
+
+    type Bfv = (bfv::Encryptor, bfv::Decryptor, bfv::Evaluator);
+    type Bgv = (bgv::Encryptor, bgv::Decryptor, bgv::Evaluator);
+
+    impl Scheme for Bfv {
+        const TYPE: SchemeType = SchemeType::Bfv;
+        fn new(context: &BFVContext, keygen: &KeyGenerator) -> Self {
+            (
+                bfv::Encryptor::new(context, &keygen.public_key()),
+                bfv::Decryptor::new(context, &keygen.secret_key()),
+                bfv::Evaluator::new(context),
+            )
+        }
+        fn encrypt(&self, plain: &Plaintext) -> Ciphertext {
+            self.0.encrypt(plain)
+        }
+        fn multiply_relin(&self, a: &Ciphertext, b: &Ciphertext, relin_keys: &RelinKeys) -> Ciphertext {
+            self.2.multiply_relin(a, b, relin_keys).unwrap()
+        }
+        fn sanitize(&self, ct: &Ciphertext, params: &SanitizeParameters) -> Result<Ciphertext, String> {
+            self.0.sanitize(ct, params)
+        }
+        fn decrypt(&self, ct: &Ciphertext) -> Plaintext {
+            self.1.decrypt(ct)
+        }
+        fn invariant_noise_budget(&self, ct: &Ciphertext) -> u32 {
+            self.1.invariant_noise_budget(ct)
+        }
+    }
+
+    impl Scheme for Bgv {
+        const TYPE: SchemeType = SchemeType::Bgv;
+        fn new(context: &BFVContext, keygen: &KeyGenerator) -> Self {
+            (
+                bgv::Encryptor::new(context, &keygen.public_key()).unwrap(),
+                bgv::Decryptor::new(context, &keygen.secret_key()).unwrap(),
+                bgv::Evaluator::new(context).unwrap(),
+            )
+        }
+        fn encrypt(&self, plain: &Plaintext) -> Ciphertext {
+            self.0.encrypt(plain)
+        }
+        fn multiply_relin(&self, a: &Ciphertext, b: &Ciphertext, relin_keys: &RelinKeys) -> Ciphertext {
+            self.2.multiply_relin(a, b, relin_keys).unwrap()
+        }
+        fn sanitize(&self, ct: &Ciphertext, params: &SanitizeParameters) -> Result<Ciphertext, String> {
+            self.0.sanitize(ct, params)
+        }
+        fn decrypt(&self, ct: &Ciphertext) -> Plaintext {
+            self.1.decrypt(ct)
+        }
+        fn invariant_noise_budget(&self, ct: &Ciphertext) -> u32 {
+            self.1.invariant_noise_budget(ct)
+        }
+    }
+
+    /// Sanitizes an encrypted product at several security levels and
+    /// target levels and checks the decryption and the tracked noise
+    fn check_sanitization<S: Scheme>() {
+        let params = BFVParameters::new(4096, T, &[60, 60, 60, 60, 60]).unwrap().with_scheme(S::TYPE);
+        let context = BFVContext::with_params(params).unwrap();
+        let keygen = KeyGenerator::new(&context);
+        let encoder = BatchEncoder::new(&context).unwrap();
+        let scheme = S::new(&context, &keygen);
+        let mut rng = rand::thread_rng();
+        let a: Vec<u64> = (0..4096).map(|_| rng.gen_range(0..T)).collect();
+        let b: Vec<u64> = (0..4096).map(|_| rng.gen_range(0..T)).collect();
+        let product: Vec<u64> = a.iter().zip(&b).map(|(&x, &y)| mod_mul(x, y, T)).collect();
+
+        let ca = scheme.encrypt(&encoder.encode(&a).unwrap());
+        let cb = scheme.encrypt(&encoder.encode(&b).unwrap());
+        let prod = scheme.multiply_relin(&ca, &cb, &keygen.relin_keys());
+        for params in [
+            SanitizeParameters::new(40),
+            SanitizeParameters::new(80),
+            SanitizeParameters::new(40).with_target_level(1),
+            SanitizeParameters::new(128).with_target_level(2),
+        ] {
+            let sanitized = scheme.sanitize(&prod, &params).unwrap();
+            assert_eq!(sanitized.level(), params.target_level.unwrap_or(prod.level()));
+            assert_ne!(sanitized.polys[1], prod.polys[1]);
+            assert_eq!(encoder.decode(&scheme.decrypt(&sanitized)), product);
+            // The tracked noise stays a conservative estimate
+            assert!(scheme.invariant_noise_budget(&sanitized) + 3 >= context.estimated_noise_budget(&sanitized));
+        }
+        assert!(scheme.sanitize(&prod, &SanitizeParameters::new(40).with_target_level(0)).is_err());
+        assert!(scheme.sanitize(&prod, &SanitizeParameters::new(200)).is_err());
+    }
+
+    #[test]
+    fn bfv_sanitization() {
+        check_sanitization::<Bfv>();
+    }
+
+    #[test]
+    fn bgv_sanitization() {
+        check_sanitization::<Bgv>();
+    }
+}
@@ -0,0 +1,1045 @@
+//! TFHE: LWE and GLWE ciphertexts on the discretized torus
+//! Programmable bootstrapping by blind rotation of a lookup table
+//!
+//! Torus elements are integers modulo 2^32 or 2^64 (`Torus`), read as
+//! fractions of 2^BITS. An LWE ciphertext (a, b) under a binary key s has
+//! phase b - <a, s> = m + e; a GLWE ciphertext does the same with k
+//! polynomials in T[X]/(X^N + 1). A GGSW ciphertext of a small integer μ
+//! holds GLWE encryptions of μ·q/B^j (base B = 2^base_log, j = 1..level)
+//! for every component, so that the external product with a GLWE
+//! ciphertext c, Σ_j Σ_i dec_j(c_i)·row_(i,j), encrypts μ·m with additive
+//! noise. CMux(μ, c0, c1) = c0 + GGSW(μ) ⊡ (c1 - c0) selects by a bit.
+//!
+//! Bootstrapping switches an LWE ciphertext modulo 2N (ã = round(2N·a/q)),
+//! starts from X^(-b̃)·v for a lookup-table polynomial v and rotates it by
+//! X^(ã_i·s_i) with one CMux per key bit; the constant coefficient of the
+//! result encrypts v_φ for the phase φ = b̃ - <ã, s>. For φ >= N the
+//! negacyclic wrap returns -v_(φ-N), so messages keep a padding bit and
+//! phases stay in [0, 1/2). Sample extraction yields an LWE ciphertext
+//! under the flattened GLWE key (dimension k·N), and key switching brings
+//! it back to the LWE key.
+//!
+//! Negacyclic products run through an f64 FFT of size N/2 on the folded
+//! polynomial (a_j + i·a_(j+N/2))·ω^j, ω = exp(πi/N). Its rounding errors
+//! land in the low bits of the torus and act as extra noise; key
+//! generation splits torus coefficients into 16-bit limbs and multiplies
+//! exactly.
+
+use super::poly;
+use num_complex::Complex64;
+use rand::Rng;
+use std::f64::consts::PI;
+use std::fmt;
+use std::marker::PhantomData;
+
+// ==================== TORUS ====================
+
+/// Discretized torus Z/2^BITS
+pub trait Torus: Copy + Default + PartialEq + Eq + fmt::Debug + Send + Sync + 'static {
+    const BITS: u32;
+    /// Reduction of x modulo 2^BITS
+    fn from_u64(x: u64) -> Self;
+    fn to_u64(self) -> u64;
+    fn wrapping_add(self, other: Self) -> Self;
+    fn wrapping_sub(self, other: Self) -> Self;
+    fn wrapping_neg(self) -> Self;
+    fn wrapping_mul(self, other: Self) -> Self;
+
+    fn zero() -> Self {
+        Self::default()
+    }
+
+    /// x·2^BITS rounded, for a fraction x of the torus
+    fn from_fraction(x: f64) -> Self {
+        Self::from_f64(x * (Self::BITS as f64).exp2())
+    }
+
+    /// Representative in [-1/2, 1/2)
+    fn to_fraction(self) -> f64 {
+        self.to_signed_f64() / (Self::BITS as f64).exp2()
+    }
+
+    /// Integer x modulo 2^BITS, rounded to the nearest element
+    fn from_f64(x: f64) -> Self {
+        // i64 holds every product of the 32-bit torus; wider values are
+        // reduced modulo 2^BITS first
+        let x = if x.abs() < 2f64.powi(62) {
+            x
+        } else {
+            let q = (Self::BITS as f64).exp2();
+            x - (x / q).round() * q
+        };
+        let r = if x >= 0.0 { x + 0.5 } else { x - 0.5 };
+        Self::from_u64(r as i64 as u64)
+    }
+
+    /// Centered representative in [-2^(BITS-1), 2^(BITS-1))
+    fn to_signed_f64(self) -> f64 {
+        let x = self.to_u64();
+        if Self::BITS == 64 {
+            x as i64 as f64
+        } else if x >> (Self::BITS - 1) == 1 {
+            x as f64 - (Self::BITS as f64).exp2()
+        } else {
+            x as f64
+        }
+    }
+
+    /// self·k for a signed integer k
+    fn mul_int(self, k: i64) -> Self {
+        self.wrapping_mul(Self::from_u64(k as u64))
+    }
+}
+
+macro_rules! impl_torus {
+    ($t:ty) => {
+        impl Torus for $t {
+            const BITS: u32 = <$t>::BITS;
+            fn from_u64(x: u64) -> Self {
+                x as $t
+            }
+            fn to_u64(self) -> u64 {
+                self as u64
+            }
+            fn wrapping_add(self, other: Self) -> Self {
+                <$t>::wrapping_add(self, other)
+            }
+            fn wrapping_sub(self, other: Self) -> Self {
+                <$t>::wrapping_sub(self, other)
+            }
+            fn wrapping_neg(self) -> Self {
+                <$t>::wrapping_neg(self)
+            }
+            fn wrapping_mul(self, other: Self) -> Self {
+                <$t>::wrapping_mul(self, other)
+            }
+        }
+    };
+}
+
+impl_torus!(u32);
+impl_torus!(u64);
+
+/// m·q/(2p): messages modulo p with one bit of padding
+pub fn encode_message<T: Torus>(m: u64, message_modulus: u64) -> T {
+    T::from_fraction((m % message_modulus) as f64 / (2 * message_modulus) as f64)
+}
+
+/// Nearest message to a phase encoded by `encode_message`
+pub fn decode_message<T: Torus>(phase: T, message_modulus: u64) -> u64 {
+    let scaled = (phase.to_fraction() * (2 * message_modulus) as f64).round() as i64;
+    scaled.rem_euclid(message_modulus as i64) as u64
+}
+
+fn sample_torus_gaussian<T: Torus>(n: usize, std_dev: f64) -> Vec<T> {
+    let scaled = std_dev * (T::BITS as f64).exp2();
+    poly::sample_gaussian(n, scaled).into_iter().map(|x| T::from_u64(x as u64)).collect()
+}
+
+fn sample_torus_uniform<T: Torus>(n: usize) -> Vec<T> {
+    let mut rng = rand::thread_rng();
+    (0..n).map(|_| T::from_u64(rng.gen())).collect()
+}
+
+fn sample_binary(n: usize) -> Vec<i64> {
+    let mut rng = rand::thread_rng();
+    (0..n).map(|_| rng.gen_range(0..=1)).collect()
+}
+
+/// Balanced digits of x in base 2^base_log, most significant first, after
+/// rounding to the top base_log·level bits
+fn decompose<T: Torus>(x: T, base_log: u32, level: usize, digits: &mut [i64]) {
+    let precision = base_log * level as u32;
+    let shift = T::BITS - precision;
+    let x = x.to_u64() as u128;
+    let mut v = if shift == 0 { x } else { (x + (1u128 << (shift - 1))) >> shift };
+    v &= (1u128 << precision) - 1;
+    let base = 1i64 << base_log;
+    for digit in digits[..level].iter_mut().rev() {
+        let mut d = (v & (base as u128 - 1)) as i64;
+        v >>= base_log;
+        if d >= base / 2 {
+            d -= base;
+            v += 1;
+        }
+        *digit = d;
+    }
+}
+
+/// q/B^level as a torus element (level counted from 1)
+fn gadget<T: Torus>(base_log: u32, level: usize) -> T {
+    T::from_u64(1u64 << (T::BITS - base_log * level as u32))
+}
+
+// ==================== PARAMETERS ====================
+
+#[derive(Clone, Debug)]
+pub struct TFHEParameters {
+    /// LWE dimension n
+    pub lwe_dimension: usize,
+    /// GLWE dimension k
+    pub glwe_dimension: usize,
+    /// Polynomial degree N of the GLWE ring
+    pub poly_degree: usize,
+    /// LWE noise standard deviation as a fraction of the torus
+    pub lwe_noise_std: f64,
+    /// GLWE noise standard deviation as a fraction of the torus
+    pub glwe_noise_std: f64,
+    /// Gadget decomposition of the bootstrapping key
+    pub pbs_base_log: u32,
+    pub pbs_level: usize,
+    /// Gadget decomposition of the key-switching key
+    pub ks_base_log: u32,
+    pub ks_level: usize,
+}
+
+impl TFHEParameters {
+    /// Parameters with the decompositions B = 2^7, l = 3 for bootstrapping
+    /// and B = 2^2, l = 8 for key switching
+    pub fn new(
+        lwe_dimension: usize,
+        glwe_dimension: usize,
+        poly_degree: usize,
+        lwe_noise_std: f64,
+        glwe_noise_std: f64,
+    ) -> Result<Self, String> {
+        if lwe_dimension == 0 || glwe_dimension == 0 {
+            return Err("LWE and GLWE dimensions must be positive".to_string());
+        }
+        if !poly_degree.is_power_of_two() || poly_degree < 4 {
+            return Err(format!("poly_degree {} must be a power of two >= 4", poly_degree));
+        }
+        if !(lwe_noise_std > 0.0 && lwe_noise_std < 1.0 && glwe_noise_std > 0.0 && glwe_noise_std < 1.0) {
+            return Err("noise standard deviations must lie in (0, 1)".to_string());
+        }
+        Ok(Self {
+            lwe_dimension,
+            glwe_dimension,
+            poly_degree,
+            lwe_noise_std,
+            glwe_noise_std,
+            pbs_base_log: 7,
+            pbs_level: 3,
+            ks_base_log: 2,
+            ks_level: 8,
+        })
+    }
+
+    pub fn with_pbs_decomposition(mut self, base_log: u32, level: usize) -> Self {
+        self.pbs_base_log = base_log;
+        self.pbs_level = level;
+        self
+    }
+
+    pub fn with_ks_decomposition(mut self, base_log: u32, level: usize) -> Self {
+        self.ks_base_log = base_log;
+        self.ks_level = level;
+        self
+    }
+
+    /// Checks that both decompositions fit a torus of `bits` bits
+    fn check_decompositions(&self, bits: u32) -> Result<(), String> {
+        for (name, base_log, level) in [
+            ("bootstrapping", self.pbs_base_log, self.pbs_level),
+            ("key-switching", self.ks_base_log, self.ks_level),
+        ] {
+            if base_log == 0 || level == 0 || base_log * level as u32 > bits || base_log > 32 {
+                return Err(format!(
+                    "{} decomposition 2^{} x {} does not fit a {}-bit torus",
+                    name, base_log, level, bits
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+// ==================== NEGACYCLIC FFT ====================
+
+/// f64 FFT for products in R[X]/(X^N + 1)
+///
+/// The forward transform leaves its values in bit-reversed order and the
+/// inverse reads them back in that order, like `NttTables`; pointwise
+/// products do not care.
+#[derive(Clone, Debug)]
+struct NegacyclicFft {
+    n: usize,
+    twist: Vec<Complex64>,   // ω^j for j < N/2
+    untwist: Vec<Complex64>, // ω^-j/(N/2)
+    roots: Vec<Complex64>, // exp(-2πi·k/len) for k < len/2, stage by stage
+}
+
+impl NegacyclicFft {
+    fn new(n: usize) -> Self {
+        let half = n / 2;
+        let mut roots = Vec::with_capacity(half);
+        let mut len = 2;
+        while len <= half {
+            roots.extend((0..len / 2).map(|k| Complex64::from_polar(1.0, -2.0 * PI * k as f64 / len as f64)));
+            len <<= 1;
+        }
+        let twist: Vec<Complex64> = (0..half).map(|j| Complex64::from_polar(1.0, PI * j as f64 / n as f64)).collect();
+        let untwist = twist.iter().map(|w| w.conj() / half as f64).collect();
+        Self { n, twist, untwist, roots }
+    }
+
+    /// Folds, twists and transforms N real coefficients into N/2 values
+    fn forward(&self, coeffs: impl Fn(usize) -> f64) -> Vec<Complex64> {
+        let half = self.n / 2;
+        let mut a: Vec<Complex64> = (0..half)
+            .map(|j| Complex64::new(coeffs(j), coeffs(j + half)) * self.twist[j])
+            .collect();
+        // decimation in frequency, natural order in, bit-reversed out
+        let mut len = half;
+        while len >= 2 {
+            let h = len / 2;
+            let roots = &self.roots[h - 1..len - 1];
+            for block in a.chunks_exact_mut(len) {
+                let (lo, hi) = block.split_at_mut(h);
+                for ((u, v), w) in lo.iter_mut().zip(hi.iter_mut()).zip(roots) {
+                    let diff = *u - *v;
+                    *u += *v;
+                    *v = diff * w;
+                }
+            }
+            len = h;
+        }
+        a
+    }
+
+    /// Inverse of `forward`, returning the N real coefficients
+    fn backward(&self, mut a: Vec<Complex64>) -> Vec<f64> {
+        let half = self.n / 2;
+        // decimation in time with conjugate roots, bit-reversed in
+        let mut len = 2;
+        while len <= half {
+            let h = len / 2;
+            let roots = &self.roots[h - 1..len - 1];
+            for block in a.chunks_exact_mut(len) {
+                let (lo, hi) = block.split_at_mut(h);
+                for ((u, v), w) in lo.iter_mut().zip(hi.iter_mut()).zip(roots) {
+                    let t = *v * w.conj();
+                    *v = *u - t;
+                    *u += t;
+                }
+            }
+            len <<= 1;
+        }
+        let mut coeffs = vec![0.0; self.n];
+        for (j, v) in a.iter().enumerate() {
+            let v = v * self.untwist[j];
+            coeffs[j] = v.re;
+            coeffs[j + half] = v.im;
+        }
+        coeffs
+    }
+}
+
+/// Torus polynomial times integer polynomial: products are rounded back
+/// onto the torus
+fn fourier_to_torus<T: Torus>(fft: &NegacyclicFft, values: Vec<Complex64>) -> Vec<T> {
+    fft.backward(values).into_iter().map(T::from_f64).collect()
+}
+
+fn torus_to_fourier<T: Torus>(fft: &NegacyclicFft, poly: &[T]) -> Vec<Complex64> {
+    fft.forward(|j| poly[j].to_signed_f64())
+}
+
+/// Exact a·s for a torus polynomial a and a binary polynomial s given by
+/// its transform: 16-bit limbs keep every product exact in f64
+fn mul_binary_exact<T: Torus>(fft: &NegacyclicFft, a: &[T], s_fourier: &[Complex64]) -> Vec<T> {
+    let n = fft.n;
+    let limbs = T::BITS / 16;
+    let mut result = vec![T::zero(); n];
+    // balanced 16-bit limbs: a = Σ_k limb_k·2^(16k) with limb_k in [-2^15, 2^15)
+    let mut rest: Vec<i128> = a.iter().map(|x| x.to_u64() as i128).collect();
+    for k in 0..limbs {
+        let limb: Vec<i64> = rest.iter_mut()
+            .map(|r| {
+                let mut d = (*r & 0xffff) as i64;
+                if d >= 1 << 15 {
+                    d -= 1 << 16;
+                }
+                *r = (*r - d as i128) >> 16;
+                d
+            })
+            .collect();
+        let mut values = fft.forward(|j| limb[j] as f64);
+        values.iter_mut().zip(s_fourier).for_each(|(v, s)| *v *= s);
+        let shift = T::from_u64(1u64 << (16 * k));
+        for (r, c) in result.iter_mut().zip(fft.backward(values)) {
+            *r = r.wrapping_add(T::from_u64(c.round() as i64 as u64).wrapping_mul(shift));
+        }
+    }
+    result
+}
+
+/// a·X^k in T[X]/(X^N + 1) for 0 <= k < 2N
+fn mul_monomial<T: Torus>(a: &[T], k: usize) -> Vec<T> {
+    let n = a.len();
+    let mut result = vec![T::zero(); n];
+    for (j, &x) in a.iter().enumerate() {
+        let e = (j + k) % (2 * n);
+        if e < n {
+            result[e] = x;
+        } else {
+            result[e - n] = x.wrapping_neg();
+        }
+    }
+    result
+}
+
+// ==================== CONTEXT ====================
+
+#[derive(Clone, Debug)]
+pub struct TFHEContext {
+    pub params: TFHEParameters,
+    fft: NegacyclicFft,
+}
+
+impl TFHEContext {
+    pub fn new(params: TFHEParameters) -> Self {
+        let fft = NegacyclicFft::new(params.poly_degree);
+        Self { params, fft }
+    }
+
+    /// Dimension k·N of the LWE ciphertexts that sample extraction yields
+    pub fn extracted_dimension(&self) -> usize {
+        self.params.glwe_dimension * self.params.poly_degree
+    }
+}
+
+// ==================== CIPHERTEXTS ====================
+
+/// LWE ciphertext with phase b - <a, s>
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LweCiphertext<T> {
+    pub mask: Vec<T>,
+    pub body: T,
+}
+
+impl<T: Torus> LweCiphertext<T> {
+    /// Noiseless encryption of `body` (zero mask)
+    pub fn trivial(dimension: usize, body: T) -> Self {
+        Self { mask: vec![T::zero(); dimension], body }
+    }
+
+    pub fn dimension(&self) -> usize {
+        self.mask.len()
+    }
+
+    pub fn add(&self, other: &Self) -> Self {
+        let mut result = self.clone();
+        result.add_inplace(other);
+        result
+    }
+
+    pub fn add_inplace(&mut self, other: &Self) {
+        assert_eq!(self.dimension(), other.dimension(), "LWE dimensions differ");
+        self.mask.iter_mut().zip(&other.mask).for_each(|(a, &b)| *a = a.wrapping_add(b));
+        self.body = self.body.wrapping_add(other.body);
+    }
+
+    pub fn sub(&self, other: &Self) -> Self {
+        let mut result = self.clone();
+        result.sub_inplace(other);
+        result
+    }
+
+    pub fn sub_inplace(&mut self, other: &Self) {
+        assert_eq!(self.dimension(), other.dimension(), "LWE dimensions differ");
+        self.mask.iter_mut().zip(&other.mask).for_each(|(a, &b)| *a = a.wrapping_sub(b));
+        self.body = self.body.wrapping_sub(other.body);
+    }
+
+    pub fn negate(&self) -> Self {
+        Self {
+            mask: self.mask.iter().map(|a| a.wrapping_neg()).collect(),
+            body: self.body.wrapping_neg(),
+        }
+    }
+
+    /// Adds a plaintext torus element to the phase
+    pub fn add_constant(&self, constant: T) -> Self {
+        Self { mask: self.mask.clone(), body: self.body.wrapping_add(constant) }
+    }
+
+    /// Multiplies the phase (message and noise) by a signed integer
+    pub fn mul_scalar(&self, k: i64) -> Self {
+        Self {
+            mask: self.mask.iter().map(|a| a.mul_int(k)).collect(),
+            body: self.body.mul_int(k),
+        }
+    }
+}
+
+/// GLWE ciphertext: k mask polynomials followed by the body
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct GlweCiphertext<T> {
+    pub polys: Vec<Vec<T>>,
+}
+
+impl<T: Torus> GlweCiphertext<T> {
+    /// Noiseless encryption of `body` (zero masks)
+    pub fn trivial(glwe_dimension: usize, body: Vec<T>) -> Self {
+        let mut polys = vec![vec![T::zero(); body.len()]; glwe_dimension];
+        polys.push(body);
+        Self { polys }
+    }
+
+    fn mul_monomial(&self, k: usize) -> Self {
+        Self { polys: self.polys.iter().map(|p| mul_monomial(p, k)).collect() }
+    }
+
+    fn sub(&self, other: &Self) -> Self {
+        let polys = self.polys.iter().zip(&other.polys)
+            .map(|(a, b)| a.iter().zip(b).map(|(&x, &y)| x.wrapping_sub(y)).collect())
+            .collect();
+        Self { polys }
+    }
+
+    fn add_inplace(&mut self, other: &Self) {
+        for (a, b) in self.polys.iter_mut().zip(&other.polys) {
+            a.iter_mut().zip(b).for_each(|(x, &y)| *x = x.wrapping_add(y));
+        }
+    }
+
+    /// LWE encryption of coefficient 0 under the flattened key:
+    /// (a·s)_0 = a_0·s_0 - Σ_(j>0) a_(N-j)·s_j
+    pub fn sample_extract(&self) -> LweCiphertext<T> {
+        let (body, masks) = self.polys.split_last().unwrap();
+        let mask = masks.iter()
+            .flat_map(|a| {
+                let n = a.len();
+                (0..n).map(move |j| if j == 0 { a[0] } else { a[n - j].wrapping_neg() })
+            })
+            .collect();
+        LweCiphertext { mask, body: body[0] }
+    }
+}
+
+/// GGSW ciphertext in the Fourier domain: (k + 1)·level GLWE rows, row
+/// (i, j) encrypting μ·q/B^j in component i (the body for i = k)
+#[derive(Clone, Debug)]
+pub struct GgswCiphertext<T> {
+    rows: Vec<Vec<Vec<Complex64>>>,
+    _torus: PhantomData<T>,
+}
+
+// ==================== KEYS ====================
+
+#[derive(Clone, Debug)]
+pub struct LweSecretKey {
+    pub(crate) bits: Vec<i64>,
+}
+
+impl LweSecretKey {
+    pub fn dimension(&self) -> usize {
+        self.bits.len()
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct GlweSecretKey {
+    pub(crate) polys: Vec<Vec<i64>>,
+}
+
+impl GlweSecretKey {
+    /// Flattened key that decrypts sample-extracted ciphertexts
+    pub fn as_lwe_key(&self) -> LweSecretKey {
+        LweSecretKey { bits: self.polys.concat() }
+    }
+}
+
+/// GGSW encryptions of the LWE key bits under the GLWE key
+#[derive(Clone, Debug)]
+pub struct BootstrapKey<T> {
+    ggsw: Vec<GgswCiphertext<T>>,
+}
+
+/// LWE encryptions of s'_i·q/B^j under the LWE key for every coefficient
+/// s'_i of the flattened GLWE key
+#[derive(Clone, Debug)]
+pub struct KeySwitchKey<T> {
+    keys: Vec<LweCiphertext<T>>, // index i·level + (j - 1)
+}
+
+pub struct KeyGenerator {
+    context: TFHEContext,
+    lwe_key: LweSecretKey,
+    glwe_key: GlweSecretKey,
+}
+
+impl KeyGenerator {
+    /// Samples binary LWE and GLWE keys
+    pub fn new(context: &TFHEContext) -> Self {
+        let params = &context.params;
+        let lwe_key = LweSecretKey { bits: sample_binary(params.lwe_dimension) };
+        let glwe_key = GlweSecretKey {
+            polys: (0..params.glwe_dimension).map(|_| sample_binary(params.poly_degree)).collect(),
+        };
+        Self { context: context.clone(), lwe_key, glwe_key }
+    }
+
+    pub fn lwe_secret_key(&self) -> LweSecretKey {
+        self.lwe_key.clone()
+    }
+
+    pub fn glwe_secret_key(&self) -> GlweSecretKey {
+        self.glwe_key.clone()
+    }
+
+    pub fn bootstrap_key<T: Torus>(&self) -> Result<BootstrapKey<T>, String> {
+        let params = &self.context.params;
+        params.check_decompositions(T::BITS)?;
+        let key_fourier: Vec<Vec<Complex64>> = self.glwe_key.polys.iter()
+            .map(|s| self.context.fft.forward(|j| s[j] as f64))
+            .collect();
+        let ggsw = self.lwe_key.bits.iter()
+            .map(|&bit| self.encrypt_ggsw::<T>(bit, &key_fourier))
+            .collect();
+        Ok(BootstrapKey { ggsw })
+    }
+
+    pub fn key_switch_key<T: Torus>(&self) -> Result<KeySwitchKey<T>, String> {
+        let params = &self.context.params;
+        params.check_decompositions(T::BITS)?;
+        let encryptor = Encryptor::new(&self.context, &self.lwe_key);
+        let keys = self.glwe_key.polys.concat().into_iter()
+            .flat_map(|s| {
+                (1..=params.ks_level).map(move |j| gadget::<T>(params.ks_base_log, j).mul_int(s))
+            })
+            .map(|plain| encryptor.encrypt(plain))
+            .collect();
+        Ok(KeySwitchKey { keys })
+    }
+
+    /// GLWE encryption of zero: masks uniform, body Σ a_i·s_i + e
+    fn encrypt_glwe_zero<T: Torus>(&self, key_fourier: &[Vec<Complex64>]) -> GlweCiphertext<T> {
+        let params = &self.context.params;
+        let n = params.poly_degree;
+        let mut body: Vec<T> = sample_torus_gaussian(n, params.glwe_noise_std);
+        let mut polys: Vec<Vec<T>> = Vec::with_capacity(params.glwe_dimension + 1);
+        for s in key_fourier {
+            let a: Vec<T> = sample_torus_uniform(n);
+            let product = mul_binary_exact(&self.context.fft, &a, s);
+            body.iter_mut().zip(product).for_each(|(b, p)| *b = b.wrapping_add(p));
+            polys.push(a);
+        }
+        polys.push(body);
+        GlweCiphertext { polys }
+    }
+
+    fn encrypt_ggsw<T: Torus>(&self, mu: i64, key_fourier: &[Vec<Complex64>]) -> GgswCiphertext<T> {
+        let params = &self.context.params;
+        let mut rows = Vec::with_capacity((params.glwe_dimension + 1) * params.pbs_level);
+        for i in 0..=params.glwe_dimension {
+            for j in 1..=params.pbs_level {
+                let mut row = self.encrypt_glwe_zero::<T>(key_fourier);
+                let g = gadget::<T>(params.pbs_base_log, j).mul_int(mu);
+                row.polys[i][0] = row.polys[i][0].wrapping_add(g);
+                rows.push(row.polys.iter().map(|p| torus_to_fourier(&self.context.fft, p)).collect());
+            }
+        }
+        GgswCiphertext { rows, _torus: PhantomData }
+    }
+}
+
+// ==================== ENCRYPTION ====================
+
+/// Secret-key LWE encryption
+pub struct Encryptor {
+    context: TFHEContext,
+    secret_key: LweSecretKey,
+}
+
+impl Encryptor {
+    pub fn new(context: &TFHEContext, secret_key: &LweSecretKey) -> Self {
+        Self { context: context.clone(), secret_key: secret_key.clone() }
+    }
+
+    /// (a, <a, s> + m + e) with uniform a
+    pub fn encrypt<T: Torus>(&self, plaintext: T) -> LweCiphertext<T> {
+        let mask: Vec<T> = sample_torus_uniform(self.secret_key.dimension());
+        let e = sample_torus_gaussian::<T>(1, self.context.params.lwe_noise_std)[0];
+        let body = dot(&mask, &self.secret_key.bits).wrapping_add(plaintext).wrapping_add(e);
+        LweCiphertext { mask, body }
+    }
+
+    /// Encrypts m modulo `message_modulus` with a padding bit
+    pub fn encrypt_message<T: Torus>(&self, m: u64, message_modulus: u64) -> LweCiphertext<T> {
+        self.encrypt(encode_message(m, message_modulus))
+    }
+}
+
+fn dot<T: Torus>(mask: &[T], key: &[i64]) -> T {
+    mask.iter().zip(key)
+        .filter(|(_, &s)| s != 0)
+        .fold(T::zero(), |acc, (&a, &s)| acc.wrapping_add(a.mul_int(s)))
+}
+
+pub struct Decryptor {
+    secret_key: LweSecretKey,
+}
+
+impl Decryptor {
+    /// `secret_key` is the LWE key, or `GlweSecretKey::as_lwe_key` for
+    /// ciphertexts straight out of sample extraction
+    pub fn new(_context: &TFHEContext, secret_key: &LweSecretKey) -> Self {
+        Self { secret_key: secret_key.clone() }
+    }
+
+    /// b - <a, s> = m + e
+    pub fn phase<T: Torus>(&self, ct: &LweCiphertext<T>) -> T {
+        assert_eq!(ct.dimension(), self.secret_key.dimension(), "ciphertext and key dimensions differ");
+        ct.body.wrapping_sub(dot(&ct.mask, &self.secret_key.bits))
+    }
+
+    pub fn decrypt_message<T: Torus>(&self, ct: &LweCiphertext<T>, message_modulus: u64) -> u64 {
+        decode_message(self.phase(ct), message_modulus)
+    }
+}
+
+// ==================== BOOTSTRAPPING ====================
+
+/// Lookup-table polynomial for programmable bootstrapping
+#[derive(Clone, Debug)]
+pub struct LookupTable<T> {
+    pub poly: Vec<T>,
+}
+
+impl<T: Torus> LookupTable<T> {
+    /// Splits the phases [0, 1/2) into `boxes` intervals centered at
+    /// i/(2·boxes) and maps the i-th to f(i)
+    pub fn new(context: &TFHEContext, boxes: usize, f: impl Fn(usize) -> T) -> Result<Self, String> {
+        let n = context.params.poly_degree;
+        if boxes == 0 || boxes > n {
+            return Err(format!("{} lookup-table boxes do not fit N = {}", boxes, n));
+        }
+        let width = n / boxes;
+        let values: Vec<T> = (0..boxes).map(&f).collect();
+        // the first half box sits below phase 0 and wraps negacyclically
+        let poly = (0..n)
+            .map(|j| {
+                let i = (j + width / 2) / width;
+                if i < boxes { values[i] } else { values[0].wrapping_neg() }
+            })
+            .collect();
+        Ok(Self { poly })
+    }
+
+    /// m -> f(m) on messages modulo p encoded by `encode_message`
+    pub fn for_message(context: &TFHEContext, message_modulus: u64, f: impl Fn(u64) -> u64) -> Result<Self, String> {
+        Self::new(context, message_modulus as usize, |m| encode_message(f(m as u64), message_modulus))
+    }
+}
+
+/// Bootstrapping and key switching with public evaluation keys
+pub struct Evaluator<T> {
+    context: TFHEContext,
+    bootstrap_key: BootstrapKey<T>,
+    key_switch_key: KeySwitchKey<T>,
+}
+
+impl<T: Torus> Evaluator<T> {
+    pub fn new(context: &TFHEContext, bootstrap_key: &BootstrapKey<T>, key_switch_key: &KeySwitchKey<T>) -> Self {
+        Self {
+            context: context.clone(),
+            bootstrap_key: bootstrap_key.clone(),
+            key_switch_key: key_switch_key.clone(),
+        }
+    }
+
+    /// GGSW(μ) ⊡ GLWE(m) = GLWE(μ·m)
+    pub fn external_product(&self, ggsw: &GgswCiphertext<T>, glwe: &GlweCiphertext<T>) -> GlweCiphertext<T> {
+        let params = &self.context.params;
+        let fft = &self.context.fft;
+        let (base_log, level) = (params.pbs_base_log, params.pbs_level);
+        let n = params.poly_degree;
+
+        let mut acc = vec![vec![Complex64::new(0.0, 0.0); n / 2]; glwe.polys.len()];
+        let mut digits = vec![0i64; level];
+        let mut decomposed = vec![vec![0i64; n]; level];
+        for (i, poly) in glwe.polys.iter().enumerate() {
+            for (k, &x) in poly.iter().enumerate() {
+                decompose(x, base_log, level, &mut digits);
+                for (j, &d) in digits.iter().enumerate() {
+                    decomposed[j][k] = d;
+                }
+            }
+            for (j, digit_poly) in decomposed.iter().enumerate() {
+                let digit_fourier = fft.forward(|k| digit_poly[k] as f64);
+                for (out, row) in acc.iter_mut().zip(&ggsw.rows[i * level + j]) {
+                    for ((o, &d), &r) in out.iter_mut().zip(&digit_fourier).zip(row) {
+                        *o += d * r;
+                    }
+                }
+            }
+        }
+        GlweCiphertext { polys: acc.into_iter().map(|values| fourier_to_torus(fft, values)).collect() }
+    }
+
+    /// c0 if μ = 0, c1 if μ = 1
+    pub fn cmux(&self, ggsw: &GgswCiphertext<T>, c0: &GlweCiphertext<T>, c1: &GlweCiphertext<T>) -> GlweCiphertext<T> {
+        let mut result = self.external_product(ggsw, &c1.sub(c0));
+        result.add_inplace(c0);
+        result
+    }
+
+    /// X^(-φ)·v for the phase φ of `ct` switched to Z_2N
+    pub fn blind_rotate(&self, ct: &LweCiphertext<T>, lut: &LookupTable<T>) -> Result<GlweCiphertext<T>, String> {
+        let params = &self.context.params;
+        let n = params.poly_degree;
+        if ct.dimension() != params.lwe_dimension {
+            return Err(format!(
+                "blind rotation needs an LWE ciphertext of dimension {}, got {}",
+                params.lwe_dimension, ct.dimension()
+            ));
+        }
+        if lut.poly.len() != n {
+            return Err(format!("lookup table has {} coefficients, expected {}", lut.poly.len(), n));
+        }
+        let switch = |x: T| -> usize {
+            let shift = T::BITS - (2 * n).trailing_zeros();
+            (((x.to_u64() as u128 + (1u128 << (shift - 1))) >> shift) % (2 * n) as u128) as usize
+        };
+
+        let b = switch(ct.body);
+        let mut acc = GlweCiphertext::trivial(params.glwe_dimension, mul_monomial(&lut.poly, (2 * n - b) % (2 * n)));
+        for (&a, ggsw) in ct.mask.iter().zip(&self.bootstrap_key.ggsw) {
+            let a = switch(a);
+            if a != 0 {
+                acc = self.cmux(ggsw, &acc, &acc.mul_monomial(a));
+            }
+        }
+        Ok(acc)
+    }
+
+    /// LWE encryption of v_φ under the flattened GLWE key
+    pub fn bootstrap(&self, ct: &LweCiphertext<T>, lut: &LookupTable<T>) -> Result<LweCiphertext<T>, String> {
+        Ok(self.blind_rotate(ct, lut)?.sample_extract())
+    }
+
+    /// Switches from the flattened GLWE key back to the LWE key
+    pub fn key_switch(&self, ct: &LweCiphertext<T>) -> Result<LweCiphertext<T>, String> {
+        let params = &self.context.params;
+        let level = params.ks_level;
+        if ct.dimension() != self.context.extracted_dimension() {
+            return Err(format!(
+                "key switching needs an LWE ciphertext of dimension {}, got {}",
+                self.context.extracted_dimension(), ct.dimension()
+            ));
+        }
+        let mut result = LweCiphertext::trivial(params.lwe_dimension, ct.body);
+        let mut digits = vec![0i64; level];
+        for (i, &a) in ct.mask.iter().enumerate() {
+            decompose(a, params.ks_base_log, level, &mut digits);
+            for (j, &d) in digits.iter().enumerate().filter(|(_, &d)| d != 0) {
+                let key = &self.key_switch_key.keys[i * level + j];
+                for (r, &k) in result.mask.iter_mut().zip(&key.mask) {
+                    *r = r.wrapping_sub(k.mul_int(d));
+                }
+                result.body = result.body.wrapping_sub(key.body.mul_int(d));
+            }
+        }
+        Ok(result)
+    }
+
+    /// Bootstrap and key switch: an LWE encryption of v_φ under the LWE key
+    pub fn programmable_bootstrap(&self, ct: &LweCiphertext<T>, lut: &LookupTable<T>) -> Result<LweCiphertext<T>, String> {
+        self.key_switch(&self.bootstrap(ct, lut)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Negacyclic schoolbook product of a torus and an integer polynomial
+    fn schoolbook<T: Torus>(a: &[T], b: &[i64]) -> Vec<T> {
+        let n = a.len();
+        let mut result = vec![T::zero(); n];
+        for (i, &x) in a.iter().enumerate() {
+            for (j, &y) in b.iter().enumerate() {
+                let p = x.mul_int(y);
+                if i + j < n {
+                    result[i + j] = result[i + j].wrapping_add(p);
+                } else {
+                    result[i + j - n] = result[i + j - n].wrapping_sub(p);
+                }
+            }
+        }
+        result
+    }
+
+    fn small_params() -> TFHEParameters {
+        TFHEParameters::new(16, 2, 256, 2f64.powi(-20), 2f64.powi(-30)).unwrap()
+    }
+
+    fn glwe_phase<T: Torus>(context: &TFHEContext, key: &GlweSecretKey, ct: &GlweCiphertext<T>) -> Vec<T> {
+        let (body, masks) = ct.polys.split_last().unwrap();
+        let mut phase = body.clone();
+        for (a, s) in masks.iter().zip(&key.polys) {
+            let s_fourier = context.fft.forward(|j| s[j] as f64);
+            let product = mul_binary_exact(&context.fft, a, &s_fourier);
+            phase.iter_mut().zip(product).for_each(|(p, x)| *p = p.wrapping_sub(x));
+        }
+        phase
+    }
+
+    #[test]
+    fn fft_products_match_schoolbook() {
+        let mut rng = rand::thread_rng();
+        for n in [8, 64, 1024] {
+            let fft = NegacyclicFft::new(n);
+            let a: Vec<u64> = sample_torus_uniform(n);
+            let s = sample_binary(n);
+            let s_fourier = fft.forward(|j| s[j] as f64);
+            assert_eq!(mul_binary_exact(&fft, &a, &s_fourier), schoolbook(&a, &s));
+
+            let a32: Vec<u32> = sample_torus_uniform(n);
+            assert_eq!(mul_binary_exact(&fft, &a32, &s_fourier), schoolbook(&a32, &s));
+
+            // Small integer products come back exactly after rounding
+            let x: Vec<i64> = (0..n).map(|_| rng.gen_range(-1000..=1000)).collect();
+            let y: Vec<i64> = (0..n).map(|_| rng.gen_range(-1000..=1000)).collect();
+            let mut values = fft.forward(|j| x[j] as f64);
+            values.iter_mut().zip(fft.forward(|j| y[j] as f64)).for_each(|(v, w)| *v *= w);
+            let x_torus: Vec<u64> = x.iter().map(|&v| v as u64).collect();
+            assert_eq!(fourier_to_torus::<u64>(&fft, values), schoolbook(&x_torus, &y));
+
+            assert_eq!(mul_monomial(&a, 1), schoolbook(&a, &(0..n).map(|j| (j == 1) as i64).collect::<Vec<_>>()));
+            let a_neg: Vec<u64> = a.iter().map(|x| x.wrapping_neg()).collect();
+            assert_eq!(mul_monomial(&a, n), a_neg);
+        }
+    }
+
+    #[test]
+    fn cmux_selects_by_the_encrypted_bit() {
+        let context = TFHEContext::new(small_params());
+        let keygen = KeyGenerator::new(&context);
+        let glwe_key = keygen.glwe_secret_key();
+        let key_fourier: Vec<Vec<Complex64>> = glwe_key.polys.iter()
+            .map(|s| context.fft.forward(|j| s[j] as f64))
+            .collect();
+        let evaluator = Evaluator::new(
+            &context,
+            &keygen.bootstrap_key::<u64>().unwrap(),
+            &keygen.key_switch_key::<u64>().unwrap(),
+        );
+
+        let mut rng = rand::thread_rng();
+        let p = 16;
+        let n = context.params.poly_degree;
+        let encrypt = |messages: &[u64]| {
+            let mut ct = keygen.encrypt_glwe_zero::<u64>(&key_fourier);
+            let body = ct.polys.last_mut().unwrap();
+            body.iter_mut().zip(messages).for_each(|(b, &m)| *b = b.wrapping_add(encode_message(m, p)));
+            ct
+        };
+        let m0: Vec<u64> = (0..n).map(|_| rng.gen_range(0..p)).collect();
+        let m1: Vec<u64> = (0..n).map(|_| rng.gen_range(0..p)).collect();
+        let (c0, c1) = (encrypt(&m0), encrypt(&m1));
+
+        for (bit, want) in [(0, &m0), (1, &m1)] {
+            let ggsw = keygen.encrypt_ggsw::<u64>(bit, &key_fourier);
+            let selected = evaluator.cmux(&ggsw, &c0, &c1);
+            let decoded: Vec<u64> = glwe_phase(&context, &glwe_key, &selected).into_iter()
+                .map(|x| decode_message(x, p))
+                .collect();
+            assert_eq!(&decoded, want);
+        }
+    }
+
+    fn check_pbs<T: Torus>(params: TFHEParameters, p: u64) {
+        let context = TFHEContext::new(params);
+        let keygen = KeyGenerator::new(&context);
+        let encryptor = Encryptor::new(&context, &keygen.lwe_secret_key());
+        let decryptor = Decryptor::new(&context, &keygen.lwe_secret_key());
+        let extracted = Decryptor::new(&context, &keygen.glwe_secret_key().as_lwe_key());
+        let evaluator = Evaluator::new(
+            &context,
+            &keygen.bootstrap_key::<T>().unwrap(),
+            &keygen.key_switch_key::<T>().unwrap(),
+        );
+
+        let f = |m: u64| (m * m + 1) % p;
+        let identity = LookupTable::for_message(&context, p, |m| m).unwrap();
+        let lut = LookupTable::for_message(&context, p, f).unwrap();
+        for m in 0..p {
+            let ct = encryptor.encrypt_message::<T>(m, p);
+            assert_eq!(decryptor.decrypt_message(&evaluator.programmable_bootstrap(&ct, &identity).unwrap(), p), m);
+
+            let bootstrapped = evaluator.bootstrap(&ct, &lut).unwrap();
+            assert_eq!(bootstrapped.dimension(), context.extracted_dimension());
+            assert_eq!(extracted.decrypt_message(&bootstrapped, p), f(m));
+            let switched = evaluator.key_switch(&bootstrapped).unwrap();
+            assert_eq!(switched.dimension(), context.params.lwe_dimension);
+            assert_eq!(decryptor.decrypt_message(&switched, p), f(m), "{}-bit torus, m = {}", T::BITS, m);
+
+            // The output is fresh enough to bootstrap again
+            let again = evaluator.programmable_bootstrap(&switched, &lut).unwrap();
+            assert_eq!(decryptor.decrypt_message(&again, p), f(f(m)));
+        }
+    }
+
+    #[test]
+    fn programmable_bootstrapping_u32() {
+        check_pbs::<u32>(TFHEParameters::new(630, 1, 1024, 2f64.powi(-15), 2f64.powi(-25)).unwrap(), 4);
+    }
+
+    #[test]
+    fn programmable_bootstrapping_u64() {
+        let params = TFHEParameters::new(742, 1, 2048, 7.0693e-6, 2.9404e-16).unwrap()
+            .with_pbs_decomposition(23, 1)
+            .with_ks_decomposition(3, 5);
+        check_pbs::<u64>(params, 16);
+    }
+
+    #[test]
+    fn dimension_and_parameter_errors() {
+        let context = TFHEContext::new(small_params());
+        let keygen = KeyGenerator::new(&context);
+        let encryptor = Encryptor::new(&context, &keygen.lwe_secret_key());
+        let evaluator = Evaluator::new(
+            &context,
+            &keygen.bootstrap_key::<u64>().unwrap(),
+            &keygen.key_switch_key::<u64>().unwrap(),
+        );
+        let lut = LookupTable::for_message(&context, 4, |m| m).unwrap();
+
+        let small = encryptor.encrypt_message::<u64>(1, 4);
+        assert!(evaluator.key_switch(&small).is_err());
+        let extracted = evaluator.bootstrap(&small, &lut).unwrap();
+        assert!(evaluator.key_switch(&extracted).is_ok());
+        assert!(evaluator.blind_rotate(&extracted, &lut).is_err());
+        let short = LookupTable { poly: vec![0u64; 16] };
+        assert!(evaluator.blind_rotate(&small, &short).is_err());
+
+        assert!(LookupTable::<u64>::for_message(&context, 0, |m| m).is_err());
+        assert!(LookupTable::<u64>::for_message(&context, 512, |m| m).is_err());
+        assert!(TFHEParameters::new(0, 1, 256, 1e-5, 1e-9).is_err());
+        assert!(TFHEParameters::new(16, 1, 100, 1e-5, 1e-9).is_err());
+        assert!(TFHEParameters::new(16, 1, 256, 1.5, 1e-9).is_err());
+        let wide = small_params().with_pbs_decomposition(20, 2);
+        let keygen = KeyGenerator::new(&TFHEContext::new(wide));
+        assert!(keygen.bootstrap_key::<u32>().is_err());
+        assert!(keygen.bootstrap_key::<u64>().is_ok());
+    }
+
+    #[test]
+    fn linear_operations() {
+        let context = TFHEContext::new(small_params());
+        let keygen = KeyGenerator::new(&context);
+        let encryptor = Encryptor::new(&context, &keygen.lwe_secret_key());
+        let decryptor = Decryptor::new(&context, &keygen.lwe_secret_key());
+        let a = encryptor.encrypt_message::<u64>(3, 16);
+        let b = encryptor.encrypt_message::<u64>(6, 16);
+        assert_eq!(decryptor.decrypt_message(&a.add(&b), 16), 9);
+        assert_eq!(decryptor.decrypt_message(&b.sub(&a), 16), 3);
+        assert_eq!(decryptor.decrypt_message(&a.mul_scalar(3), 16), 9);
+        assert_eq!(decryptor.decrypt_message(&a.negate().add_constant(encode_message(5, 16)), 16), 2);
+        let trivial = LweCiphertext::trivial(16, encode_message::<u64>(7, 16));
+        assert_eq!(decryptor.decrypt_message(&trivial, 16), 7);
+    }
+}
@@ -0,0 +1,190 @@
+//! Boolean gates on TFHE (gate bootstrapping)
+//! Every binary gate is one linear combination and one bootstrap
+//!
+//! A bit is encrypted as ±1/8 on the torus (true = +1/8). A gate adds or
+//! subtracts its inputs, shifts by a constant so that the result lands
+//! in (0, 1/2) for true and (-1/2, 0) for false, and bootstraps with the
+//! constant lookup table 1/8, which the negacyclic wrap turns into -1/8
+//! for the negative half:
+//!
+//!   AND  -1/8 + a + b        NAND  1/8 - a - b
+//!   OR    1/8 + a + b        NOR  -1/8 - a - b
+//!   XOR   1/4 + 2(a + b)     XNOR -1/4 - 2(a + b)
+//!
+//! The output carries the fresh noise of the bootstrap and key switch, so
+//! circuits of any depth evaluate correctly. NOT negates without noise
+//! and needs no bootstrap. MUX(c, a, b) blind-rotates AND(c, a) and
+//! AND(NOT c, b) and key switches their sum 1/8 + u1 + u2 once.
+//!
+//! Parameter sets (32-bit torus, about 128 bits of security by the
+//! lattice estimates their authors published; decryption failure well
+//! below 2^-40 per gate):
+//! - `boolean_default_128`: n = 722, k = 2, N = 512 (TFHE-rs defaults)
+//! - `boolean_tfhe_lib_128`: n = 630, k = 1, N = 1024 (TFHE library)
+
+use super::tfhe::{
+    BootstrapKey, Decryptor, Encryptor, Evaluator, KeySwitchKey, LookupTable, LweCiphertext,
+    TFHEContext, TFHEParameters, Torus,
+};
+
+impl TFHEParameters {
+    pub fn boolean_default_128() -> Self {
+        TFHEParameters::new(722, 2, 512, 0.000013071021089943935, 0.00000004990272175010415)
+            .expect("valid boolean parameters")
+            .with_pbs_decomposition(6, 3)
+            .with_ks_decomposition(3, 4)
+    }
+
+    pub fn boolean_tfhe_lib_128() -> Self {
+        TFHEParameters::new(630, 1, 1024, 2f64.powi(-15), 2f64.powi(-25))
+            .expect("valid boolean parameters")
+            .with_pbs_decomposition(7, 3)
+            .with_ks_decomposition(2, 8)
+    }
+}
+
+/// Torus encoding of a bit
+fn encode_bool<T: Torus>(value: bool) -> T {
+    let eighth = T::from_fraction(0.125);
+    if value { eighth } else { eighth.wrapping_neg() }
+}
+
+impl Encryptor {
+    pub fn encrypt_bool<T: Torus>(&self, value: bool) -> LweCiphertext<T> {
+        self.encrypt(encode_bool(value))
+    }
+}
+
+impl Decryptor {
+    /// Sign of the phase: true in [0, 1/2)
+    pub fn decrypt_bool<T: Torus>(&self, ct: &LweCiphertext<T>) -> bool {
+        self.phase(ct).to_fraction() >= 0.0
+    }
+}
+
+/// Boolean gates with public evaluation keys
+pub struct BooleanEvaluator<T> {
+    evaluator: Evaluator<T>,
+    /// 1/8 in every coefficient: phases in [0, 1/2) map to 1/8, the rest to -1/8
+    sign: LookupTable<T>,
+    lwe_dimension: usize,
+}
+
+impl<T: Torus> BooleanEvaluator<T> {
+    pub fn new(context: &TFHEContext, bootstrap_key: &BootstrapKey<T>, key_switch_key: &KeySwitchKey<T>) -> Self {
+        Self {
+            evaluator: Evaluator::new(context, bootstrap_key, key_switch_key),
+            sign: LookupTable { poly: vec![encode_bool(true); context.params.poly_degree] },
+            lwe_dimension: context.params.lwe_dimension,
+        }
+    }
+
+    /// Noiseless encryption of a public bit
+    pub fn trivial(&self, value: bool) -> LweCiphertext<T> {
+        LweCiphertext::trivial(self.lwe_dimension, encode_bool(value))
+    }
+
+    pub fn and(&self, a: &LweCiphertext<T>, b: &LweCiphertext<T>) -> Result<LweCiphertext<T>, String> {
+        self.gate(-0.125, &a.add(b))
+    }
+
+    pub fn or(&self, a: &LweCiphertext<T>, b: &LweCiphertext<T>) -> Result<LweCiphertext<T>, String> {
+        self.gate(0.125, &a.add(b))
+    }
+
+    pub fn xor(&self, a: &LweCiphertext<T>, b: &LweCiphertext<T>) -> Result<LweCiphertext<T>, String> {
+        self.gate(0.25, &a.add(b).mul_scalar(2))
+    }
+
+    pub fn nand(&self, a: &LweCiphertext<T>, b: &LweCiphertext<T>) -> Result<LweCiphertext<T>, String> {
+        self.gate(0.125, &a.add(b).negate())
+    }
+
+    pub fn nor(&self, a: &LweCiphertext<T>, b: &LweCiphertext<T>) -> Result<LweCiphertext<T>, String> {
+        self.gate(-0.125, &a.add(b).negate())
+    }
+
+    pub fn xnor(&self, a: &LweCiphertext<T>, b: &LweCiphertext<T>) -> Result<LweCiphertext<T>, String> {
+        self.gate(-0.25, &a.add(b).mul_scalar(-2))
+    }
+
+    /// Negation; no bootstrap and no added noise
+    pub fn not(&self, a: &LweCiphertext<T>) -> LweCiphertext<T> {
+        a.negate()
+    }
+
+    /// a if c else b: two blind rotations, one key switch
+    pub fn mux(&self, c: &LweCiphertext<T>, a: &LweCiphertext<T>, b: &LweCiphertext<T>) -> Result<LweCiphertext<T>, String> {
+        let eighth = T::from_fraction(0.125);
+        let c_and_a = self.evaluator.bootstrap(&c.add(a).add_constant(eighth.wrapping_neg()), &self.sign)?;
+        let not_c_and_b = self.evaluator.bootstrap(&b.sub(c).add_constant(eighth.wrapping_neg()), &self.sign)?;
+        self.evaluator.key_switch(&c_and_a.add(&not_c_and_b).add_constant(eighth))
+    }
+
+    /// Bootstraps `constant + combination` to ±1/8
+    fn gate(&self, constant: f64, combination: &LweCiphertext<T>) -> Result<LweCiphertext<T>, String> {
+        let shifted = combination.add_constant(T::from_fraction(constant));
+        self.evaluator.programmable_bootstrap(&shifted, &self.sign)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fhe::tfhe::KeyGenerator;
+
+    fn check_truth_tables(params: TFHEParameters) {
+        let context = TFHEContext::new(params);
+        let keygen = KeyGenerator::new(&context);
+        let encryptor = Encryptor::new(&context, &keygen.lwe_secret_key());
+        let decryptor = Decryptor::new(&context, &keygen.lwe_secret_key());
+        let ev = BooleanEvaluator::new(&context, &keygen.bootstrap_key().unwrap(), &keygen.key_switch_key().unwrap());
+        let decrypt = |ct: Result<LweCiphertext<u32>, String>| decryptor.decrypt_bool(&ct.unwrap());
+        for a in [false, true] {
+            let ca = encryptor.encrypt_bool::<u32>(a);
+            assert_eq!(decryptor.decrypt_bool(&ev.not(&ca)), !a);
+            for b in [false, true] {
+                let cb = encryptor.encrypt_bool::<u32>(b);
+                assert_eq!(decrypt(ev.and(&ca, &cb)), a & b, "and({}, {})", a, b);
+                assert_eq!(decrypt(ev.or(&ca, &cb)), a | b, "or({}, {})", a, b);
+                assert_eq!(decrypt(ev.xor(&ca, &cb)), a ^ b, "xor({}, {})", a, b);
+                assert_eq!(decrypt(ev.nand(&ca, &cb)), !(a & b), "nand({}, {})", a, b);
+                assert_eq!(decrypt(ev.nor(&ca, &cb)), !(a | b), "nor({}, {})", a, b);
+                assert_eq!(decrypt(ev.xnor(&ca, &cb)), !(a ^ b), "xnor({}, {})", a, b);
+                assert_eq!(decrypt(ev.and(&ev.trivial(a), &cb)), a & b);
+                for c in [false, true] {
+                    let cc = encryptor.encrypt_bool::<u32>(c);
+                    assert_eq!(decrypt(ev.mux(&cc, &ca, &cb)), if c { a } else { b }, "mux({}, {}, {})", c, a, b);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn truth_tables_default_parameters() {
+        check_truth_tables(TFHEParameters::boolean_default_128());
+    }
+
+    #[test]
+    fn truth_tables_tfhe_lib_parameters() {
+        check_truth_tables(TFHEParameters::boolean_tfhe_lib_128());
+    }
+
+    #[test]
+    fn deep_circuits_stay_correct() {
+        let context = TFHEContext::new(TFHEParameters::boolean_default_128());
+        let keygen = KeyGenerator::new(&context);
+        let encryptor = Encryptor::new(&context, &keygen.lwe_secret_key());
+        let decryptor = Decryptor::new(&context, &keygen.lwe_secret_key());
+        let ev = BooleanEvaluator::new(&context, &keygen.bootstrap_key().unwrap(), &keygen.key_switch_key().unwrap());
+        let mut ct = encryptor.encrypt_bool::<u32>(true);
+        let mut plain = true;
+        for i in 0..40 {
+            // XOR doubles its input noise, the worst case among the gates
+            let other = encryptor.encrypt_bool::<u32>(i % 3 == 0);
+            ct = ev.xor(&ct, &other).unwrap();
+            plain ^= i % 3 == 0;
+            assert_eq!(decryptor.decrypt_bool(&ct), plain, "gate {}", i);
+        }
+    }
+}
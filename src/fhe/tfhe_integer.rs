@@ -0,0 +1,753 @@
+//! Radix encrypted integers on TFHE (u8, u16, u32 with wraparound)
+//! Every operator is exact modulo 2^BITS, like `wrapping_*` on the
+//! plaintext type
+//!
+//! An integer is split into 2-bit blocks, least significant first, and
+//! each block is an LWE encryption of a value in [0, 16) on the 64-bit
+//! torus: 2 message bits and 2 carry bits above a padding bit. Linear
+//! operations add blocks without bootstrapping and let the carries fill
+//! up; a programmable bootstrap per block then extracts the message
+//! (v mod 4) and the carry (v / 4), which is how carries are propagated
+//! and how every non-linear block function is evaluated. Functions of
+//! two blocks bootstrap the packed value 4·x + y.
+//!
+//! Blocks live under the flattened GLWE key (dimension k·N): each
+//! bootstrap key switches first and then blind-rotates, so the noise
+//! entering the blind rotation is that of the key switch and the modulus
+//! switch, independent of the linear combination that preceded it. Build
+//! the Encryptor and Decryptor from `GlweSecretKey::as_lwe_key`.
+//!
+//! Carry propagation:
+//! - Sequential: one carry bootstrap per block, n rounds for n blocks
+//! - Parallel: each block sum is classified as generating (>= 4),
+//!   propagating (= 3) or neither, the classes are combined by a
+//!   Hillis–Steele prefix in log2(n) rounds, and all bootstraps of a
+//!   round run on separate threads (sequentially on wasm32)
+//!
+//! Multiplication forms the partial products a_i·b_j of blocks with
+//! i + j < n by bivariate bootstraps (low and high digit), sums them in
+//! carry space with a carry-save reduction and propagates once.
+//! Comparisons classify each block pair as less, equal or greater and
+//! combine the classes from the most significant block down by a tree.
+
+use super::tfhe::{
+    encode_message, BootstrapKey, Decryptor, Encryptor, Evaluator, KeySwitchKey, LookupTable,
+    LweCiphertext, TFHEContext, TFHEParameters,
+};
+
+/// Message values per block (2 bits)
+pub const MESSAGE_MODULUS: u64 = 4;
+/// Carry values per block (2 bits)
+pub const CARRY_MODULUS: u64 = 4;
+/// Values a block can hold before the padding bit is reached
+const BLOCK_MODULUS: u64 = MESSAGE_MODULUS * CARRY_MODULUS;
+
+/// Carry-propagation classes of a block sum
+const NONE: u64 = 0;
+const GENERATE: u64 = 1;
+const PROPAGATE: u64 = 2;
+
+/// Comparison classes of a block pair
+const LESS: u64 = 0;
+const EQUAL: u64 = 1;
+const GREATER: u64 = 2;
+
+impl TFHEParameters {
+    /// 2 message and 2 carry bits per block on the 64-bit torus
+    /// (TFHE-rs PARAM_MESSAGE_2_CARRY_2_KS_PBS), about 128 bits of
+    /// security; the key switch and modulus switch leave about 2^-9 of
+    /// noise against the 2^-6 half-box of a 16-value table
+    pub fn integer_message_2_carry_2() -> Self {
+        TFHEParameters::new(834, 1, 2048, 0.0000035539902359442825, 0.000000000000002845267479601915)
+            .expect("valid integer parameters")
+            .with_pbs_decomposition(23, 1)
+            .with_ks_decomposition(3, 5)
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CarryPropagation {
+    #[default]
+    Sequential,
+    Parallel,
+}
+
+/// Unsigned BITS-bit integer as BITS/2 encrypted blocks, least
+/// significant first
+#[derive(Clone, Debug)]
+pub struct EncryptedUint<const BITS: u32> {
+    pub blocks: Vec<LweCiphertext<u64>>,
+}
+
+pub type EncryptedU8 = EncryptedUint<8>;
+pub type EncryptedU16 = EncryptedUint<16>;
+pub type EncryptedU32 = EncryptedUint<32>;
+
+impl<const BITS: u32> EncryptedUint<BITS> {
+    pub const BLOCKS: usize = BITS as usize / 2;
+
+    fn from_blocks(blocks: Vec<LweCiphertext<u64>>) -> Self {
+        debug_assert_eq!(blocks.len(), Self::BLOCKS);
+        Self { blocks }
+    }
+}
+
+/// Encrypted comparison result: one block holding 0 or 1
+#[derive(Clone, Debug)]
+pub struct EncryptedBool {
+    pub block: LweCiphertext<u64>,
+}
+
+/// Base-4 digits of the low `blocks` blocks of `value`
+fn digits(value: u64, blocks: usize) -> impl Iterator<Item = u64> {
+    (0..blocks).map(move |i| if 2 * i < 64 { (value >> (2 * i)) % MESSAGE_MODULUS } else { 0 })
+}
+
+fn mask(bits: u32) -> u64 {
+    if bits >= 64 { u64::MAX } else { (1u64 << bits) - 1 }
+}
+
+impl Encryptor {
+    /// Encrypts value mod 2^BITS; the key must be the flattened GLWE key
+    pub fn encrypt_uint<const BITS: u32>(&self, value: u64) -> EncryptedUint<BITS> {
+        let blocks = digits(value, EncryptedUint::<BITS>::BLOCKS)
+            .map(|d| self.encrypt_message(d, BLOCK_MODULUS))
+            .collect();
+        EncryptedUint::from_blocks(blocks)
+    }
+
+    pub fn encrypt_bool_block(&self, value: bool) -> EncryptedBool {
+        EncryptedBool { block: self.encrypt_message(value as u64, BLOCK_MODULUS) }
+    }
+}
+
+impl Decryptor {
+    /// Σ (block mod 4)·4^i mod 2^BITS; the carries of evaluated blocks are
+    /// always empty
+    pub fn decrypt_uint<const BITS: u32>(&self, ct: &EncryptedUint<BITS>) -> u64 {
+        let value = ct.blocks.iter().enumerate().fold(0u64, |acc, (i, block)| {
+            let d = self.decrypt_message(block, BLOCK_MODULUS) % MESSAGE_MODULUS;
+            acc | d.checked_shl(2 * i as u32).unwrap_or(0)
+        });
+        value & mask(BITS)
+    }
+
+    pub fn decrypt_bool_block(&self, ct: &EncryptedBool) -> bool {
+        self.decrypt_message(&ct.block, BLOCK_MODULUS) % MESSAGE_MODULUS == 1
+    }
+}
+
+/// Integer arithmetic with public evaluation keys
+pub struct IntegerEvaluator {
+    context: TFHEContext,
+    evaluator: Evaluator<u64>,
+    carry_propagation: CarryPropagation,
+}
+
+impl IntegerEvaluator {
+    pub fn new(context: &TFHEContext, bootstrap_key: &BootstrapKey<u64>, key_switch_key: &KeySwitchKey<u64>) -> Self {
+        Self {
+            context: context.clone(),
+            evaluator: Evaluator::new(context, bootstrap_key, key_switch_key),
+            carry_propagation: CarryPropagation::Sequential,
+        }
+    }
+
+    pub fn with_carry_propagation(mut self, carry_propagation: CarryPropagation) -> Self {
+        self.carry_propagation = carry_propagation;
+        self
+    }
+
+    /// Noiseless encryption of a public value mod 2^BITS
+    pub fn trivial<const BITS: u32>(&self, value: u64) -> EncryptedUint<BITS> {
+        let blocks = digits(value, EncryptedUint::<BITS>::BLOCKS).map(|d| self.trivial_block(d)).collect();
+        EncryptedUint::from_blocks(blocks)
+    }
+
+    // ==================== ARITHMETIC ====================
+
+    pub fn add<const BITS: u32>(&self, a: &EncryptedUint<BITS>, b: &EncryptedUint<BITS>) -> Result<EncryptedUint<BITS>, String> {
+        let sums = a.blocks.iter().zip(&b.blocks).map(|(x, y)| x.add(y)).collect();
+        Ok(EncryptedUint::from_blocks(self.propagate(sums)?))
+    }
+
+    /// a + !b + 1, with the final carry dropped
+    pub fn sub<const BITS: u32>(&self, a: &EncryptedUint<BITS>, b: &EncryptedUint<BITS>) -> Result<EncryptedUint<BITS>, String> {
+        let sums = a.blocks.iter().zip(&b.blocks).enumerate()
+            .map(|(i, (x, y))| {
+                let complement = MESSAGE_MODULUS - 1 + (i == 0) as u64;
+                x.sub(y).add_constant(self.encode(complement))
+            })
+            .collect();
+        Ok(EncryptedUint::from_blocks(self.propagate(sums)?))
+    }
+
+    pub fn scalar_add<const BITS: u32>(&self, a: &EncryptedUint<BITS>, k: u64) -> Result<EncryptedUint<BITS>, String> {
+        let sums = a.blocks.iter().zip(digits(k, a.blocks.len()))
+            .map(|(x, d)| x.add_constant(self.encode(d)))
+            .collect();
+        Ok(EncryptedUint::from_blocks(self.propagate(sums)?))
+    }
+
+    /// Σ_j d_j·4^j·a over the base-4 digits d_j of k
+    pub fn scalar_mul<const BITS: u32>(&self, a: &EncryptedUint<BITS>, k: u64) -> Result<EncryptedUint<BITS>, String> {
+        let n = a.blocks.len();
+        let mut jobs = Vec::new();
+        let mut shifts = Vec::new();
+        let mut tables = Vec::new();
+        for (j, d) in digits(k, n).enumerate().filter(|&(_, d)| d != 0) {
+            tables.push((
+                self.lut(move |x| (x % MESSAGE_MODULUS) * d % MESSAGE_MODULUS)?,
+                self.lut(move |x| (x % MESSAGE_MODULUS) * d / MESSAGE_MODULUS)?,
+            ));
+            shifts.push(j);
+        }
+        for (lo, hi) in &tables {
+            jobs.extend(a.blocks.iter().map(|x| (x.clone(), lo)));
+            jobs.extend(a.blocks.iter().map(|x| (x.clone(), hi)));
+        }
+        let products = self.bootstrap_many(jobs)?;
+        let terms = products.chunks(n).zip(shifts.iter().flat_map(|&j| [j, j + 1]))
+            .map(|(blocks, shift)| self.shift_blocks_up(blocks, shift, n))
+            .collect();
+        Ok(EncryptedUint::from_blocks(self.sum_terms(terms, n)?))
+    }
+
+    /// Schoolbook product of the blocks with i + j < n
+    pub fn mul<const BITS: u32>(&self, a: &EncryptedUint<BITS>, b: &EncryptedUint<BITS>) -> Result<EncryptedUint<BITS>, String> {
+        let n = a.blocks.len();
+        let lo = self.bivariate_lut(|x, y| x * y % MESSAGE_MODULUS)?;
+        let hi = self.bivariate_lut(|x, y| x * y / MESSAGE_MODULUS)?;
+        let mut jobs = Vec::new();
+        for j in 0..n {
+            for i in 0..n - j {
+                let packed = self.pack(&a.blocks[i], &b.blocks[j]);
+                if i + j + 1 < n {
+                    jobs.push((packed.clone(), &hi));
+                }
+                jobs.push((packed, &lo));
+            }
+        }
+        let mut products = self.bootstrap_many(jobs)?.into_iter();
+
+        // row j contributes a low-digit term at i + j and a high-digit term at i + j + 1
+        let mut terms = Vec::new();
+        for j in 0..n {
+            let mut low = vec![None; n];
+            let mut high = vec![None; n];
+            for i in 0..n - j {
+                if i + j + 1 < n {
+                    high[i + j + 1] = products.next();
+                }
+                low[i + j] = products.next();
+            }
+            terms.push(low);
+            if j + 1 < n {
+                terms.push(high);
+            }
+        }
+        let terms = terms.into_iter()
+            .map(|row| row.into_iter().map(|b| b.unwrap_or_else(|| self.trivial_block(0))).collect())
+            .collect();
+        Ok(EncryptedUint::from_blocks(self.sum_terms(terms, n)?))
+    }
+
+    // ==================== COMPARISONS ====================
+
+    pub fn eq<const BITS: u32>(&self, a: &EncryptedUint<BITS>, b: &EncryptedUint<BITS>) -> Result<EncryptedBool, String> {
+        self.compare(a, b, |c| c == EQUAL)
+    }
+
+    pub fn ne<const BITS: u32>(&self, a: &EncryptedUint<BITS>, b: &EncryptedUint<BITS>) -> Result<EncryptedBool, String> {
+        self.compare(a, b, |c| c != EQUAL)
+    }
+
+    pub fn lt<const BITS: u32>(&self, a: &EncryptedUint<BITS>, b: &EncryptedUint<BITS>) -> Result<EncryptedBool, String> {
+        self.compare(a, b, |c| c == LESS)
+    }
+
+    pub fn le<const BITS: u32>(&self, a: &EncryptedUint<BITS>, b: &EncryptedUint<BITS>) -> Result<EncryptedBool, String> {
+        self.compare(a, b, |c| c != GREATER)
+    }
+
+    pub fn gt<const BITS: u32>(&self, a: &EncryptedUint<BITS>, b: &EncryptedUint<BITS>) -> Result<EncryptedBool, String> {
+        self.compare(a, b, |c| c == GREATER)
+    }
+
+    pub fn ge<const BITS: u32>(&self, a: &EncryptedUint<BITS>, b: &EncryptedUint<BITS>) -> Result<EncryptedBool, String> {
+        self.compare(a, b, |c| c != LESS)
+    }
+
+    pub fn min<const BITS: u32>(&self, a: &EncryptedUint<BITS>, b: &EncryptedUint<BITS>) -> Result<EncryptedUint<BITS>, String> {
+        let a_greater = self.gt(a, b)?;
+        self.select(&a_greater, b, a)
+    }
+
+    pub fn max<const BITS: u32>(&self, a: &EncryptedUint<BITS>, b: &EncryptedUint<BITS>) -> Result<EncryptedUint<BITS>, String> {
+        let a_greater = self.gt(a, b)?;
+        self.select(&a_greater, a, b)
+    }
+
+    /// a if condition else b, blockwise: f(c, a_i) + g(c, b_i) with one of
+    /// the two zero
+    pub fn select<const BITS: u32>(&self, condition: &EncryptedBool, a: &EncryptedUint<BITS>, b: &EncryptedUint<BITS>) -> Result<EncryptedUint<BITS>, String> {
+        let if_true = self.bivariate_lut(|c, x| if c == 1 { x } else { 0 })?;
+        let if_false = self.bivariate_lut(|c, x| if c == 1 { 0 } else { x })?;
+        let jobs = a.blocks.iter().map(|x| (self.pack(&condition.block, x), &if_true))
+            .chain(b.blocks.iter().map(|x| (self.pack(&condition.block, x), &if_false)))
+            .collect();
+        let selected = self.bootstrap_many(jobs)?;
+        let (from_a, from_b) = selected.split_at(a.blocks.len());
+        Ok(EncryptedUint::from_blocks(from_a.iter().zip(from_b).map(|(x, y)| x.add(y)).collect()))
+    }
+
+    // ==================== SHIFTS AND BITWISE ====================
+
+    /// a << (k mod BITS), like `wrapping_shl`
+    pub fn shl<const BITS: u32>(&self, a: &EncryptedUint<BITS>, k: u32) -> Result<EncryptedUint<BITS>, String> {
+        let k = k % BITS;
+        let mut blocks = a.blocks.clone();
+        if k % 2 == 1 {
+            // 2·a_i splits into a low bit kept in block i and a carry bit for block i + 1
+            let lo = self.lut(|x| 2 * x % MESSAGE_MODULUS)?;
+            let hi = self.lut(|x| 2 * (x % MESSAGE_MODULUS) / MESSAGE_MODULUS)?;
+            let jobs = blocks.iter().map(|x| (x.clone(), &lo))
+                .chain(blocks.iter().map(|x| (x.clone(), &hi)))
+                .collect();
+            let shifted = self.bootstrap_many(jobs)?;
+            let (low, high) = shifted.split_at(blocks.len());
+            blocks = low.iter().enumerate()
+                .map(|(i, x)| if i == 0 { x.clone() } else { x.add(&high[i - 1]) })
+                .collect();
+        }
+        Ok(EncryptedUint::from_blocks(self.shift_blocks_up(&blocks, k as usize / 2, blocks.len())))
+    }
+
+    /// a >> (k mod BITS), like `wrapping_shr`
+    pub fn shr<const BITS: u32>(&self, a: &EncryptedUint<BITS>, k: u32) -> Result<EncryptedUint<BITS>, String> {
+        let k = k % BITS;
+        let n = a.blocks.len();
+        let q = k as usize / 2;
+        let mut blocks: Vec<_> = (0..n)
+            .map(|i| a.blocks.get(i + q).cloned().unwrap_or_else(|| self.trivial_block(0)))
+            .collect();
+        if k % 2 == 1 {
+            let lo = self.lut(|x| (x % MESSAGE_MODULUS) / 2)?;
+            let hi = self.lut(|x| (x % 2) * 2)?;
+            let jobs = blocks.iter().map(|x| (x.clone(), &lo))
+                .chain(blocks.iter().skip(1).map(|x| (x.clone(), &hi)))
+                .collect();
+            let shifted = self.bootstrap_many(jobs)?;
+            let (low, high) = shifted.split_at(n);
+            blocks = low.iter().enumerate()
+                .map(|(i, x)| high.get(i).map_or_else(|| x.clone(), |h| x.add(h)))
+                .collect();
+        }
+        Ok(EncryptedUint::from_blocks(blocks))
+    }
+
+    pub fn bitand<const BITS: u32>(&self, a: &EncryptedUint<BITS>, b: &EncryptedUint<BITS>) -> Result<EncryptedUint<BITS>, String> {
+        self.blockwise(a, b, |x, y| x & y)
+    }
+
+    pub fn bitor<const BITS: u32>(&self, a: &EncryptedUint<BITS>, b: &EncryptedUint<BITS>) -> Result<EncryptedUint<BITS>, String> {
+        self.blockwise(a, b, |x, y| x | y)
+    }
+
+    pub fn bitxor<const BITS: u32>(&self, a: &EncryptedUint<BITS>, b: &EncryptedUint<BITS>) -> Result<EncryptedUint<BITS>, String> {
+        self.blockwise(a, b, |x, y| x ^ y)
+    }
+
+    /// 3 - a_i per block; no bootstrap
+    pub fn bitnot<const BITS: u32>(&self, a: &EncryptedUint<BITS>) -> EncryptedUint<BITS> {
+        let blocks = a.blocks.iter()
+            .map(|x| x.negate().add_constant(self.encode(MESSAGE_MODULUS - 1)))
+            .collect();
+        EncryptedUint::from_blocks(blocks)
+    }
+
+    // ==================== CARRIES ====================
+
+    /// Turns block sums of at most 7 into clean blocks, dropping the carry
+    /// out of the top block
+    fn propagate(&self, sums: Vec<LweCiphertext<u64>>) -> Result<Vec<LweCiphertext<u64>>, String> {
+        match self.carry_propagation {
+            CarryPropagation::Sequential => self.propagate_sequential(sums),
+            CarryPropagation::Parallel => self.propagate_parallel(sums),
+        }
+    }
+
+    fn propagate_sequential(&self, sums: Vec<LweCiphertext<u64>>) -> Result<Vec<LweCiphertext<u64>>, String> {
+        let message = self.lut(|x| x % MESSAGE_MODULUS)?;
+        let carry = self.lut(|x| x / MESSAGE_MODULUS)?;
+        let n = sums.len();
+        let mut result = Vec::with_capacity(n);
+        let mut carry_in: Option<LweCiphertext<u64>> = None;
+        for (i, mut block) in sums.into_iter().enumerate() {
+            if let Some(c) = &carry_in {
+                block.add_inplace(c);
+            }
+            if i + 1 < n {
+                carry_in = Some(self.bootstrap(&block, &carry)?);
+            }
+            result.push(self.bootstrap(&block, &message)?);
+        }
+        Ok(result)
+    }
+
+    /// Kogge-Stone style: block i receives a carry iff the nearest block
+    /// below it that is not PROPAGATE is GENERATE
+    fn propagate_parallel(&self, sums: Vec<LweCiphertext<u64>>) -> Result<Vec<LweCiphertext<u64>>, String> {
+        let n = sums.len();
+        // block 0 has no carry in, so PROPAGATE resolves to NONE there
+        let first = self.lut(|x| if x >= MESSAGE_MODULUS { GENERATE } else { NONE })?;
+        let class = self.lut(|x| match x {
+            x if x >= MESSAGE_MODULUS => GENERATE,
+            x if x == MESSAGE_MODULUS - 1 => PROPAGATE,
+            _ => NONE,
+        })?;
+        let jobs = sums.iter().enumerate()
+            .map(|(i, s)| (s.clone(), if i == 0 { &first } else { &class }))
+            .collect();
+        let mut prefix = self.bootstrap_many(jobs)?;
+
+        let combine = self.bivariate_lut(|high, low| if high == PROPAGATE { low } else { high })?;
+        let mut distance = 1;
+        while distance < n {
+            let jobs = (distance..n).map(|i| (self.pack(&prefix[i], &prefix[i - distance]), &combine)).collect();
+            let combined = self.bootstrap_many(jobs)?;
+            prefix.splice(distance.., combined);
+            distance *= 2;
+        }
+
+        // prefix[i] is now GENERATE (1) or NONE (0): the carry out of block i
+        let message = self.lut(|x| x % MESSAGE_MODULUS)?;
+        let jobs = sums.iter().enumerate()
+            .map(|(i, s)| (if i == 0 { s.clone() } else { s.add(&prefix[i - 1]) }, &message))
+            .collect();
+        self.bootstrap_many(jobs)
+    }
+
+    /// Sums clean radix terms: groups of up to 5 are added in carry space
+    /// (at most 15 per block) and split into message and carry terms until
+    /// two remain, which are added with carry propagation
+    fn sum_terms(&self, mut terms: Vec<Vec<LweCiphertext<u64>>>, n: usize) -> Result<Vec<LweCiphertext<u64>>, String> {
+        let max_terms = ((BLOCK_MODULUS - 1) / (MESSAGE_MODULUS - 1)) as usize;
+        let message = self.lut(|x| x % MESSAGE_MODULUS)?;
+        let carry = self.lut(|x| x / MESSAGE_MODULUS)?;
+        while terms.len() > 2 {
+            let groups: Vec<Vec<LweCiphertext<u64>>> = terms.chunks(max_terms)
+                .map(|group| (0..n).map(|i| self.sum_column(group, i)).collect())
+                .collect();
+            let mut jobs = Vec::new();
+            for group in &groups {
+                jobs.extend(group.iter().map(|x| (x.clone(), &message)));
+                jobs.extend(group[..n - 1].iter().map(|x| (x.clone(), &carry)));
+            }
+            let mut split = self.bootstrap_many(jobs)?.into_iter();
+            terms = Vec::new();
+            for _ in &groups {
+                terms.push(split.by_ref().take(n).collect());
+                let carries: Vec<_> = split.by_ref().take(n - 1).collect();
+                terms.push(self.shift_blocks_up(&carries, 1, n));
+            }
+        }
+        let sums = (0..n).map(|i| self.sum_column(&terms, i)).collect();
+        self.propagate(sums)
+    }
+
+    fn sum_column(&self, terms: &[Vec<LweCiphertext<u64>>], i: usize) -> LweCiphertext<u64> {
+        terms.iter().fold(self.trivial_block(0), |acc, term| acc.add(&term[i]))
+    }
+
+    /// n blocks with blocks[i] at i + shift and zeros elsewhere
+    fn shift_blocks_up(&self, blocks: &[LweCiphertext<u64>], shift: usize, n: usize) -> Vec<LweCiphertext<u64>> {
+        (0..n)
+            .map(|i| i.checked_sub(shift).and_then(|j| blocks.get(j)).cloned())
+            .map(|b| b.unwrap_or_else(|| self.trivial_block(0)))
+            .collect()
+    }
+
+    // ==================== HELPERS ====================
+
+    /// Reduces each block pair to LESS, EQUAL or GREATER, combines the
+    /// classes from the top block down and maps the result through `test`
+    fn compare<const BITS: u32>(&self, a: &EncryptedUint<BITS>, b: &EncryptedUint<BITS>, test: impl Fn(u64) -> bool) -> Result<EncryptedBool, String> {
+        let class = self.bivariate_lut(|x, y| match x.cmp(&y) {
+            std::cmp::Ordering::Less => LESS,
+            std::cmp::Ordering::Equal => EQUAL,
+            std::cmp::Ordering::Greater => GREATER,
+        })?;
+        let jobs = a.blocks.iter().zip(&b.blocks).map(|(x, y)| (self.pack(x, y), &class)).collect();
+        let mut classes = self.bootstrap_many(jobs)?;
+
+        // classes[i] covers a run of blocks; the more significant run decides unless EQUAL
+        let combine = self.bivariate_lut(|high, low| if high == EQUAL { low } else { high })?;
+        while classes.len() > 1 {
+            let jobs: Vec<_> = classes.chunks(2)
+                .filter(|pair| pair.len() == 2)
+                .map(|pair| (self.pack(&pair[1], &pair[0]), &combine))
+                .collect();
+            let odd = (classes.len() % 2 == 1).then(|| classes[classes.len() - 1].clone());
+            classes = self.bootstrap_many(jobs)?;
+            classes.extend(odd);
+        }
+        let result = self.lut(|c| test(c) as u64)?;
+        Ok(EncryptedBool { block: self.bootstrap(&classes[0], &result)? })
+    }
+
+    fn blockwise<const BITS: u32>(&self, a: &EncryptedUint<BITS>, b: &EncryptedUint<BITS>, f: impl Fn(u64, u64) -> u64) -> Result<EncryptedUint<BITS>, String> {
+        let table = self.bivariate_lut(f)?;
+        let jobs = a.blocks.iter().zip(&b.blocks).map(|(x, y)| (self.pack(x, y), &table)).collect();
+        Ok(EncryptedUint::from_blocks(self.bootstrap_many(jobs)?))
+    }
+
+    fn encode(&self, value: u64) -> u64 {
+        encode_message(value, BLOCK_MODULUS)
+    }
+
+    fn trivial_block(&self, value: u64) -> LweCiphertext<u64> {
+        LweCiphertext::trivial(self.context.extracted_dimension(), self.encode(value))
+    }
+
+    fn lut(&self, f: impl Fn(u64) -> u64) -> Result<LookupTable<u64>, String> {
+        LookupTable::for_message(&self.context, BLOCK_MODULUS, |x| f(x) % BLOCK_MODULUS)
+    }
+
+    /// Table of f(x, y) for the packed block 4·x + y of two clean blocks
+    fn bivariate_lut(&self, f: impl Fn(u64, u64) -> u64) -> Result<LookupTable<u64>, String> {
+        self.lut(|packed| f(packed / MESSAGE_MODULUS, packed % MESSAGE_MODULUS))
+    }
+
+    /// 4·x + y; both blocks must be clean
+    fn pack(&self, x: &LweCiphertext<u64>, y: &LweCiphertext<u64>) -> LweCiphertext<u64> {
+        x.mul_scalar(MESSAGE_MODULUS as i64).add(y)
+    }
+
+    /// Key switch, then bootstrap back to the flattened GLWE key
+    fn bootstrap(&self, ct: &LweCiphertext<u64>, lut: &LookupTable<u64>) -> Result<LweCiphertext<u64>, String> {
+        self.evaluator.bootstrap(&self.evaluator.key_switch(ct)?, lut)
+    }
+
+    /// Independent bootstraps, on all cores under parallel carry propagation
+    fn bootstrap_many(&self, jobs: Vec<(LweCiphertext<u64>, &LookupTable<u64>)>) -> Result<Vec<LweCiphertext<u64>>, String> {
+        #[cfg(not(target_arch = "wasm32"))]
+        if self.carry_propagation == CarryPropagation::Parallel && jobs.len() > 1 {
+            let threads = std::thread::available_parallelism().map_or(1, |n| n.get()).min(jobs.len());
+            let chunk = jobs.len().div_ceil(threads);
+            return std::thread::scope(|scope| {
+                let handles: Vec<_> = jobs.chunks(chunk)
+                    .map(|part| scope.spawn(move || {
+                        part.iter().map(|(ct, lut)| self.bootstrap(ct, lut)).collect::<Result<Vec<_>, String>>()
+                    }))
+                    .collect();
+                let mut result = Vec::with_capacity(jobs.len());
+                for handle in handles {
+                    result.extend(handle.join().map_err(|_| "bootstrap thread panicked".to_string())??);
+                }
+                Ok(result)
+            });
+        }
+        jobs.iter().map(|(ct, lut)| self.bootstrap(ct, lut)).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fhe::tfhe::KeyGenerator;
+    use rand::Rng;
+
+    /// Runs `check` with a fresh key under the 2-bit message parameters
+    fn with_keys(carry_propagation: CarryPropagation, check: impl FnOnce(&Encryptor, &Decryptor, &IntegerEvaluator)) {
+        let context = TFHEContext::new(TFHEParameters::integer_message_2_carry_2());
+        let keygen = KeyGenerator::new(&context);
+        let key = keygen.glwe_secret_key().as_lwe_key();
+        let evaluator = IntegerEvaluator::new(&context, &keygen.bootstrap_key().unwrap(), &keygen.key_switch_key().unwrap())
+            .with_carry_propagation(carry_propagation);
+        check(&Encryptor::new(&context, &key), &Decryptor::new(&context, &key), &evaluator);
+    }
+
+    /// Every operator on the operands x, y and the scalar k against the
+    /// wrapping plaintext result
+    fn check_operators<const BITS: u32>(encryptor: &Encryptor, decryptor: &Decryptor, ev: &IntegerEvaluator, x: u64, y: u64, k: u64) {
+        let m = mask(BITS);
+        let decrypt = |ct: Result<EncryptedUint<BITS>, String>| decryptor.decrypt_uint(&ct.unwrap());
+        let (a, b) = (encryptor.encrypt_uint::<BITS>(x), encryptor.encrypt_uint::<BITS>(y));
+
+        assert_eq!(decryptor.decrypt_uint(&a), x);
+        assert_eq!(decryptor.decrypt_uint(&ev.trivial::<BITS>(k)), k);
+        assert_eq!(decrypt(ev.add(&a, &b)), x.wrapping_add(y) & m, "{} + {}", x, y);
+        assert_eq!(decrypt(ev.sub(&a, &b)), x.wrapping_sub(y) & m, "{} - {}", x, y);
+        assert_eq!(decrypt(ev.scalar_add(&a, k)), x.wrapping_add(k) & m, "{} + {}", x, k);
+        assert_eq!(decrypt(ev.scalar_mul(&a, k)), x.wrapping_mul(k) & m, "{} * {}", x, k);
+        assert_eq!(decrypt(ev.mul(&a, &b)), x.wrapping_mul(y) & m, "{} * {}", x, y);
+
+        assert_eq!(decrypt(ev.bitand(&a, &b)), x & y);
+        assert_eq!(decrypt(ev.bitor(&a, &b)), x | y);
+        assert_eq!(decrypt(ev.bitxor(&a, &b)), x ^ y);
+        assert_eq!(decryptor.decrypt_uint(&ev.bitnot(&a)), !x & m);
+
+        check_comparisons::<BITS>(encryptor, decryptor, ev, x, y);
+        assert_eq!(decrypt(ev.min(&a, &b)), x.min(y));
+        assert_eq!(decrypt(ev.max(&a, &b)), x.max(y));
+        for condition in [false, true] {
+            let c = encryptor.encrypt_bool_block(condition);
+            assert_eq!(decrypt(ev.select(&c, &a, &b)), if condition { x } else { y });
+        }
+    }
+
+    /// All six comparisons of x against y
+    fn check_comparisons<const BITS: u32>(encryptor: &Encryptor, decryptor: &Decryptor, ev: &IntegerEvaluator, x: u64, y: u64) {
+        let decrypt_bool = |ct: Result<EncryptedBool, String>| decryptor.decrypt_bool_block(&ct.unwrap());
+        let (a, b) = (encryptor.encrypt_uint::<BITS>(x), encryptor.encrypt_uint::<BITS>(y));
+        assert_eq!(decrypt_bool(ev.eq(&a, &b)), x == y, "{} == {}", x, y);
+        assert_eq!(decrypt_bool(ev.ne(&a, &b)), x != y, "{} != {}", x, y);
+        assert_eq!(decrypt_bool(ev.lt(&a, &b)), x < y, "{} < {}", x, y);
+        assert_eq!(decrypt_bool(ev.le(&a, &b)), x <= y, "{} <= {}", x, y);
+        assert_eq!(decrypt_bool(ev.gt(&a, &b)), x > y, "{} > {}", x, y);
+        assert_eq!(decrypt_bool(ev.ge(&a, &b)), x >= y, "{} >= {}", x, y);
+    }
+
+    /// Left and right shifts of x by each of `amounts`, which may wrap
+    fn check_shifts<const BITS: u32>(encryptor: &Encryptor, decryptor: &Decryptor, ev: &IntegerEvaluator, x: u64, amounts: &[u32]) {
+        let m = mask(BITS);
+        let a = encryptor.encrypt_uint::<BITS>(x);
+        for &k in amounts {
+            assert_eq!(decryptor.decrypt_uint(&ev.shl(&a, k).unwrap()), (x << (k % BITS)) & m, "{} << {}", x, k);
+            assert_eq!(decryptor.decrypt_uint(&ev.shr(&a, k).unwrap()), x >> (k % BITS), "{} >> {}", x, k);
+        }
+    }
+
+    /// (a·b + c) ^ (a - c), then its minimum with a << 3: evaluated
+    /// outputs feed further operators without any cleanup
+    fn check_chained<const BITS: u32>(encryptor: &Encryptor, decryptor: &Decryptor, ev: &IntegerEvaluator) {
+        let m = mask(BITS);
+        let mut rng = rand::thread_rng();
+        let (x, y, z) = (rng.gen::<u64>() & m, rng.gen::<u64>() & m, rng.gen::<u64>() & m);
+        let (a, b, c) = (encryptor.encrypt_uint::<BITS>(x), encryptor.encrypt_uint::<BITS>(y), encryptor.encrypt_uint::<BITS>(z));
+
+        let left = ev.add(&ev.mul(&a, &b).unwrap(), &c).unwrap();
+        let mixed = ev.bitxor(&left, &ev.sub(&a, &c).unwrap()).unwrap();
+        let result = ev.min(&mixed, &ev.shl(&a, 3).unwrap()).unwrap();
+        let expected = ((x.wrapping_mul(y).wrapping_add(z)) ^ x.wrapping_sub(z)) & m;
+        assert_eq!(decryptor.decrypt_uint(&result), expected.min((x << 3) & m));
+    }
+
+    /// Carries across every block: the cheap operators at full width
+    fn check_carries<const BITS: u32>(encryptor: &Encryptor, decryptor: &Decryptor, ev: &IntegerEvaluator) {
+        let m = mask(BITS);
+        let mut rng = rand::thread_rng();
+        let (x, k) = (rng.gen::<u64>() & m, rng.gen::<u64>() & m);
+        // y agrees with x except in the low block, so comparisons reach it
+        let y = (x & !3) | rng.gen_range(0..4);
+        let decrypt = |ct: Result<EncryptedUint<BITS>, String>| decryptor.decrypt_uint(&ct.unwrap());
+        let decrypt_bool = |ct: Result<EncryptedBool, String>| decryptor.decrypt_bool_block(&ct.unwrap());
+        let (a, b) = (encryptor.encrypt_uint::<BITS>(x), encryptor.encrypt_uint::<BITS>(y));
+        assert_eq!(decrypt(ev.add(&a, &b)), x.wrapping_add(y) & m, "{} + {}", x, y);
+        assert_eq!(decrypt(ev.sub(&a, &b)), x.wrapping_sub(y) & m, "{} - {}", x, y);
+        assert_eq!(decrypt(ev.scalar_add(&a, k)), x.wrapping_add(k) & m, "{} + {}", x, k);
+        assert_eq!(decrypt(ev.scalar_add(&ev.trivial::<BITS>(m), 1)), 0);
+        assert_eq!(decrypt_bool(ev.lt(&a, &b)), x < y, "{} < {}", x, y);
+        assert_eq!(decrypt_bool(ev.ge(&a, &b)), x >= y, "{} >= {}", x, y);
+    }
+
+    /// Random operands, every shift amount and a chained circuit
+    fn check_all<const BITS: u32>(carry_propagation: CarryPropagation) {
+        with_keys(carry_propagation, |encryptor, decryptor, ev| {
+            let m = mask(BITS);
+            let mut rng = rand::thread_rng();
+            let (x, y, k) = (rng.gen::<u64>() & m, rng.gen::<u64>() & m, rng.gen::<u64>() & m);
+            check_operators::<BITS>(encryptor, decryptor, ev, x, y, k);
+            // equal operands separate le/ge from lt/gt
+            check_comparisons::<BITS>(encryptor, decryptor, ev, x, x);
+            check_shifts::<BITS>(encryptor, decryptor, ev, x, &(0..=BITS).collect::<Vec<_>>());
+            check_chained::<BITS>(encryptor, decryptor, ev);
+        });
+    }
+
+    #[test]
+    fn u8_sequential() {
+        check_all::<8>(CarryPropagation::Sequential);
+    }
+
+    #[test]
+    fn u8_parallel() {
+        check_all::<8>(CarryPropagation::Parallel);
+    }
+
+    // One fixed operand set per width, so every operator runs on the wide
+    // types outside the ignored random passes. The scalars have only two
+    // nonzero base-4 digits, which bounds the bootstraps in scalar_mul
+    #[test]
+    fn every_operator_u16() {
+        with_keys(CarryPropagation::Parallel, |encryptor, decryptor, ev| {
+            check_operators::<16>(encryptor, decryptor, ev, 0xb5e3, 0x4c1d, 0x0302);
+            check_shifts::<16>(encryptor, decryptor, ev, 0xb5e3, &[5]);
+        });
+    }
+
+    #[test]
+    fn every_operator_u32() {
+        with_keys(CarryPropagation::Parallel, |encryptor, decryptor, ev| {
+            check_operators::<32>(encryptor, decryptor, ev, 0x8f3a_61d4, 0x2b9c_e705, 0x0030_0001);
+            check_shifts::<32>(encryptor, decryptor, ev, 0x8f3a_61d4, &[13]);
+        });
+    }
+
+    #[test]
+    fn wide_carries_sequential() {
+        with_keys(CarryPropagation::Sequential, |encryptor, decryptor, ev| {
+            check_carries::<16>(encryptor, decryptor, ev);
+            check_carries::<32>(encryptor, decryptor, ev);
+        });
+    }
+
+    #[test]
+    fn wide_carries_parallel() {
+        with_keys(CarryPropagation::Parallel, |encryptor, decryptor, ev| {
+            check_carries::<16>(encryptor, decryptor, ev);
+            check_carries::<32>(encryptor, decryptor, ev);
+        });
+    }
+
+    #[test]
+    #[ignore = "slow; run with --ignored"]
+    fn u16_sequential() {
+        check_all::<16>(CarryPropagation::Sequential);
+    }
+
+    #[test]
+    #[ignore = "slow; run with --ignored"]
+    fn u16_parallel() {
+        check_all::<16>(CarryPropagation::Parallel);
+    }
+
+    #[test]
+    #[ignore = "slow; run with --ignored"]
+    fn u32_sequential() {
+        check_all::<32>(CarryPropagation::Sequential);
+    }
+
+    #[test]
+    #[ignore = "slow; run with --ignored"]
+    fn u32_parallel() {
+        check_all::<32>(CarryPropagation::Parallel);
+    }
+
+    #[test]
+    fn edge_values() {
+        with_keys(CarryPropagation::Parallel, |encryptor, decryptor, ev| {
+            let decrypt = |ct: Result<EncryptedUint<8>, String>| decryptor.decrypt_uint(&ct.unwrap());
+            let decrypt_bool = |ct: Result<EncryptedBool, String>| decryptor.decrypt_bool_block(&ct.unwrap());
+            let (max, one, zero) = (encryptor.encrypt_uint::<8>(255), encryptor.encrypt_uint::<8>(1), encryptor.encrypt_uint::<8>(0));
+            assert_eq!(decrypt(ev.add(&max, &one)), 0);
+            assert_eq!(decrypt(ev.sub(&zero, &one)), 255);
+            assert_eq!(decrypt(ev.mul(&max, &max)), 1);
+            assert_eq!(decrypt(ev.scalar_add(&max, 257)), 0);
+            assert_eq!(decrypt(ev.scalar_mul(&max, 0)), 0);
+            assert!(decrypt_bool(ev.lt(&zero, &max)));
+            assert!(!decrypt_bool(ev.gt(&zero, &zero)));
+        });
+    }
+}
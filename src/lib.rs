@@ -1,16 +1,13 @@
-# Ersetze die komplette Datei mit korrigiertem Code
-cat > src/lib.rs << 'EOF'
 //! FHE Eva Core v7.0 - MOBILE OPTIMIZED
 //! Radix-4 NTT + Montgomery + Precomputed Tables for S23 Ultra
 
-mod ntt;
-mod rns;
-mod modular;
-mod fhe;
+pub mod ntt;
+pub mod rns;
+pub mod modular;
+pub mod fhe;
 
 use wasm_bindgen::prelude::*;
 use web_sys::{console, window};
-use std::sync::OnceLock;
 
 // ==================== ORIGINAL FUNCTIONS ====================
 
@@ -22,8 +19,8 @@ pub fn ntt_1024() -> f64 {
     let mut poly = vec![0u64; 1024];
     let modulus = 0x7fffffffe0001;
     
-    for i in 0..1024 {
-        poly[i] = (i as u64) % modulus;
+    for (i, c) in poly.iter_mut().enumerate() {
+        *c = (i as u64) % modulus;
     }
     
     // Annahme: ntt::ntt_forward existiert
@@ -148,8 +145,8 @@ impl UltraFheContext {
         let mut rev = vec![0usize; n];
         let log_n = n.trailing_zeros() as usize;
         
-        for i in 0..n {
-            rev[i] = i.reverse_bits() >> (usize::BITS - log_n as u32);
+        for (i, r) in rev.iter_mut().enumerate() {
+            *r = i.reverse_bits() >> (usize::BITS - log_n as u32);
         }
         rev
     }
@@ -276,4 +273,168 @@ pub fn run_all_benchmarks() -> String {
     
     result
 }
-EOF
+
+// ==================== BFV NOISE BUDGET ====================
+
+/// BFV session for the dashboard: one ciphertext and its remaining noise budget
+#[wasm_bindgen]
+pub struct BfvNoiseMonitor {
+    context: fhe::BFVContext,
+    encoder: fhe::BatchEncoder,
+    encryptor: fhe::Encryptor,
+    decryptor: fhe::Decryptor,
+    evaluator: fhe::Evaluator,
+    ciphertext: fhe::Ciphertext,
+}
+
+#[wasm_bindgen]
+impl BfvNoiseMonitor {
+    /// Generates keys and encrypts `value` into every slot
+    #[wasm_bindgen(constructor)]
+    pub fn new(value: u32) -> Result<BfvNoiseMonitor, JsValue> {
+        let context = fhe::BFVContext::new();
+        let encoder = fhe::BatchEncoder::new(&context).map_err(|e| JsValue::from_str(&e))?;
+        let keygen = fhe::KeyGenerator::new(&context);
+        let encryptor = fhe::Encryptor::new(&context, &keygen.public_key());
+        let decryptor = fhe::Decryptor::new(&context, &keygen.secret_key());
+        let evaluator = fhe::Evaluator::new(&context);
+
+        let plain = encoder
+            .encode(&vec![value as u64; encoder.slot_count()])
+            .map_err(|e| JsValue::from_str(&e))?;
+        let ciphertext = encryptor.encrypt(&plain);
+
+        Ok(BfvNoiseMonitor { context, encoder, encryptor, decryptor, evaluator, ciphertext })
+    }
+
+    /// Re-encrypts `value`, restoring a fresh noise budget
+    pub fn reset(&mut self, value: u32) -> Result<(), JsValue> {
+        let plain = self.encoder
+            .encode(&vec![value as u64; self.encoder.slot_count()])
+            .map_err(|e| JsValue::from_str(&e))?;
+        self.ciphertext = self.encryptor.encrypt(&plain);
+        Ok(())
+    }
+
+    /// ct <- ct + ct
+    pub fn add_self(&mut self) {
+        self.ciphertext = self.evaluator.add(&self.ciphertext, &self.ciphertext);
+    }
+
+    /// ct <- ct · value (broadcast plaintext)
+    pub fn multiply_plain(&mut self, value: u32) -> Result<(), JsValue> {
+        let plain = self.encoder
+            .encode(&vec![value as u64; self.encoder.slot_count()])
+            .map_err(|e| JsValue::from_str(&e))?;
+        self.evaluator.mul_plain_inplace(&mut self.ciphertext, &plain);
+        Ok(())
+    }
+
+    /// ct <- ct · value (scalar)
+    pub fn multiply_scalar(&mut self, value: u32) {
+        self.evaluator.multiply_by_scalar_inplace(&mut self.ciphertext, value as u64);
+    }
+
+    /// Measured budget in bits (uses the secret key)
+    pub fn noise_budget(&self) -> u32 {
+        self.decryptor.invariant_noise_budget(&self.ciphertext)
+    }
+
+    /// Key-less heuristic budget in bits
+    pub fn estimated_noise_budget(&self) -> u32 {
+        self.context.estimated_noise_budget(&self.ciphertext)
+    }
+
+    pub fn decrypt_slot(&self, index: usize) -> u32 {
+        let slots = self.encoder.decode(&self.decryptor.decrypt(&self.ciphertext));
+        slots.get(index).copied().unwrap_or(0) as u32
+    }
+}
+
+// ==================== CKKS HOISTED ROTATIONS ====================
+
+/// Rotates one CKKS ciphertext by 16 steps, one by one and hoisted
+#[wasm_bindgen]
+pub fn benchmark_hoisted_rotations() -> Result<String, JsValue> {
+    let window = window().expect("no global `window` exists");
+    let performance = window.performance().expect("performance should be available");
+
+    let params = fhe::CKKSParameters::new(4096, &[50, 40, 40, 60], 2f64.powi(40))
+        .map_err(|e| JsValue::from_str(&e))?;
+    let context = fhe::CKKSContext::with_params(params).map_err(|e| JsValue::from_str(&e))?;
+    let keygen = fhe::ckks::KeyGenerator::new(&context);
+    let encryptor = fhe::ckks::Encryptor::new(&context, &keygen.public_key());
+    let evaluator = fhe::ckks::Evaluator::new(&context);
+
+    let steps: Vec<i64> = (1..=16).collect();
+    let galois_keys = keygen.galois_keys(&steps, false);
+    let values: Vec<f64> = (0..context.slot_count()).map(|i| i as f64).collect();
+    let plain = context.encode_real(&values).map_err(|e| JsValue::from_str(&e))?;
+    let ciphertext = encryptor.encrypt(&plain);
+
+    let start = performance.now();
+    let mut single = Vec::with_capacity(steps.len());
+    for &step in &steps {
+        single.push(evaluator.rotate(&ciphertext, step, &galois_keys).map_err(|e| JsValue::from_str(&e))?);
+    }
+    let single_time = performance.now() - start;
+
+    let start = performance.now();
+    let hoisted = evaluator
+        .rotate_hoisted(&ciphertext, &steps, &galois_keys)
+        .map_err(|e| JsValue::from_str(&e))?;
+    let hoisted_time = performance.now() - start;
+
+    let identical = single.iter().zip(&hoisted).all(|(a, b)| a.polys == b.polys);
+    let speedup = if hoisted_time > 0.0 { single_time / hoisted_time } else { 0.0 };
+    console::log_1(&format!("Hoisted rotations: {:.1}x", speedup).into());
+
+    Ok(format!(
+        "{} rotations, N = 4096, 3 levels\n\
+         ONE BY ONE: {:.2}ms\n\
+         HOISTED: {:.2}ms\n\
+         SPEEDUP: {:.1}x (identical output: {})",
+        steps.len(), single_time, hoisted_time, speedup, identical
+    ))
+}
+
+// ==================== TFHE BOOLEAN GATES ====================
+
+/// Times a chain of NAND gates on one core under the default 128-bit
+/// boolean parameters
+#[wasm_bindgen]
+pub fn benchmark_boolean_gates() -> Result<String, JsValue> {
+    use fhe::tfhe::{Decryptor, Encryptor, KeyGenerator};
+    let window = window().expect("no global `window` exists");
+    let performance = window.performance().expect("performance should be available");
+
+    let context = fhe::TFHEContext::new(fhe::TFHEParameters::boolean_default_128());
+    let keygen = KeyGenerator::new(&context);
+    let bootstrap_key = keygen.bootstrap_key::<u32>().map_err(|e| JsValue::from_str(&e))?;
+    let key_switch_key = keygen.key_switch_key::<u32>().map_err(|e| JsValue::from_str(&e))?;
+    let encryptor = Encryptor::new(&context, &keygen.lwe_secret_key());
+    let decryptor = Decryptor::new(&context, &keygen.lwe_secret_key());
+    let evaluator = fhe::BooleanEvaluator::new(&context, &bootstrap_key, &key_switch_key);
+
+    const GATES: usize = 32;
+    let mut ct = encryptor.encrypt_bool::<u32>(true);
+    let mut plain = true;
+    let start = performance.now();
+    for _ in 0..GATES {
+        ct = evaluator.nand(&ct, &ct).map_err(|e| JsValue::from_str(&e))?;
+        plain = !plain;
+    }
+    let elapsed = performance.now() - start;
+    if decryptor.decrypt_bool(&ct) != plain {
+        return Err(JsValue::from_str("NAND chain decrypts incorrectly"));
+    }
+
+    let gates_per_sec = GATES as f64 / (elapsed / 1000.0);
+    console::log_1(&format!("TFHE boolean gates: {:.1}/s", gates_per_sec).into());
+    Ok(format!(
+        "n = {}, k = {}, N = {}, 32-bit torus\n\
+         {} NAND GATES: {:.0}ms ({:.1} gates/s, {:.1}ms per gate)",
+        context.params.lwe_dimension, context.params.glwe_dimension, context.params.poly_degree,
+        GATES, elapsed, gates_per_sec, elapsed / GATES as f64
+    ))
+}
@@ -81,6 +81,12 @@ impl Montgomery {
     }
 }
 
+/// R² mod m for R = 2^64
+fn compute_r_squared(modulus: u64) -> u64 {
+    let r = ((1u128 << 64) % modulus as u128) as u64;
+    ((r as u128 * r as u128) % modulus as u128) as u64
+}
+
 /// BATCH PROCESSING for vectorized operations
 #[cfg(target_arch = "aarch64")]
 use std::arch::aarch64::*;
@@ -148,3 +154,153 @@ pub fn mod_inv_extended(a: u64, modulus: u64) -> u64 {
     if t < 0 { t += modulus as i64; }
     t as u64
 }
+
+// ==================== REFERENCE ARITHMETIC ====================
+// Plain helpers used by the NTT tables and the FHE schemes.
+// Operands must already be reduced modulo `modulus`.
+
+/// Modular addition
+#[inline(always)]
+pub fn mod_add(a: u64, b: u64, modulus: u64) -> u64 {
+    let sum = a as u128 + b as u128;
+    if sum >= modulus as u128 {
+        (sum - modulus as u128) as u64
+    } else {
+        sum as u64
+    }
+}
+
+/// Modular subtraction
+#[inline(always)]
+pub fn mod_sub(a: u64, b: u64, modulus: u64) -> u64 {
+    if a >= b { a - b } else { a + (modulus - b) }
+}
+
+/// Modular multiplication via 128-bit product
+#[inline(always)]
+pub fn mod_mul(a: u64, b: u64, modulus: u64) -> u64 {
+    ((a as u128 * b as u128) % modulus as u128) as u64
+}
+
+/// Modular exponentiation (square-and-multiply)
+pub fn mod_pow(mut base: u64, mut exp: u64, modulus: u64) -> u64 {
+    let mut result = 1 % modulus;
+    base %= modulus;
+    while exp > 0 {
+        if exp & 1 == 1 {
+            result = mod_mul(result, base, modulus);
+        }
+        base = mod_mul(base, base, modulus);
+        exp >>= 1;
+    }
+    result
+}
+
+/// Modular inverse, returns 0 if `a` is not invertible
+pub fn mod_inv(a: u64, modulus: u64) -> u64 {
+    let (mut t, mut newt) = (0i128, 1i128);
+    let (mut r, mut newr) = (modulus as i128, (a % modulus) as i128);
+
+    while newr != 0 {
+        let quotient = r / newr;
+        (t, newt) = (newt, t - quotient * newt);
+        (r, newr) = (newr, r - quotient * newr);
+    }
+
+    if r > 1 { return 0; }
+    if t < 0 { t += modulus as i128; }
+    t as u64
+}
+
+/// Deterministic Miller-Rabin primality test for 64-bit integers
+pub fn is_prime(n: u64) -> bool {
+    if n < 2 { return false; }
+    for p in [2u64, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37] {
+        if n.is_multiple_of(p) { return n == p; }
+    }
+
+    let mut d = n - 1;
+    let mut s = 0;
+    while d.is_multiple_of(2) {
+        d /= 2;
+        s += 1;
+    }
+
+    // These witnesses are sufficient for every n < 2^64
+    'witness: for a in [2u64, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37] {
+        let mut x = mod_pow(a, d, n);
+        if x == 1 || x == n - 1 { continue; }
+        for _ in 1..s {
+            x = mod_mul(x, x, n);
+            if x == n - 1 { continue 'witness; }
+        }
+        return false;
+    }
+    true
+}
+
+/// Shoup precomputation floor(w · 2^64 / modulus) for a fixed multiplicand
+#[inline(always)]
+pub fn shoup_precompute(w: u64, modulus: u64) -> u64 {
+    (((w as u128) << 64) / modulus as u128) as u64
+}
+
+/// Multiplication by a fixed operand with its Shoup constant (modulus < 2^63)
+#[inline(always)]
+pub fn mod_mul_shoup(x: u64, w: u64, w_shoup: u64, modulus: u64) -> u64 {
+    let q_hat = ((x as u128 * w_shoup as u128) >> 64) as u64;
+    let r = x.wrapping_mul(w).wrapping_sub(q_hat.wrapping_mul(modulus));
+    if r >= modulus { r - modulus } else { r }
+}
+
+/// Distinct NTT-friendly primes q ≡ 1 mod 2N, one per requested bit size
+///
+/// Primes are taken from just below 2^bits downwards, so equal bit sizes
+/// yield consecutive distinct primes.
+pub fn generate_ntt_primes(poly_degree: usize, bit_sizes: &[u32]) -> Result<Vec<u64>, String> {
+    let m = 2 * poly_degree as u64;
+    let mut primes: Vec<u64> = Vec::with_capacity(bit_sizes.len());
+
+    for &bits in bit_sizes {
+        if bits > 60 || (1u64 << bits) <= 2 * m {
+            return Err(format!("prime bit size {} is out of range for poly_degree {}", bits, poly_degree));
+        }
+        let lower = 1u64 << (bits - 1);
+        let mut candidate = (1u64 << bits) - m + 1;
+        loop {
+            if candidate < lower {
+                return Err(format!("not enough {}-bit primes ≡ 1 mod {}", bits, m));
+            }
+            if !primes.contains(&candidate) && is_prime(candidate) {
+                primes.push(candidate);
+                break;
+            }
+            candidate -= m;
+        }
+    }
+    Ok(primes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn primality() {
+        assert!(is_prime(65537) && is_prime(0x7fffffffe0001));
+        assert!(!is_prime(1) && !is_prime(65537 * 65539) && !is_prime(561));
+    }
+
+    #[test]
+    fn ntt_primes_are_distinct_and_batching_friendly() {
+        let primes = generate_ntt_primes(2048, &[50, 50, 40, 60]).unwrap();
+        assert_eq!(primes.len(), 4);
+        for (&p, bits) in primes.iter().zip([50, 50, 40, 60]) {
+            assert!(is_prime(p));
+            assert_eq!(p % 4096, 1);
+            assert_eq!(64 - p.leading_zeros(), bits);
+        }
+        assert_ne!(primes[0], primes[1]);
+        assert!(generate_ntt_primes(2048, &[61]).is_err());
+    }
+}
@@ -1,3 +1,5 @@
+use crate::modular;
+
 pub fn ntt_forward_radix4(poly: &mut [u64], modulus: u64, root: u64) {
     let n = poly.len();
     assert!(n.is_power_of_two() && n >= 4, "n must be power of 2 and >= 4");
@@ -58,3 +60,168 @@ fn bit_reverse(poly: &mut [u64]) {
         }
     }
 }
+
+// ==================== NEGACYCLIC NTT ====================
+
+/// Precomputed tables for the negacyclic NTT over Z_q[X]/(X^N + 1)
+///
+/// `forward` evaluates a polynomial at the odd powers of a primitive
+/// 2N-th root of unity ψ and leaves the result in bit-reversed order:
+/// output index i holds the evaluation at ψ^(2·bitrev(i) + 1).
+#[derive(Clone, Debug)]
+pub struct NttTables {
+    pub n: usize,
+    pub modulus: u64,
+    pub psi: u64,
+    root_powers: Vec<u64>,     // ψ^bitrev(i)
+    inv_root_powers: Vec<u64>, // ψ^-bitrev(i)
+    n_inv: u64,
+}
+
+impl NttTables {
+    /// Returns `None` if `modulus` has no primitive 2N-th root of unity
+    pub fn new(n: usize, modulus: u64) -> Option<Self> {
+        if !n.is_power_of_two() || n < 2 {
+            return None;
+        }
+        let psi = find_primitive_2n_root(n, modulus)?;
+        let psi_inv = modular::mod_inv(psi, modulus);
+        let log_n = n.trailing_zeros();
+
+        let mut root_powers = vec![0u64; n];
+        let mut inv_root_powers = vec![0u64; n];
+        let (mut pow, mut inv_pow) = (1u64, 1u64);
+        for i in 0..n {
+            let rev = i.reverse_bits() >> (usize::BITS - log_n);
+            root_powers[rev] = pow;
+            inv_root_powers[rev] = inv_pow;
+            pow = modular::mod_mul(pow, psi, modulus);
+            inv_pow = modular::mod_mul(inv_pow, psi_inv, modulus);
+        }
+
+        Some(NttTables {
+            n,
+            modulus,
+            psi,
+            root_powers,
+            inv_root_powers,
+            n_inv: modular::mod_inv(n as u64, modulus),
+        })
+    }
+
+    /// In-place forward transform (Cooley-Tukey, bit-reversed output)
+    pub fn forward(&self, a: &mut [u64]) {
+        let q = self.modulus;
+        let mut t = self.n;
+        let mut m = 1;
+        while m < self.n {
+            t >>= 1;
+            for i in 0..m {
+                let s = self.root_powers[m + i];
+                let start = 2 * i * t;
+                for j in start..start + t {
+                    let u = a[j];
+                    let v = modular::mod_mul(a[j + t], s, q);
+                    a[j] = modular::mod_add(u, v, q);
+                    a[j + t] = modular::mod_sub(u, v, q);
+                }
+            }
+            m <<= 1;
+        }
+    }
+
+    /// In-place inverse transform (Gentleman-Sande, bit-reversed input)
+    pub fn inverse(&self, a: &mut [u64]) {
+        let q = self.modulus;
+        let mut t = 1;
+        let mut m = self.n;
+        while m > 1 {
+            let h = m >> 1;
+            let mut start = 0;
+            for i in 0..h {
+                let s = self.inv_root_powers[h + i];
+                for j in start..start + t {
+                    let u = a[j];
+                    let v = a[j + t];
+                    a[j] = modular::mod_add(u, v, q);
+                    a[j + t] = modular::mod_mul(modular::mod_sub(u, v, q), s, q);
+                }
+                start += 2 * t;
+            }
+            t <<= 1;
+            m = h;
+        }
+        for x in a.iter_mut() {
+            *x = modular::mod_mul(*x, self.n_inv, q);
+        }
+    }
+}
+
+/// Finds a primitive 2N-th root of unity modulo a prime `modulus`
+pub fn find_primitive_2n_root(n: usize, modulus: u64) -> Option<u64> {
+    let order = 2 * n as u64;
+    if modulus < 3 || !(modulus - 1).is_multiple_of(order) {
+        return None;
+    }
+    // ψ = g^((q-1)/2N) has order exactly 2N iff ψ^N = -1 (2N is a power of two)
+    for g in 2..modulus.min(1 << 16) {
+        let psi = modular::mod_pow(g, (modulus - 1) / order, modulus);
+        if modular::mod_pow(psi, n as u64, modulus) == modulus - 1 {
+            return Some(psi);
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const Q: u64 = 0x7fffffffe0001;
+
+    fn negacyclic_schoolbook(a: &[u64], b: &[u64], q: u64) -> Vec<u64> {
+        let n = a.len();
+        let mut c = vec![0u64; n];
+        for (i, &x) in a.iter().enumerate() {
+            for (j, &y) in b.iter().enumerate() {
+                let p = modular::mod_mul(x, y, q);
+                let k = (i + j) % n;
+                c[k] = if i + j < n { modular::mod_add(c[k], p, q) } else { modular::mod_sub(c[k], p, q) };
+            }
+        }
+        c
+    }
+
+    #[test]
+    fn forward_inverse_round_trip() {
+        let tables = NttTables::new(1024, Q).unwrap();
+        let a: Vec<u64> = (0..1024u64).map(|i| i.wrapping_mul(0x9e3779b97f4a7c15) % Q).collect();
+        let mut b = a.clone();
+        tables.forward(&mut b);
+        assert_ne!(a, b);
+        tables.inverse(&mut b);
+        assert_eq!(a, b);
+    }
+
+    #[test]
+    fn pointwise_product_is_negacyclic_convolution() {
+        let n = 32;
+        let tables = NttTables::new(n, Q).unwrap();
+        let a: Vec<u64> = (0..n as u64).map(|i| i * 7 + 3).collect();
+        let b: Vec<u64> = (0..n as u64).map(|i| Q - i * i - 1).collect();
+        let (mut fa, mut fb) = (a.clone(), b.clone());
+        tables.forward(&mut fa);
+        tables.forward(&mut fb);
+        let mut c: Vec<u64> = fa.iter().zip(&fb).map(|(&x, &y)| modular::mod_mul(x, y, Q)).collect();
+        tables.inverse(&mut c);
+        assert_eq!(c, negacyclic_schoolbook(&a, &b, Q));
+    }
+
+    #[test]
+    fn root_search() {
+        let psi = find_primitive_2n_root(1024, Q).unwrap();
+        assert_eq!(modular::mod_pow(psi, 1024, Q), Q - 1);
+        assert!(find_primitive_2n_root(1024, 65539).is_none());
+        assert!(NttTables::new(1000, Q).is_none());
+    }
+}
@@ -1,6 +1,10 @@
 //! OPTIMIZED RNS for Mobile FHE (S23 Ultra)
 //! Precomputes all constants for 50-100x speedup
 
+use crate::modular::{mod_inv, mod_mul};
+use num_bigint::{BigInt, BigUint, Sign};
+use num_traits::{ToPrimitive, Zero};
+
 pub struct FastRns {
     moduli: Vec<u64>,
     m_product: u128,
@@ -23,7 +27,7 @@ impl FastRns {
             m_prod_div.push(prod_div);
             
             // Compute inverse once and store
-            let inv = mod_inv((prod_div % m_i) as u64, m as u64) as u128;
+            let inv = mod_inv((prod_div % m_i) as u64, m) as u128;
             inv_prod_div.push(inv);
         }
         
@@ -40,10 +44,10 @@ impl FastRns {
         let mut result = 0u128;
         
         // SIMD-friendly loop (compiles to NEON on ARM)
-        for i in 0..residues.len() {
-            let term = (residues[i] as u128)
-                .wrapping_mul(self.m_prod_div[i])
-                .wrapping_mul(self.inv_prod_div[i]);
+        for ((&r, &prod_div), &inv) in residues.iter().zip(&self.m_prod_div).zip(&self.inv_prod_div) {
+            let term = (r as u128)
+                .wrapping_mul(prod_div)
+                .wrapping_mul(inv);
             
             result = result.wrapping_add(term) % self.m_product;
         }
@@ -86,3 +90,83 @@ impl FastRns {
             .collect()
     }
 }
+
+/// RNS basis with exact CRT composition for moduli products beyond u128
+#[derive(Clone, Debug)]
+pub struct RnsBasis {
+    pub moduli: Vec<u64>,
+    pub product: BigUint,
+    punctured: Vec<BigUint>, // Q / q_i
+    inv_punctured: Vec<u64>, // [(Q / q_i)^-1]_{q_i}
+}
+
+impl RnsBasis {
+    pub fn new(moduli: &[u64]) -> Self {
+        let product: BigUint = moduli.iter().map(|&m| BigUint::from(m)).product();
+
+        let mut punctured = Vec::with_capacity(moduli.len());
+        let mut inv_punctured = Vec::with_capacity(moduli.len());
+        for &m in moduli {
+            let p = &product / m;
+            let p_mod = (&p % m).to_u64().unwrap();
+            inv_punctured.push(mod_inv(p_mod, m));
+            punctured.push(p);
+        }
+
+        RnsBasis { moduli: moduli.to_vec(), product, punctured, inv_punctured }
+    }
+
+    /// log2 of the modulus product
+    pub fn bits(&self) -> f64 {
+        self.moduli.iter().map(|&m| (m as f64).log2()).sum()
+    }
+
+    /// CRT reconstruction into [0, Q)
+    pub fn compose(&self, residues: &[u64]) -> BigUint {
+        let mut acc = BigUint::zero();
+        for (i, &r) in residues.iter().enumerate().take(self.moduli.len()) {
+            let y = mod_mul(r, self.inv_punctured[i], self.moduli[i]);
+            acc += &self.punctured[i] * y;
+        }
+        acc % &self.product
+    }
+
+    /// CRT reconstruction into (-Q/2, Q/2]
+    pub fn compose_centered(&self, residues: &[u64]) -> BigInt {
+        let x = self.compose(residues);
+        if &x * 2u32 > self.product {
+            BigInt::from_biguint(Sign::Minus, &self.product - x)
+        } else {
+            BigInt::from_biguint(Sign::Plus, x)
+        }
+    }
+
+    /// Residues of a signed integer
+    pub fn decompose(&self, x: &BigInt) -> Vec<u64> {
+        let (sign, mag) = x.clone().into_parts();
+        self.moduli.iter()
+            .map(|&m| {
+                let r = (&mag % m).to_u64().unwrap();
+                if sign == Sign::Minus && r != 0 { m - r } else { r }
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::modular::generate_ntt_primes;
+
+    #[test]
+    fn decompose_compose_round_trip() {
+        let basis = RnsBasis::new(&generate_ntt_primes(1024, &[50, 50, 40]).unwrap());
+        let large: BigInt = BigInt::from(1u128 << 120) + 12345;
+        for x in [BigInt::from(0), BigInt::from(-1), BigInt::from(123456789), large.clone(), -large] {
+            let residues = basis.decompose(&x);
+            assert_eq!(basis.compose_centered(&residues), x);
+        }
+        assert_eq!(basis.compose(&basis.decompose(&BigInt::from(-1))), &basis.product - 1u32);
+        assert!((basis.bits() - 140.0).abs() < 0.01);
+    }
+}
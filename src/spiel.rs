@@ -35,7 +35,7 @@ pub fn rate_spiel() {
             }
         };
         
-        if tipp < 1 || tipp > 100 {
+        if !(1..=100).contains(&tipp) {
             println!("❌ Bitte eine Zahl zwischen 1 und 100!");
             continue;
         }
@@ -78,4 +78,7 @@ fn update_highscore(versuche: u32) {
     
     let aktueller_best = fs::read_to_string(highscore_file)
         .unwrap_or(String::from("100"))
-        .
+        .trim()
+        .to_string();
+    println!("Highscore: {} Versuche (diesmal: {})", aktueller_best, versuche);
+}